@@ -8,26 +8,84 @@
 //!
 //! The AST is based on the [interaction calculus].
 //!
+//! With the `serde` feature, [`Book`], [`Net`] and [`Tree`] can be serialized;
+//! the JSON schema is documented in `serde.rs`.
+//!
 //! [interaction calculus]: https://en.wikipedia.org/wiki/Interaction_nets#Interaction_calculus
 #![cfg_attr(not(feature = "std"), no_std)]
 
+mod canonical;
+#[cfg(feature = "std")]
+mod import;
+mod labels;
+mod lambda;
+mod literals;
 #[cfg(feature = "parser")]
 mod parser;
+mod pretty;
+#[cfg(feature = "serde")]
+mod serde;
+
+#[cfg(feature = "macros")]
+pub use hvm64_derive::{book, net};
+#[cfg(feature = "std")]
+pub use import::ImportError;
+pub use lambda::{Program, Term};
+pub use literals::{decode_list, encode_list, Encoding};
+#[cfg(feature = "parser")]
+pub use parser::ParseOpts;
+pub use pretty::PrettyOpts;
 
 use alloc::collections::BTreeMap;
 
 use hvm64_num::Num;
 use hvm64_util::{create_var, deref_to, maybe_grow, multi_iterator, prelude::*, var_to_num};
 
+#[cfg(feature = "serde")]
+use ::serde::{Deserialize, Serialize};
+
 pub type Lab = u16;
 
 /// The top level AST node, representing a collection of named nets.
 ///
-/// This is a newtype wrapper around a `BTreeMap<String, Net>`, and is
-/// dereferencable to such.
+/// This is a wrapper around a `BTreeMap<String, Net>`, and is dereferencable
+/// to such.
 #[derive(Clone, Hash, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Book {
   pub nets: BTreeMap<String, Net>,
+  /// The comments before each net, one per line, including their leading
+  /// `//`s; e.g. `/// Adds two numbers.`.
+  ///
+  /// These are printed before the nets, and are kept by the transform passes.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub docs: BTreeMap<String, String>,
+  /// The books this book imports, which have not been resolved yet; see
+  /// `Book::resolve_imports`.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub imports: Vec<Import>,
+  /// The names of the named labels in this book, such as `#dup_x{a b}`, and
+  /// the tags they were interned to (the label of a combinator is its tag
+  /// times two, plus one if it uses `{}`).
+  ///
+  /// These are used instead of the tags when printing the book.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub labels: BTreeMap<String, Lab>,
+}
+
+/// An `@import "path" as ns` directive, which includes the nets of the book at
+/// `path` under the namespace `ns`, i.e. with their names prefixed with `ns/`.
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Import {
+  /// The path of the imported file, relative to the directory of the importing
+  /// file.
+  pub path: String,
+  pub ns: String,
+  /// The comments before the import, as with [`Book::docs`]; empty if there
+  /// are none.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub doc: String,
 }
 
 deref_to!(Book => self.nets: BTreeMap<String, Net>);
@@ -40,6 +98,7 @@ deref_to!(Book => self.nets: BTreeMap<String, Net>);
 /// (The wiring connecting the leaves of all the trees is represented within the
 /// trees via pairs of [`Tree::Var`] nodes with the same name.)
 #[derive(Clone, Hash, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Net {
   pub root: Tree,
   pub redexes: Vec<(Tree, Tree)>,
@@ -61,8 +120,13 @@ pub enum Tree {
   /// A native number.
   Num(Num),
   /// A nilary node, referencing a named net.
+  ///
+  /// The name may contain alphanumeric characters, `_`, `.`, `$`, and `/`, and
+  /// is printed as-is.
   Ref(String),
-  /// A n-ary interaction combinator.
+  /// A binary interaction combinator.
+  ///
+  /// The syntax `(a b c)` is short for `(a (b c))`; see [`Tree::n_ary`].
   Ctr {
     /// The label of the combinator. (Combinators with the same label
     /// annihilate, and combinators with different labels commute.)
@@ -161,6 +225,14 @@ impl Tree {
     }
   }
 
+  /// Builds the n-ary combinator `(p0 p1 .. pn)` with label `lab`, which is
+  /// encoded as the nested binary combinators `(p0 (p1 .. pn))`.
+  ///
+  /// With one port, this returns that port; with none, an eraser.
+  pub fn n_ary(lab: Lab, ports: Vec<Tree>) -> Tree {
+    ports.into_iter().rev().reduce(|p2, p1| Tree::Ctr { lab, p1: Box::new(p1), p2: Box::new(p2) }).unwrap_or_default()
+  }
+
   /// Increases `fresh` until `create_var(*fresh)` does not conflict
   /// with a [`Tree::Var`]  in `tree`
   ///
@@ -176,6 +248,12 @@ impl Tree {
   }
 }
 
+impl From<Num> for Tree {
+  fn from(num: Num) -> Self {
+    Tree::Num(num)
+  }
+}
+
 // Manually implemented to avoid stack overflows.
 impl Clone for Tree {
   fn clone(&self) -> Tree {
@@ -218,46 +296,135 @@ impl Drop for Tree {
   }
 }
 
+/// Used by the expansions of [`net!`] and [`book!`].
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+  use super::*;
+
+  pub use hvm64_util::prelude::{vec, Box};
+
+  /// The number with the raw representation `raw`, which the macro got from a
+  /// [`Num`].
+  pub fn num(raw: u32) -> Tree {
+    Tree::Num(unsafe { Num::from_raw(raw) })
+  }
+
+  /// A Scott-encoded list of `items`, with vars named `.lit{n + 1}`,
+  /// `.lit{n + 2}`, etc., as the parser names them.
+  pub fn list(items: Vec<Tree>, mut n: usize) -> Tree {
+    encode_list(items, Encoding::Scott, &mut || {
+      n += 1;
+      format!(".lit{n}")
+    })
+  }
+}
+
 impl fmt::Display for Book {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for import in &self.imports {
+      writeln!(f, "{import}")?;
+    }
+    if !self.imports.is_empty() && !self.is_empty() {
+      writeln!(f)?;
+    }
+    let names = self.label_names();
     for (i, (name, net)) in self.iter().enumerate() {
       if i != 0 {
         f.write_str("\n\n")?;
       }
 
-      write!(f, "@{name} = {net}")?;
+      if let Some(doc) = self.docs.get(name) {
+        writeln!(f, "{doc}")?;
+      }
+      write!(f, "@{name} = {}", Printed { inner: net, names: &names, literals: None })?;
     }
     Ok(())
   }
 }
 
+impl fmt::Display for Import {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if !self.doc.is_empty() {
+      writeln!(f, "{}", self.doc)?;
+    }
+    f.write_str("@import ")?;
+    literals::write_string(f, &self.path)?;
+    write!(f, " as {}", self.ns)
+  }
+}
+
 impl fmt::Display for Net {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "{}", &self.root)?;
+    fmt::Display::fmt(&Printed { inner: self, names: &BTreeMap::new(), literals: None }, f)
+  }
+}
 
-    for (a, b) in &self.redexes {
-      write!(f, "\n  & {a} ~ {b}")?;
+impl fmt::Display for Tree {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(&Printed { inner: self, names: &BTreeMap::new(), literals: None }, f)
+  }
+}
+
+/// A [`Net`] or [`Tree`], displayed with the names of its labels, by their
+/// tags (see [`Book::labels`]), and optionally with its lists as literals.
+pub(crate) struct Printed<'a, T> {
+  pub inner: &'a T,
+  pub names: &'a BTreeMap<Lab, String>,
+  pub literals: Option<Encoding>,
+}
+
+impl<'a, T> Printed<'a, T> {
+  fn with<U>(&self, inner: &'a U) -> Printed<'a, U> {
+    Printed { inner, names: self.names, literals: self.literals }
+  }
+}
+
+impl fmt::Display for Printed<'_, Net> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.with(&self.inner.root))?;
+
+    for (a, b) in &self.inner.redexes {
+      write!(f, "\n  & {} ~ {}", self.with(a), self.with(b))?;
     }
 
     Ok(())
   }
 }
 
-impl fmt::Display for Tree {
+impl fmt::Display for Printed<'_, Tree> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    maybe_grow(move || match self {
+    if let Some(items) = self.literals.and_then(|encoding| literals::decode_literal(self.inner, encoding)) {
+      if let Some(string) = literals::decode_string(&items) {
+        return literals::write_string(f, &string);
+      }
+      f.write_str("[")?;
+      for (i, item) in items.into_iter().enumerate() {
+        if i != 0 {
+          f.write_str(" ")?;
+        }
+        write!(f, "{}", self.with(item))?;
+      }
+      return f.write_str("]");
+    }
+    maybe_grow(move || match self.inner {
       Tree::Era => write!(f, "*"),
-      Tree::Ctr { lab, p1, p2 } => match lab {
-        0 => write!(f, "({p1} {p2})"),
-        1 => write!(f, "{{{p1} {p2}}}"),
-        _ if lab % 2 == 0 => write!(f, "#{}({p1} {p2})", lab / 2),
-        _ => write!(f, "#{}{{{p1} {p2}}}", lab / 2),
-      },
+      &Tree::Ctr { lab, ref p1, ref p2 } => {
+        let (p1, p2) = (self.with(&**p1), self.with(&**p2));
+        match (lab, self.names.get(&(lab / 2))) {
+          (_, Some(name)) if lab % 2 == 0 => write!(f, "#{name}({p1} {p2})"),
+          (_, Some(name)) => write!(f, "#{name}{{{p1} {p2}}}"),
+          (0, _) => write!(f, "({p1} {p2})"),
+          (1, _) => write!(f, "{{{p1} {p2}}}"),
+          _ if lab % 2 == 0 => write!(f, "#{}({p1} {p2})", lab / 2),
+          _ => write!(f, "#{}{{{p1} {p2}}}", lab / 2),
+        }
+      }
       Tree::Var(name) => write!(f, "{name}"),
       Tree::Ref(name) => write!(f, "@{name}"),
       Tree::Num(val) => write!(f, "{val}"),
-      Tree::Op { rhs, out } => write!(f, "$({rhs} {out})"),
-      Tree::Switch { arms, out } => write!(f, "?({arms} {out})"),
+      Tree::Op { rhs, out } => write!(f, "$({} {})", self.with(&**rhs), self.with(&**out)),
+      Tree::Switch { arms, out } => write!(f, "?({} {})", self.with(&**arms), self.with(&**out)),
     })
   }
 }
@@ -284,3 +451,14 @@ fn test_tree_drop() {
   }
   drop(big_tree);
 }
+
+#[test]
+#[cfg(feature = "parser")]
+fn test_n_ary() {
+  use core::str::FromStr;
+
+  let tree = Tree::from_str("(a b c {d e f} #2(g h i))").unwrap();
+  assert_eq!(tree.to_string(), "(a (b (c ({d {e f}} #2(g #2(h i))))))");
+  assert_eq!(tree, Tree::from_str(&tree.to_string()).unwrap());
+  assert!(Tree::from_str("(a)").is_err());
+}
@@ -0,0 +1,184 @@
+//! Canonical forms of nets, and alpha-equivalence.
+
+use super::*;
+
+use core::cmp::Ordering;
+
+impl Net {
+  /// Puts this net into a canonical form.
+  ///
+  /// The redexes are sorted by their structure, ignoring variable names, and
+  /// the variables are then renamed to `a`, `b`, ... in the order in which they
+  /// first occur, traversing the root and then each redex depth-first, left to
+  /// right. This is also the order in which readback names variables.
+  ///
+  /// Nets which only differ in the naming of their variables and the order of
+  /// their redexes have the same canonical form, unless they contain redexes
+  /// with the same structure but different wiring; use [`Net::alpha_eq`] to
+  /// compare such nets.
+  pub fn canonicalize(&mut self) {
+    self.redexes.sort_by(|(a1, a2), (b1, b2)| cmp_shape(a1, b1).then_with(|| cmp_shape(a2, b2)));
+
+    let mut names = Map::new();
+    let mut stack = self.trees_mut().collect::<Vec<_>>();
+    stack.reverse();
+    while let Some(tree) = stack.pop() {
+      if let Tree::Var(name) = tree {
+        let next = create_var(names.len());
+        *name = names.entry(mem::take(name)).or_insert(next).clone();
+      }
+      stack.extend(tree.children_mut().rev());
+    }
+  }
+
+  /// Returns whether this net is equal to `other` up to the naming of its
+  /// variables and the order of its redexes.
+  ///
+  /// Redexes with the same structure are matched up by backtracking, which can
+  /// take exponential time in contrived cases.
+  pub fn alpha_eq(&self, other: &Net) -> bool {
+    let mut vars = VarMap::default();
+    self.redexes.len() == other.redexes.len()
+      && vars.tree_eq(&self.root, &other.root)
+      && vars.redexes_eq(&self.redexes, &other.redexes, &mut vec![false; other.redexes.len()])
+  }
+}
+
+/// Compares the structure of two trees, ignoring the names of variables.
+fn cmp_shape(a: &Tree, b: &Tree) -> Ordering {
+  fn index(tree: &Tree) -> u8 {
+    match tree {
+      Tree::Era => 0,
+      Tree::Num(_) => 1,
+      Tree::Ref(_) => 2,
+      Tree::Ctr { .. } => 3,
+      Tree::Op { .. } => 4,
+      Tree::Switch { .. } => 5,
+      Tree::Var(_) => 6,
+    }
+  }
+
+  let mut stack = vec![(a, b)];
+  while let Some((a, b)) = stack.pop() {
+    let ord = match (a, b) {
+      (Tree::Num(a), Tree::Num(b)) => a.cmp(b),
+      (Tree::Ref(a), Tree::Ref(b)) => a.cmp(b),
+      (Tree::Ctr { lab: a, .. }, Tree::Ctr { lab: b, .. }) => a.cmp(b),
+      _ => index(a).cmp(&index(b)),
+    };
+    if ord != Ordering::Equal {
+      return ord;
+    }
+    stack.extend(a.children().zip(b.children()).rev());
+  }
+  Ordering::Equal
+}
+
+/// A bijection between the variables of two nets, built up while comparing
+/// them.
+#[derive(Default)]
+struct VarMap<'a> {
+  fwd: Map<&'a str, &'a str>,
+  bwd: Map<&'a str, &'a str>,
+  /// The pairs of variables in the order they were added, so that they can be
+  /// removed when backtracking.
+  trail: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> VarMap<'a> {
+  fn tree_eq(&mut self, a: &'a Tree, b: &'a Tree) -> bool {
+    let mut stack = vec![(a, b)];
+    while let Some((a, b)) = stack.pop() {
+      match (a, b) {
+        (Tree::Var(a), Tree::Var(b)) => {
+          if !self.bind(a, b) {
+            return false;
+          }
+        }
+        _ if node_eq(a, b) => stack.extend(a.children().zip(b.children())),
+        _ => return false,
+      }
+    }
+    true
+  }
+
+  fn bind(&mut self, a: &'a str, b: &'a str) -> bool {
+    match (self.fwd.get(a), self.bwd.get(b)) {
+      (None, None) => {
+        self.fwd.insert(a, b);
+        self.bwd.insert(b, a);
+        self.trail.push((a, b));
+        true
+      }
+      (Some(&x), Some(&y)) => x == b && y == a,
+      _ => false,
+    }
+  }
+
+  fn undo(&mut self, len: usize) {
+    for (a, b) in self.trail.drain(len ..) {
+      self.fwd.remove(a);
+      self.bwd.remove(b);
+    }
+  }
+
+  /// Matches each redex in `a` with an unused redex in `b`.
+  fn redexes_eq(&mut self, a: &'a [(Tree, Tree)], b: &'a [(Tree, Tree)], used: &mut [bool]) -> bool {
+    let Some(((a1, a2), rest)) = a.split_first() else { return true };
+    maybe_grow(|| {
+      for (i, (b1, b2)) in b.iter().enumerate() {
+        if used[i] {
+          continue;
+        }
+        let len = self.trail.len();
+        if self.tree_eq(a1, b1) && self.tree_eq(a2, b2) {
+          used[i] = true;
+          if self.redexes_eq(rest, b, used) {
+            return true;
+          }
+          used[i] = false;
+        }
+        self.undo(len);
+      }
+      false
+    })
+  }
+}
+
+/// Whether the roots of two trees (that are not both variables) are equal.
+fn node_eq(a: &Tree, b: &Tree) -> bool {
+  match (a, b) {
+    (Tree::Era, Tree::Era) | (Tree::Op { .. }, Tree::Op { .. }) | (Tree::Switch { .. }, Tree::Switch { .. }) => true,
+    (Tree::Num(a), Tree::Num(b)) => a == b,
+    (Tree::Ref(a), Tree::Ref(b)) => a == b,
+    (Tree::Ctr { lab: a, .. }, Tree::Ctr { lab: b, .. }) => a == b,
+    _ => false,
+  }
+}
+
+#[test]
+#[cfg(feature = "parser")]
+fn test_canonicalize() {
+  let mut net: Net = "(x (y x)) & (* z) ~ y & @foo ~ $(w z) & 1 ~ w".parse().unwrap();
+  let other: Net = "(b (a b)) & @foo ~ $(d c) & (* c) ~ a & 1 ~ d".parse().unwrap();
+  assert!(net.alpha_eq(&other));
+  assert!(other.alpha_eq(&net));
+  net.canonicalize();
+  assert_eq!(net.to_string(), "(a (b a))\n  & 1 ~ c\n  & @foo ~ $(c d)\n  & (* d) ~ b");
+  assert!(net.alpha_eq(&other));
+
+  let unequal = [
+    "(a (b a)) & (* c) ~ b & @foo ~ $(d c) & 1 ~ c",
+    "(a (b b)) & (* c) ~ a & @foo ~ $(d c) & 1 ~ d",
+    "(a (b a)) & (* c) ~ b & @foo ~ $(d c) & 2 ~ d",
+    "(a (b a)) & (* c) ~ b & @foo ~ $(d c)",
+  ];
+  for unequal in unequal {
+    assert!(!net.alpha_eq(&unequal.parse().unwrap()), "{unequal}");
+  }
+
+  // redexes of the same structure are matched up by backtracking
+  let a: Net = "(a (b c)) & (x y) ~ a & (x y) ~ b".parse().unwrap();
+  let b: Net = "(a (b c)) & (x y) ~ b & (x y) ~ a".parse().unwrap();
+  assert!(a.alpha_eq(&b));
+}
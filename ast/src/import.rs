@@ -0,0 +1,127 @@
+//! Resolving `@import` directives, and merging books.
+
+use super::*;
+
+use std::{
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// An error resolving the imports of a book, or merging books.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+  /// An imported file could not be read or parsed; contains the error returned
+  /// by the `read` callback of [`Book::resolve_imports`].
+  Load(String),
+  /// A file imports itself; contains the chain of imported files, starting and
+  /// ending with that file.
+  Cycle(Vec<PathBuf>),
+  /// A net is defined in more than one of the merged books.
+  Duplicate(String),
+}
+
+impl fmt::Display for ImportError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ImportError::Load(e) => write!(f, "{e}"),
+      ImportError::Cycle(paths) => {
+        write!(f, "import cycle: ")?;
+        for (i, path) in paths.iter().enumerate() {
+          if i != 0 {
+            write!(f, " -> ")?;
+          }
+          write!(f, "{}", path.display())?;
+        }
+        Ok(())
+      }
+      ImportError::Duplicate(name) => write!(f, "duplicate definition of `@{name}`"),
+    }
+  }
+}
+
+impl Error for ImportError {}
+
+impl Book {
+  /// Resolves the imports of this book, which was read from the file at `path`.
+  ///
+  /// Each imported book has its own imports resolved, and is then merged into
+  /// this book, with the names of its nets (and the refs to them) prefixed with
+  /// its namespace. Import paths are relative to the directory of the importing
+  /// file. `read` reads and parses the book in a file; its errors should
+  /// mention the path.
+  pub fn resolve_imports(
+    &mut self,
+    path: &Path,
+    read: &mut dyn FnMut(&Path) -> Result<Book, String>,
+  ) -> Result<(), ImportError> {
+    self.resolve_imports_in(&mut vec![canonicalize(path)], read)
+  }
+
+  /// `stack` contains the canonical paths of the files being imported, ending
+  /// with the file this book was read from.
+  fn resolve_imports_in(
+    &mut self,
+    stack: &mut Vec<PathBuf>,
+    read: &mut dyn FnMut(&Path) -> Result<Book, String>,
+  ) -> Result<(), ImportError> {
+    let dir = stack.last().unwrap().parent().map(Path::to_owned).unwrap_or_default();
+    for import in mem::take(&mut self.imports) {
+      let path = dir.join(&import.path);
+      let canonical = canonicalize(&path);
+      if let Some(i) = stack.iter().position(|x| *x == canonical) {
+        let mut cycle = stack[i ..].to_vec();
+        cycle.push(canonical);
+        return Err(ImportError::Cycle(cycle));
+      }
+      let mut book = read(&path).map_err(ImportError::Load)?;
+      stack.push(canonical);
+      book.resolve_imports_in(stack, read)?;
+      stack.pop();
+      book.add_namespace(&import.ns);
+      self.merge(book)?;
+    }
+    Ok(())
+  }
+
+  /// Adds the nets of `other` to this book, failing if any of them are already
+  /// in it.
+  ///
+  /// Named labels are renumbered where they conflict with the labels of the
+  /// other book; named labels with the same name are merged.
+  pub fn merge(&mut self, mut other: Book) -> Result<(), ImportError> {
+    if let Some(name) = other.keys().find(|name| self.contains_key(*name)) {
+      return Err(ImportError::Duplicate(name.clone()));
+    }
+    self.merge_labels(&mut other);
+    self.nets.extend(other.nets);
+    self.docs.extend(other.docs);
+    self.imports.extend(other.imports);
+    Ok(())
+  }
+
+  /// Prefixes the names of the nets and labels in this book with `ns/`,
+  /// updating the refs to them.
+  fn add_namespace(&mut self, ns: &str) {
+    let names = self.keys().cloned().collect::<Set<_>>();
+    for net in self.values_mut() {
+      let mut stack = net.trees_mut().collect::<Vec<_>>();
+      while let Some(tree) = stack.pop() {
+        if let Tree::Ref(name) = tree {
+          if names.contains(name) {
+            *name = format!("{ns}/{name}");
+          }
+        }
+        stack.extend(tree.children_mut());
+      }
+    }
+    self.nets = mem::take(&mut self.nets).into_iter().map(|(name, net)| (format!("{ns}/{name}"), net)).collect();
+    self.docs = mem::take(&mut self.docs).into_iter().map(|(name, doc)| (format!("{ns}/{name}"), doc)).collect();
+    self.labels = mem::take(&mut self.labels).into_iter().map(|(name, tag)| (format!("{ns}/{name}"), tag)).collect();
+  }
+}
+
+/// Canonicalizes `path` if it exists, so that cycles can be detected.
+fn canonicalize(path: &Path) -> PathBuf {
+  fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
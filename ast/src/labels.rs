@@ -0,0 +1,113 @@
+//! Named labels, such as `#dup_x{a b}`.
+//!
+//! The parser interns each label name to a number that none of the numeric
+//! labels in the same input use; [`Book::labels`] stores the numbers, so that
+//! the book can be printed with the names again.
+
+use super::*;
+
+impl Book {
+  /// Gives the named labels of this book and `other` numbers that don't
+  /// conflict, so that the books can be merged.
+  ///
+  /// Named labels with the same name get the same number, and named labels with
+  /// different names, or numeric labels, keep different numbers. Afterwards,
+  /// the names of all the labels are in this book.
+  #[cfg(feature = "std")]
+  pub(crate) fn merge_labels(&mut self, other: &mut Book) {
+    let mut numeric = self.numeric_tags();
+    numeric.extend(other.numeric_tags());
+    let mut taken = numeric.clone();
+    taken.extend(self.labels.values().copied());
+    let mut labels = BTreeMap::new();
+    let mut self_map = Map::new();
+    for (name, &tag) in &self.labels {
+      let new = if numeric.contains(&tag) { fresh_tag(&taken) } else { tag };
+      taken.insert(new);
+      labels.insert(name.clone(), new);
+      self_map.insert(tag, new);
+    }
+    let mut other_map = Map::new();
+    for (name, &tag) in &other.labels {
+      let new = match labels.get(name) {
+        Some(&new) => new,
+        None if taken.contains(&tag) => fresh_tag(&taken),
+        None => tag,
+      };
+      taken.insert(new);
+      labels.insert(name.clone(), new);
+      other_map.insert(tag, new);
+    }
+    self.relabel(&self_map);
+    other.relabel(&other_map);
+    self.labels = labels;
+    other.labels.clear();
+  }
+
+  /// The tags of the labels in this book that don't have names, including `0`.
+  #[cfg(feature = "std")]
+  fn numeric_tags(&self) -> Set<Lab> {
+    let named = self.labels.values().collect::<Set<_>>();
+    let mut tags = Set::new();
+    tags.insert(0);
+    for net in self.values() {
+      let mut stack = net.trees().collect::<Vec<_>>();
+      while let Some(tree) = stack.pop() {
+        if let Tree::Ctr { lab, .. } = tree {
+          if !named.contains(&(lab / 2)) {
+            tags.insert(lab / 2);
+          }
+        }
+        stack.extend(tree.children());
+      }
+    }
+    tags
+  }
+
+  /// Changes the tag of each label in this book according to `map`.
+  #[cfg(feature = "std")]
+  fn relabel(&mut self, map: &Map<Lab, Lab>) {
+    if map.iter().all(|(old, new)| old == new) {
+      return;
+    }
+    for net in self.values_mut() {
+      let mut stack = net.trees_mut().collect::<Vec<_>>();
+      while let Some(tree) = stack.pop() {
+        if let Tree::Ctr { lab, .. } = tree {
+          if let Some(new) = map.get(&(*lab / 2)) {
+            *lab = new * 2 + *lab % 2;
+          }
+        }
+        stack.extend(tree.children_mut());
+      }
+    }
+  }
+
+  /// The names of the labels of this book, by their tags.
+  pub(crate) fn label_names(&self) -> BTreeMap<Lab, String> {
+    self.labels.iter().map(|(name, &tag)| (tag, name.clone())).collect()
+  }
+}
+
+/// The smallest tag that isn't in `taken`.
+#[cfg(any(feature = "parser", feature = "std"))]
+pub(crate) fn fresh_tag(taken: &Set<Lab>) -> Lab {
+  (1 ..= Lab::MAX / 2).find(|tag| !taken.contains(tag)).expect("ran out of labels")
+}
+
+#[test]
+#[cfg(all(feature = "parser", feature = "std"))]
+fn test_labels() {
+  let mut a: Book = "@a = (#x{b c} #1(b #y(c *)))".parse().unwrap();
+  assert_eq!(a.labels, [("x".to_owned(), 2), ("y".to_owned(), 3)].into());
+  assert_eq!(a.nets["a"].root.to_string(), "(#2{b c} #1(b #3(c *)))");
+  assert_eq!(a.to_string(), "@a = (#x{b c} #1(b #y(c *)))");
+
+  let b: Book = "@b = #y{#x(* *) #2{* *}}".parse().unwrap();
+  assert_eq!(b.labels, [("x".to_owned(), 3), ("y".to_owned(), 1)].into());
+  a.merge(b).unwrap();
+  assert_eq!(a.labels, [("x".to_owned(), 4), ("y".to_owned(), 3)].into());
+  assert_eq!(a.to_string(), "@a = (#x{b c} #1(b #y(c *)))\n\n@b = #y{#x(* *) #2{* *}}");
+  assert_eq!(a.nets["a"].root.to_string(), "(#4{b c} #1(b #3(c *)))");
+  assert_eq!(a.nets["b"].root.to_string(), "#3{#4(* *) #2{* *}}");
+}
@@ -0,0 +1,314 @@
+//! A front end for the untyped λ-calculus, which compiles λ-terms to nets.
+//!
+//! A [`Program`] is a collection of named terms, such as
+//! ```text
+//! // Church numerals
+//! c2 = λf λx (f (f x))
+//! main = let triple = λn (* n 3) (c2 triple 1)
+//! ```
+//! - `λx body` (or `\x body`) is a lambda. Its body is a single term, so
+//!   applying `λx x` to `y` is written `(λx x y)`.
+//! - `(f a b)` applies `f` to `a` and then `b`.
+//! - `let x = val body` binds `x` to `val` in `body`; it is not recursive.
+//! - numbers are written as in hvm64, and native operations as `(+ a b)`, with
+//!   any of the binary operators of hvm64 numbers.
+//! - `[a b c]` is a Scott-encoded list; see [`Encoding::Scott`].
+//! - names which aren't bound by a `λ` or a `let` refer to the other terms in
+//!   the program, and `@name` refers to any net.
+//!
+//! Each term is compiled to a net in the usual way: a lambda is a `(x body)`
+//! combinator, and an application `(f a)` connects `f` to `(a r)`, where `r`
+//! is the result. A variable which is used more than once is duplicated with a
+//! tree of `{}` combinators with a label that no other variable uses, and a
+//! variable which isn't used is erased.
+//!
+//! The labels are named after the def and the variable, such as `c2.f`, so
+//! that [`Book::merge`] keeps them apart from the labels of other books, such
+//! as those of the nets referred to with `@name`.
+
+use super::*;
+
+use hvm64_num::NumTag;
+
+#[cfg(feature = "parser")]
+mod parser;
+
+/// A λ-calculus program; see the [module docs](self).
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Default)]
+pub struct Program {
+  pub defs: BTreeMap<String, Term>,
+}
+
+/// A λ-term; see the [module docs](self).
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub enum Term {
+  /// A variable, or a reference to another term in the program.
+  Var(String),
+  /// A reference to a net.
+  Ref(String),
+  Lam {
+    var: String,
+    body: Box<Term>,
+  },
+  App {
+    fun: Box<Term>,
+    arg: Box<Term>,
+  },
+  Let {
+    var: String,
+    val: Box<Term>,
+    body: Box<Term>,
+  },
+  Num(Num),
+  /// A native operation on numbers.
+  Op {
+    op: NumTag,
+    lhs: Box<Term>,
+    rhs: Box<Term>,
+  },
+  List(Vec<Term>),
+}
+
+impl Term {
+  /// Returns the function and the arguments of a chain of applications, `(f a
+  /// b)`.
+  fn unapply(&self) -> (&Term, Vec<&Term>) {
+    let mut args = vec![];
+    let mut fun = self;
+    while let Term::App { fun: f, arg } = fun {
+      args.push(&**arg);
+      fun = f;
+    }
+    args.reverse();
+    (fun, args)
+  }
+}
+
+impl Program {
+  /// Compiles this program to a book, with a net for each term.
+  ///
+  /// Fails if a term uses an unbound variable.
+  pub fn to_book(&self) -> Result<Book, String> {
+    let mut book = Book::default();
+    for (name, term) in &self.defs {
+      let mut compiler =
+        Compiler { defs: &self.defs, def: name, labels: &mut book.labels, vars: 0, scope: vec![], redexes: vec![] };
+      let root = compiler.term(term).map_err(|e| format!("in `{name}`: {e}"))?;
+      let mut net = Net { root, redexes: compiler.redexes };
+      net.substitute_vars();
+      net.canonicalize();
+      book.nets.insert(name.clone(), net);
+    }
+    Ok(book)
+  }
+}
+
+struct Compiler<'a> {
+  defs: &'a BTreeMap<String, Term>,
+  /// The name of the def being compiled.
+  def: &'a str,
+  /// The labels used so far, in the whole program.
+  labels: &'a mut BTreeMap<String, Lab>,
+  vars: usize,
+  /// The variables in scope, innermost last, with the vars their uses are
+  /// connected to.
+  scope: Vec<(String, Vec<String>)>,
+  redexes: Vec<(Tree, Tree)>,
+}
+
+impl Compiler<'_> {
+  /// Returns the tree the value of `term` is connected to.
+  fn term(&mut self, term: &Term) -> Result<Tree, String> {
+    maybe_grow(move || match term {
+      Term::Var(name) => match self.scope.iter().rposition(|(var, _)| var == name) {
+        Some(i) => {
+          let var = self.var();
+          self.scope[i].1.push(var.clone());
+          Ok(Tree::Var(var))
+        }
+        None if self.defs.contains_key(name) => Ok(Tree::Ref(name.clone())),
+        None => Err(format!("unbound variable `{name}`")),
+      },
+      Term::Ref(name) => Ok(Tree::Ref(name.clone())),
+      Term::Lam { var, body } => {
+        self.scope.push((var.clone(), vec![]));
+        let body = self.term(body)?;
+        let var = self.bind()?;
+        Ok(Tree::Ctr { lab: 0, p1: Box::new(var), p2: Box::new(body) })
+      }
+      Term::App { .. } => {
+        let (fun, args) = term.unapply();
+        let fun = self.term(fun)?;
+        let mut ports = args.into_iter().map(|arg| self.term(arg)).collect::<Result<Vec<_>, _>>()?;
+        let out = self.var();
+        ports.push(Tree::Var(out.clone()));
+        self.redexes.push((fun, Tree::n_ary(0, ports)));
+        Ok(Tree::Var(out))
+      }
+      Term::Let { var, val, body } => {
+        let val = self.term(val)?;
+        self.scope.push((var.clone(), vec![]));
+        let body = self.term(body)?;
+        let var = self.bind()?;
+        self.redexes.push((var, val));
+        Ok(body)
+      }
+      Term::Num(num) => Ok(Tree::Num(*num)),
+      Term::Op { op, lhs, rhs } => {
+        let lhs = self.term(lhs)?;
+        let rhs = self.term(rhs)?;
+        let out = self.var();
+        let rhs = Tree::Op { rhs: Box::new(rhs), out: Box::new(Tree::Var(out.clone())) };
+        self.redexes.push((lhs, Tree::Op { rhs: Box::new(Tree::Num(Num::new_sym(*op))), out: Box::new(rhs) }));
+        Ok(Tree::Var(out))
+      }
+      Term::List(items) => {
+        let items = items.iter().map(|item| self.term(item)).collect::<Result<Vec<_>, _>>()?;
+        Ok(encode_list(items, Encoding::Scott, &mut || self.var()))
+      }
+    })
+  }
+
+  fn var(&mut self) -> String {
+    self.vars += 1;
+    create_var(self.vars - 1)
+  }
+
+  /// Ends the scope of the innermost variable, returning the tree its value is
+  /// connected to: a duplicator of its uses, its one use, or an eraser.
+  fn bind(&mut self) -> Result<Tree, String> {
+    let (var, mut uses) = self.scope.pop().unwrap();
+    if uses.len() < 2 {
+      return Ok(uses.pop().map_or(Tree::Era, Tree::Var));
+    }
+    let tag = self.labels.len() as Lab + 1;
+    if tag > Lab::MAX / 2 {
+      return Err("too many duplicated variables".to_owned());
+    }
+    let mut name = format!("{}.{var}", self.def);
+    for i in 2 .. {
+      if !self.labels.contains_key(&name) {
+        break;
+      }
+      name = format!("{}.{var}.{i}", self.def);
+    }
+    self.labels.insert(name, tag);
+    Ok(Tree::n_ary(tag * 2 + 1, uses.into_iter().map(Tree::Var).collect()))
+  }
+}
+
+impl Net {
+  /// Removes the redexes which have a var on one side, replacing the other
+  /// occurrence of the var with the tree on the other side.
+  fn substitute_vars(&mut self) {
+    let mut i = 0;
+    while i < self.redexes.len() {
+      let (var, tree) = match &mut self.redexes[i] {
+        (Tree::Var(var), tree) | (tree, Tree::Var(var)) => (mem::take(var), mem::take(tree)),
+        _ => {
+          i += 1;
+          continue;
+        }
+      };
+      self.redexes.remove(i);
+      let mut tree = Some(tree);
+      let mut stack = self.trees_mut().collect::<Vec<_>>();
+      while let Some(other) = stack.pop() {
+        if matches!(other, Tree::Var(name) if *name == var) {
+          *other = tree.take().unwrap();
+          break;
+        }
+        stack.extend(other.children_mut());
+      }
+      // the other occurrence is in the tree itself, so the var can't be removed
+      if let Some(tree) = tree {
+        self.redexes.insert(i, (Tree::Var(var), tree));
+        i += 1;
+      }
+    }
+  }
+}
+
+impl fmt::Display for Program {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, (name, term)) in self.defs.iter().enumerate() {
+      if i != 0 {
+        writeln!(f)?;
+      }
+      write!(f, "{name} = {term}")?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for Term {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    maybe_grow(move || match self {
+      Term::Var(name) => write!(f, "{name}"),
+      Term::Ref(name) => write!(f, "@{name}"),
+      Term::Lam { var, body } => write!(f, "λ{var} {body}"),
+      Term::App { .. } => {
+        let (fun, args) = self.unapply();
+        write!(f, "({fun}")?;
+        for arg in args {
+          write!(f, " {arg}")?;
+        }
+        write!(f, ")")
+      }
+      Term::Let { var, val, body } => write!(f, "let {var} = {val} {body}"),
+      Term::Num(num) => write!(f, "{num}"),
+      Term::Op { op, lhs, rhs } => write!(f, "({op} {lhs} {rhs})"),
+      Term::List(items) => {
+        write!(f, "[")?;
+        for (i, item) in items.iter().enumerate() {
+          if i != 0 {
+            write!(f, " ")?;
+          }
+          write!(f, "{item}")?;
+        }
+        write!(f, "]")
+      }
+    })
+  }
+}
+
+#[test]
+#[cfg(all(feature = "parser", feature = "std"))]
+fn test_lambda() {
+  use core::str::FromStr;
+
+  let program = Program::from_str(
+    "
+    // Church numerals
+    c2 = λf λx (f (f x))
+    k = \\x \\y x
+    sq = λn let m = n (* m m)
+    main = (c2 k (+ 1 2))
+    list = [1 λx x]
+    ",
+  )
+  .unwrap();
+  assert_eq!(
+    program.to_string(),
+    "c2 = λf λx (f (f x))\nk = λx λy x\nlist = [1 λx x]\nmain = (c2 k (+ 1 2))\nsq = λn let m = n (* m m)"
+  );
+  assert_eq!(Program::from_str(&program.to_string()).unwrap(), program);
+
+  let book = program.to_book().unwrap();
+  assert_eq!(book.nets["c2"].to_string(), "(#1{(a b) (c a)} (c b))");
+  assert_eq!(book.nets["k"].to_string(), "(a (* a))");
+  assert_eq!(book.nets["list"].to_string(), "((1 ((((a a) ((* (b b)) c)) (* c)) d)) (* d))");
+  assert_eq!(book.nets["sq"].to_string(), "(#2{$([*] $(a b)) a} b)");
+  assert!(book.nets["main"].alpha_eq(&"a & @c2 ~ (@k (b a)) & 1 ~ $([+] $(2 b))".parse().unwrap()));
+
+  assert_eq!(book.to_string().lines().next().unwrap(), "@c2 = (#c2.f{(a b) (c a)} (c b))");
+
+  // the labels are renumbered when merged with a book that uses them
+  let mut merged: Book = "@dup = (#1{a b} (a b))".parse().unwrap();
+  merged.merge(book).unwrap();
+  assert_eq!(merged.nets["c2"].to_string(), "(#2{(a b) (c a)} (c b))");
+  assert_eq!(merged.nets["dup"].to_string(), "(#1{a b} (a b))");
+
+  let err = Program::from_str("f = λx (x y)").unwrap().to_book().unwrap_err();
+  assert_eq!(err, "in `f`: unbound variable `y`");
+}
@@ -0,0 +1,154 @@
+use super::*;
+
+use core::str::FromStr;
+
+use TSPL::{new_parser, Parser};
+
+new_parser!(LambdaParser);
+
+impl<'i> LambdaParser<'i> {
+  /// Program = (Name "=" Term)*
+  fn parse_program(&mut self) -> Result<Program, String> {
+    let mut defs = BTreeMap::new();
+    while {
+      self.skip_trivia();
+      self.index != self.input.len()
+    } {
+      let name = self.parse_name()?;
+      self.consume("=")?;
+      let term = self.parse_term()?;
+      if defs.insert(name.clone(), term).is_some() {
+        return Err(format!("duplicate definition of `{name}`"));
+      }
+    }
+    Ok(Program { defs })
+  }
+
+  fn parse_term(&mut self) -> Result<Term, String> {
+    maybe_grow(move || {
+      self.skip_trivia();
+      match self.peek_one() {
+        // Lam = ("λ" | "\\") Name Term
+        Some('λ' | '\\') => {
+          self.advance_one();
+          self.skip_trivia();
+          let var = self.parse_name()?;
+          let body = Box::new(self.parse_term()?);
+          Ok(Term::Lam { var, body })
+        }
+        Some('(') => {
+          self.advance_one();
+          self.skip_trivia();
+          // Op = "(" Operator Term Term ")"
+          if let Some(op) = self.parse_op() {
+            let lhs = Box::new(self.parse_term()?);
+            let rhs = Box::new(self.parse_term()?);
+            self.consume(")")?;
+            return Ok(Term::Op { op, lhs, rhs });
+          }
+          // App = "(" Term Term* ")"
+          let mut term = self.parse_term()?;
+          while {
+            self.skip_trivia();
+            self.peek_one() != Some(')')
+          } {
+            let arg = Box::new(self.parse_term()?);
+            term = Term::App { fun: Box::new(term), arg };
+          }
+          self.consume(")")?;
+          Ok(term)
+        }
+        // List = "[" Term* "]"
+        Some('[') => {
+          self.advance_one();
+          let mut items = vec![];
+          while {
+            self.skip_trivia();
+            self.peek_one() != Some(']')
+          } {
+            items.push(self.parse_term()?);
+          }
+          self.consume("]")?;
+          Ok(Term::List(items))
+        }
+        // Ref = "@" Name
+        Some('@') => {
+          self.advance_one();
+          Ok(Term::Ref(self.parse_name()?))
+        }
+        // Num = /[0-9+-][0-9a-zA-Z.+-]*/, as in hvm64
+        Some('0' ..= '9' | '+' | '-') => {
+          let num = self.take_while(|c| c.is_alphanumeric() || c == '+' || c == '-' || c == '.');
+          match Tree::from_str(num) {
+            Ok(Tree::Num(num)) => Ok(Term::Num(num)),
+            _ => self.expected("number"),
+          }
+        }
+        _ => {
+          let name = self.parse_name()?;
+          // Let = "let" Name "=" Term Term
+          if name == "let" {
+            self.skip_trivia();
+            let var = self.parse_name()?;
+            self.consume("=")?;
+            let val = Box::new(self.parse_term()?);
+            let body = Box::new(self.parse_term()?);
+            return Ok(Term::Let { var, val, body });
+          }
+          // Var = Name
+          Ok(Term::Var(name))
+        }
+      }
+    })
+  }
+
+  /// Operator = /[^ ]+/, followed by whitespace; one of the binary operators of
+  /// hvm64 numbers, such as `+` or `<<`.
+  fn parse_op(&mut self) -> Option<NumTag> {
+    let rest = &self.input[self.index ..];
+    let op = NumTag::from_str_prefix(rest).filter(|&op| !op.is_ty() && op != NumTag::Sym)?;
+    if !rest[op.as_str().len() ..].starts_with(char::is_whitespace) {
+      return None;
+    }
+    self.advance_many(op.as_str().len());
+    Some(op)
+  }
+
+  /// Name = /[a-zA-Z0-9_.$\/]+/
+  fn parse_name(&mut self) -> Result<String, String> {
+    let name = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$' || c == '/');
+    if name.is_empty() {
+      return self.expected("name");
+    }
+    Ok(name.to_owned())
+  }
+}
+
+/// Parses the input with the callback, ensuring that the whole input is
+/// consumed.
+fn parse_eof<'i, T>(
+  input: &'i str,
+  parse_fn: impl Fn(&mut LambdaParser<'i>) -> Result<T, String>,
+) -> Result<T, String> {
+  let mut parser = LambdaParser::new(input);
+  let out = parse_fn(&mut parser)?;
+  parser.skip_trivia();
+  if parser.index != parser.input.len() {
+    return Err("Unable to parse the whole input. Is this not a λ-calculus program?".to_owned());
+  }
+  Ok(out)
+}
+
+impl FromStr for Program {
+  type Err = String;
+  fn from_str(str: &str) -> Result<Self, Self::Err> {
+    parse_eof(str, |parser| parser.parse_program())
+  }
+}
+
+impl FromStr for Term {
+  type Err = String;
+  fn from_str(str: &str) -> Result<Self, Self::Err> {
+    parse_eof(str, |parser| parser.parse_term())
+  }
+}
@@ -0,0 +1,214 @@
+//! Literal syntax for lists and strings, such as `[1 2 3]` and `"hi"`.
+//!
+//! Literals are expanded by the parser into ordinary trees, using either the
+//! [`Scott`] or the [`Church`] encoding of lists; a string is the list of its
+//! characters, as `u24` numbers.
+//! - the Scott encoding of a list is either `nil`, which is `λc λn. n` (the
+//!   tree `(* (r r))`), or `cons(head, tail)`, which is `λc λn. c head tail`
+//!   (the tree `((head (tail r)) (* r))`).
+//! - the Church encoding of a list is its right fold, `λc λn. c x0 (..)`, where
+//!   `(..)` is `(c x1 (.. (c xk n)))`. As `c` is used once for each element, it
+//!   is duplicated with a tree of `{a b}` nodes.
+//!
+//! When printing, such trees can be turned back into literals; see
+//! [`PrettyOpts::literals`].
+//!
+//! [`Scott`]: Encoding::Scott
+//! [`Church`]: Encoding::Church
+
+use super::*;
+
+use hvm64_num::NumTag;
+
+/// How lists are encoded; see the [module docs](self).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+  #[default]
+  Scott,
+  Church,
+}
+
+/// Builds the tree of a list of `items`, calling `var` to name the vars it
+/// needs.
+pub fn encode_list(items: Vec<Tree>, encoding: Encoding, var: &mut dyn FnMut() -> String) -> Tree {
+  match encoding {
+    Encoding::Scott => {
+      let r = var();
+      let mut tree = ctr(Tree::Era, ctr(Tree::Var(r.clone()), Tree::Var(r)));
+      for item in items.into_iter().rev() {
+        let r = var();
+        tree = ctr(ctr(item, ctr(tree, Tree::Var(r.clone()))), ctr(Tree::Era, Tree::Var(r)));
+      }
+      tree
+    }
+    Encoding::Church => {
+      // `(c0 (x0 r))`, `(c1 (x1 x0))`, .. `(ck (n xk-1))`, joined with `{a b}`
+      let r = Tree::Var(var());
+      let mut apps = vec![];
+      let mut prev = r.clone();
+      for item in items {
+        let next = Tree::Var(var());
+        apps.push(ctr(item, ctr(next.clone(), prev)));
+        prev = next;
+      }
+      let c = apps.into_iter().rev().reduce(|b, a| Tree::Ctr { lab: 1, p1: Box::new(a), p2: Box::new(b) });
+      ctr(c.unwrap_or(Tree::Era), ctr(prev, r))
+    }
+  }
+}
+
+/// Returns the items of `tree`, if it is a list encoded with `encoding`; the
+/// inverse of [`encode_list`].
+pub fn decode_list(tree: &Tree, encoding: Encoding) -> Option<Vec<&Tree>> {
+  match encoding {
+    Encoding::Scott => {
+      let mut items = vec![];
+      let mut rest = tree;
+      loop {
+        let (c, rest_) = un_ctr(rest)?;
+        let (n, r) = un_ctr(rest_)?;
+        if *c == Tree::Era {
+          return same_var(n, r).then_some(items);
+        }
+        let (head, rest_) = un_ctr(c)?;
+        let (tail, r_) = un_ctr(rest_)?;
+        if *n != Tree::Era || !same_var(r_, r) {
+          return None;
+        }
+        items.push(head);
+        rest = tail;
+      }
+    }
+    Encoding::Church => {
+      let (c, rest) = un_ctr(tree)?;
+      let (n, r) = un_ctr(rest)?;
+      if !matches!(n, Tree::Var(_)) || !matches!(r, Tree::Var(_)) {
+        return None;
+      }
+      // the applications of `c`, in any order, as `(head, next, prev)`
+      let mut apps = vec![];
+      let mut dups = vec![c];
+      while let Some(tree) = dups.pop() {
+        match tree {
+          Tree::Era if apps.is_empty() && dups.is_empty() => {}
+          Tree::Ctr { lab: 1, p1, p2 } => dups.extend([&**p2, &**p1]),
+          _ => {
+            let (head, rest) = un_ctr(tree)?;
+            let (next, prev) = un_ctr(rest)?;
+            apps.push((head, next, prev));
+          }
+        }
+      }
+      let mut items = Vec::with_capacity(apps.len());
+      let mut prev = r;
+      while !apps.is_empty() {
+        let i = apps.iter().position(|&(_, _, p)| same_var(p, prev))?;
+        let (head, next, _) = apps.swap_remove(i);
+        items.push(head);
+        prev = next;
+      }
+      same_var(prev, n).then_some(items)
+    }
+  }
+}
+
+/// Returns the items of `tree`, as [`decode_list`] does, if it can be printed
+/// as a list literal; lists such as `[*]`, `[+1]` or `[* 2]` can't, as they
+/// would be read as numbers.
+pub(crate) fn decode_literal(tree: &Tree, encoding: Encoding) -> Option<Vec<&Tree>> {
+  let items = decode_list(tree, encoding)?;
+  let is_op = |tree: &Tree| match tree {
+    Tree::Era => true,
+    Tree::Var(name) => name.parse::<NumTag>().is_ok(),
+    _ => false,
+  };
+  let looks_like_num = match items[..] {
+    [Tree::Num(num)] => num.to_string().starts_with(['+', '-']),
+    [item] | [item, Tree::Num(_)] => is_op(item),
+    _ => false,
+  };
+  (!looks_like_num).then_some(items)
+}
+
+/// Returns the string `items` represent, if there are any, and they are all
+/// characters that aren't control characters (other than `\n`, `\r`, and
+/// `\t`), so that lists of small numbers aren't printed as strings.
+pub(crate) fn decode_string(items: &[&Tree]) -> Option<String> {
+  if items.is_empty() {
+    return None;
+  }
+  items
+    .iter()
+    .map(|item| match item {
+      Tree::Num(num) if num.tag() == NumTag::U24 => {
+        char::from_u32(num.get_u24()).filter(|&c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+/// Writes `string` in quotes, escaping `"`, `\`, and control characters.
+pub(crate) fn write_string(f: &mut impl fmt::Write, string: &str) -> fmt::Result {
+  f.write_char('"')?;
+  for c in string.chars() {
+    match c {
+      '"' => f.write_str("\\\"")?,
+      '\\' => f.write_str("\\\\")?,
+      '\n' => f.write_str("\\n")?,
+      '\r' => f.write_str("\\r")?,
+      '\t' => f.write_str("\\t")?,
+      _ => f.write_char(c)?,
+    }
+  }
+  f.write_char('"')
+}
+
+fn ctr(p1: Tree, p2: Tree) -> Tree {
+  Tree::Ctr { lab: 0, p1: Box::new(p1), p2: Box::new(p2) }
+}
+
+fn un_ctr(tree: &Tree) -> Option<(&Tree, &Tree)> {
+  match tree {
+    Tree::Ctr { lab: 0, p1, p2 } => Some((p1, p2)),
+    _ => None,
+  }
+}
+
+fn same_var(a: &Tree, b: &Tree) -> bool {
+  matches!(a, Tree::Var(_)) && a == b
+}
+
+#[test]
+#[cfg(feature = "parser")]
+fn test_literals() {
+  use core::str::FromStr;
+
+  let scott = Tree::from_str(r#"[1 "a\"" []]"#).unwrap();
+  assert_eq!(
+    scott.to_string(),
+    "((1 (((((97 (((34 ((* (.lit1 .lit1)) .lit2)) (* .lit2)) .lit3)) (* .lit3)) \
+     ((((* (.lit4 .lit4)) ((* (.lit5 .lit5)) .lit6)) (* .lit6)) .lit7)) (* .lit7)) .lit8)) (* .lit8))"
+  );
+  let opts = PrettyOpts { literals: Some(Encoding::Scott), ..Default::default() };
+  assert_eq!(scott.pretty(&opts), r#"[1 "a\"" []]"#);
+
+  let opts = ParseOpts { literals: Encoding::Church };
+  let church = Tree::parse_with("[1 2]", &opts).unwrap();
+  assert_eq!(church.to_string(), "({(1 (.lit2 .lit1)) (2 (.lit3 .lit2))} (.lit3 .lit1))");
+  assert_eq!(church.pretty(&PrettyOpts { literals: Some(Encoding::Church), ..Default::default() }), "[1 2]");
+  assert_eq!(church.pretty(&PrettyOpts { literals: Some(Encoding::Scott), ..Default::default() }), church.to_string());
+
+  // operators are not lists, and lists that would be read as numbers aren't
+  // printed as literals
+  assert_eq!(Tree::from_str("[+1]").unwrap().to_string(), "[+1]");
+  let opts = PrettyOpts { literals: Some(Encoding::Scott), ..Default::default() };
+  for items in [vec![Tree::Era], vec![Tree::Num(Num::new_i24(1))], vec![Tree::Era, Tree::Num(Num::new_u24(2))]] {
+    let mut n = 0;
+    let list = encode_list(items, Encoding::Scott, &mut || {
+      n += 1;
+      format!("r{n}")
+    });
+    assert!(list.pretty(&opts).starts_with("(("), "{list}");
+  }
+}
@@ -1,45 +1,120 @@
 use hvm64_util::prelude::*;
 
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use core::str::FromStr;
 
-use crate::{Book, Net, Tree};
+use crate::{encode_list, labels::fresh_tag, Book, Encoding, Import, Lab, Net, Tree};
 use hvm64_num::{Num, NumTag};
 use hvm64_util::maybe_grow;
 
-use TSPL::{new_parser, Parser};
+use TSPL::Parser;
 
-new_parser!(Hvm64Parser);
+struct Hvm64Parser<'i> {
+  input: &'i str,
+  index: usize,
+  /// The offsets of all comments seen, whether skipped as trivia or read by
+  /// `parse_comments`.
+  comments: BTreeSet<usize>,
+  /// The offsets of the comments kept as the docs of defs.
+  docs: BTreeSet<usize>,
+}
+
+impl<'i> Parser<'i> for Hvm64Parser<'i> {
+  fn input(&mut self) -> &'i str {
+    self.input
+  }
+
+  fn index(&mut self) -> &mut usize {
+    &mut self.index
+  }
+
+  fn skip_trivia(&mut self) {
+    self.parse_comments();
+  }
+}
 
 impl<'i> Hvm64Parser<'i> {
-  /// Book = ("@" Name "=" Net)*
-  fn parse_book(&mut self) -> Result<Book, String> {
+  fn new(input: &'i str) -> Self {
+    Hvm64Parser { input, index: 0, comments: BTreeSet::new(), docs: BTreeSet::new() }
+  }
+
+  /// Book = (Comment* ("@" Name "=" Net | Import))*
+  fn parse_book(&mut self, opts: &ParseOpts) -> Result<Book, String> {
     maybe_grow(move || {
-      let mut book = BTreeMap::new();
-      while self.consume("@").is_ok() {
+      let mut nets = BTreeMap::new();
+      let mut docs = BTreeMap::new();
+      let mut imports = Vec::new();
+      let mut state = State::new(self.input, opts);
+      loop {
+        let (doc, doc_offsets) = self.parse_comments();
+        if self.consume("@").is_err() {
+          break;
+        }
         let name = self.parse_name()?;
+        self.skip_trivia();
+        // Import = "@import" String "as" Name
+        if name == "import" && self.peek_one() == Some('"') {
+          let path = self.parse_string()?;
+          self.consume("as")?;
+          self.skip_trivia();
+          let ns = self.parse_name()?;
+          self.docs.extend(doc_offsets);
+          imports.push(Import { path, ns, doc });
+          continue;
+        }
         self.consume("=")?;
-        let net = self.parse_net()?;
-        book.insert(name, net);
+        let net = self.parse_net(&mut state)?;
+        if !doc.is_empty() {
+          docs.insert(name.clone(), doc);
+          self.docs.extend(doc_offsets);
+        }
+        nets.insert(name, net);
       }
-      Ok(Book { nets: book })
+      Ok(Book { nets, docs, imports, labels: state.labels })
     })
   }
 
+  /// Comment = "//" /[^\n]*/
+  ///
+  /// Returns the comments before the next token, one per line, and their
+  /// offsets.
+  fn parse_comments(&mut self) -> (String, Vec<usize>) {
+    let mut comments = String::new();
+    let mut offsets = Vec::new();
+    loop {
+      self.take_while(char::is_whitespace);
+      if !self.input[self.index ..].starts_with("//") {
+        return (comments, offsets);
+      }
+      if !comments.is_empty() {
+        comments.push('\n');
+      }
+      self.comments.insert(self.index);
+      offsets.push(self.index);
+      comments.push_str(self.take_while(|c| c != '\n').trim_end());
+    }
+  }
+
+  /// The lines of the comments that weren't kept as docs.
+  fn stray_comment_lines(&self) -> Vec<usize> {
+    let line = |offset: usize| self.input[.. offset].matches('\n').count() + 1;
+    self.comments.difference(&self.docs).map(|&offset| line(offset)).collect()
+  }
+
   /// Net = Tree ("&" Tree "~" Tree)*
-  fn parse_net(&mut self) -> Result<Net, String> {
+  fn parse_net(&mut self, state: &mut State) -> Result<Net, String> {
     let mut redexes = Vec::new();
-    let root = self.parse_tree()?;
-    while self.consume("&").is_ok() {
-      let tree1 = self.parse_tree()?;
+    let root = self.parse_tree(state)?;
+    while self.try_consume("&") {
+      let tree1 = self.parse_tree(state)?;
       self.consume("~")?;
-      let tree2 = self.parse_tree()?;
+      let tree2 = self.parse_tree(state)?;
       redexes.push((tree1, tree2));
     }
     Ok(Net { root, redexes })
   }
 
-  fn parse_tree(&mut self) -> Result<Tree, String> {
+  fn parse_tree(&mut self, state: &mut State) -> Result<Tree, String> {
     maybe_grow(move || {
       self.skip_trivia();
       match self.peek_one() {
@@ -48,11 +123,18 @@ impl<'i> Hvm64Parser<'i> {
           self.advance_one();
           Ok(Tree::Era)
         }
-        // Ctr = ("#" Num)? ("(" Tree Tree ")" | "{" Tree Tree "}")
+        // Ctr = ("#" (Num | Name))? ("(" Tree Tree+ ")" | "{" Tree Tree+ "}")
+        //
+        // (With more than two ports, this is an n-ary combinator; see
+        // `Tree::n_ary`.)
         mut char @ Some('(' | '{' | '#') => {
           self.advance_one();
           let tag = if char == Some('#') {
-            let tag = self.parse_u64().unwrap() as u16;
+            let tag = if self.peek_one().is_some_and(|c| c.is_ascii_digit()) {
+              self.parse_u64()? as u16
+            } else {
+              state.intern(self.parse_name()?)
+            };
             self.skip_trivia();
             char = self.advance_one();
             tag
@@ -66,10 +148,15 @@ impl<'i> Hvm64Parser<'i> {
           };
           let lab = tag * 2 + kind;
           self.skip_trivia();
-          let p1 = Box::new(self.parse_tree()?);
-          let p2 = Box::new(self.parse_tree()?);
+          let mut ports = vec![self.parse_tree(state)?, self.parse_tree(state)?];
+          while {
+            self.skip_trivia();
+            !self.input[self.index ..].starts_with(close)
+          } {
+            ports.push(self.parse_tree(state)?);
+          }
           self.consume(close)?;
-          Ok(Tree::Ctr { lab, p1, p2 })
+          Ok(Tree::n_ary(lab, ports))
         }
         // Ref = "@" Name
         Some('@') => {
@@ -81,19 +168,43 @@ impl<'i> Hvm64Parser<'i> {
         // Op = "$(" Tree Tree ")"
         Some('$') => {
           self.consume("$(")?;
-          let rhs = Box::new(self.parse_tree()?);
-          let out = Box::new(self.parse_tree()?);
+          let rhs = Box::new(self.parse_tree(state)?);
+          let out = Box::new(self.parse_tree(state)?);
           self.consume(")")?;
           Ok(Tree::Op { rhs, out })
         }
         // Switch = "?(" Tree Tree ")"
         Some('?') => {
           self.consume("?(")?;
-          let arms = Box::new(self.parse_tree()?);
-          let out = Box::new(self.parse_tree()?);
+          let arms = Box::new(self.parse_tree(state)?);
+          let out = Box::new(self.parse_tree(state)?);
           self.consume(")")?;
           Ok(Tree::Switch { arms, out })
         }
+        // List = "[" Tree* "]"
+        //
+        // (If the brackets hold just an operator or a type, optionally followed
+        // by a number, such as `[+]`, `[u24]`, `[* 2]` or `[ +1]`, this is a
+        // number instead, however it is spaced; `[* 2 3]` and `[-1 2]` are
+        // lists. A list that would be read as a number, such as one of a
+        // single `*`, must be written out as a tree.)
+        Some('[') if !self.at_num() => {
+          self.advance_one();
+          let mut items = vec![];
+          while {
+            self.skip_trivia();
+            self.peek_one() != Some(']')
+          } {
+            items.push(self.parse_tree(state)?);
+          }
+          self.consume("]")?;
+          Ok(state.list(items))
+        }
+        // String = "\"" (/[^"\\]/ | "\\" /./)* "\""
+        Some('"') => {
+          let string = self.parse_string()?;
+          Ok(state.list(string.chars().map(|c| Tree::Num(Num::new_u24(c as u32))).collect()))
+        }
         Some('0' ..= '9' | '+' | '-' | '[') => {
           let num = self.parse_num()?;
           Ok(Tree::Num(num))
@@ -104,15 +215,57 @@ impl<'i> Hvm64Parser<'i> {
     })
   }
 
-  /// Name = /[a-zA-Z0-9_.$]+/
+  /// String = "\"" (/[^"\\]/ | "\\" /./)* "\""
+  fn parse_string(&mut self) -> Result<String, String> {
+    self.consume("\"")?;
+    let mut string = String::new();
+    loop {
+      match self.advance_one() {
+        Some('"') => return Ok(string),
+        Some('\\') => match self.advance_one() {
+          Some('n') => string.push('\n'),
+          Some('r') => string.push('\r'),
+          Some('t') => string.push('\t'),
+          Some(c @ ('"' | '\\')) => string.push(c),
+          _ => return self.expected("escape sequence"),
+        },
+        Some(c) => string.push(c),
+        None => return self.expected("`\"`"),
+      }
+    }
+  }
+
+  /// Consumes `text` if it is the next token.
+  ///
+  /// Unlike `consume`, this leaves the trivia before the next token in place if
+  /// it isn't `text`, so that the comments before a net can be parsed.
+  fn try_consume(&mut self, text: &str) -> bool {
+    let index = self.index;
+    let consumed = self.consume(text).is_ok();
+    if !consumed {
+      self.index = index;
+    }
+    consumed
+  }
+
+  /// Name = /[a-zA-Z0-9_.$\/]+/
   fn parse_name(&mut self) -> Result<String, String> {
-    let name = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$');
+    let name = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$' || c == '/');
     if name.is_empty() {
       return self.expected("name");
     }
     Ok(name.to_owned())
   }
 
+  /// Whether a number, such as `[+]` or `[* 2]`, rather than a list, starts at
+  /// the cursor.
+  fn at_num(&mut self) -> bool {
+    let index = self.index;
+    let is_num = self.parse_num().is_ok();
+    self.index = index;
+    is_num
+  }
+
   fn parse_num(&mut self) -> Result<Num, String> {
     self.skip_trivia();
 
@@ -121,13 +274,14 @@ impl<'i> Hvm64Parser<'i> {
     }
 
     self.consume("[")?;
+    self.skip_trivia();
 
     let Some(op) = NumTag::from_str_prefix(&self.input[self.index ..]) else { self.expected("operator")? };
     self.advance_many(op.as_str().len());
 
     self.skip_trivia();
 
-    if op.is_ty() || self.peek_one() == Some(']') {
+    if op.is_ty() || op == NumTag::Sym || self.peek_one() == Some(']') {
       self.consume("]")?;
       return Ok(Num::new_sym(op));
     }
@@ -140,27 +294,68 @@ impl<'i> Hvm64Parser<'i> {
   }
 
   fn parse_num_lit(&mut self) -> Result<Num, String> {
+    let index = self.index;
     let num = self.take_while(|x| x.is_alphanumeric() || x == '+' || x == '-' || x == '.');
-    if num.contains('.') || num.contains("inf") || num.contains("NaN") {
-      let val: f32 = num.parse().map_err(|err| format!("{err:?}"))?;
-      Ok(Num::new_f24(val))
-    } else if num.starts_with('+') || num.starts_with('-') {
-      let val = Self::parse_int(&num[1 ..])? as i32;
-      Ok(Num::new_i24(if num.starts_with('-') { -val } else { val }))
-    } else {
-      let val = Self::parse_int(num)? as u32;
-      Ok(Num::new_u24(val))
+    match Num::parse_literal(num) {
+      Some(num) => Ok(num),
+      None => {
+        self.index = index;
+        self.expected("number")
+      }
     }
   }
+}
 
-  fn parse_int(input: &str) -> Result<u64, String> {
-    if let Some(rest) = input.strip_prefix("0x") {
-      u64::from_str_radix(rest, 16).map_err(|err| format!("{err:?}"))
-    } else if let Some(rest) = input.strip_prefix("0b") {
-      u64::from_str_radix(rest, 2).map_err(|err| format!("{err:?}"))
-    } else {
-      input.parse::<u64>().map_err(|err| format!("{err:?}"))
+/// Options for parsing; see [`Book::parse_with`].
+#[derive(Clone, Debug, Default)]
+pub struct ParseOpts {
+  /// How list and string literals, such as `[1 2]` and `"hi"`, are encoded.
+  pub literals: Encoding,
+}
+
+/// The state of the parser, other than its position in the input.
+struct State {
+  /// The names of the labels in the input, and the tags they are interned to.
+  labels: BTreeMap<String, Lab>,
+  /// The tags used by numeric labels in the input, which names must not be
+  /// interned to.
+  taken: Set<Lab>,
+  literals: Encoding,
+  /// The number of vars created for literals.
+  vars: usize,
+}
+
+impl State {
+  fn new(input: &str, opts: &ParseOpts) -> Self {
+    // this may find things that aren't labels, such as `#1` in a comment, but
+    // that only means that tag won't be used for names
+    let taken = input
+      .split('#')
+      .skip(1)
+      .filter_map(|rest| rest[.. rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())].parse::<u64>().ok())
+      .map(|tag| tag as Lab)
+      .chain([0])
+      .collect();
+    State { labels: BTreeMap::new(), taken, literals: opts.literals, vars: 0 }
+  }
+
+  fn intern(&mut self, name: String) -> Lab {
+    if let Some(&tag) = self.labels.get(&name) {
+      return tag;
     }
+    let tag = fresh_tag(&self.taken);
+    self.taken.insert(tag);
+    self.labels.insert(name, tag);
+    tag
+  }
+
+  /// Expands a list literal. The vars it creates are named `.lit1`, `.lit2`,
+  /// etc., which don't conflict with conventional names.
+  fn list(&mut self, items: Vec<Tree>) -> Tree {
+    encode_list(items, self.literals, &mut || {
+      self.vars += 1;
+      format!(".lit{}", self.vars)
+    })
   }
 }
 
@@ -169,29 +364,87 @@ impl<'i> Hvm64Parser<'i> {
 fn parse_eof<'i, T>(input: &'i str, parse_fn: impl Fn(&mut Hvm64Parser<'i>) -> Result<T, String>) -> Result<T, String> {
   let mut parser = Hvm64Parser::new(input);
   let out = parse_fn(&mut parser)?;
+  parser.skip_trivia();
   if parser.index != parser.input.len() {
     return Err("Unable to parse the whole input. Is this not an hvm64 file?".to_owned());
   }
   Ok(out)
 }
 
+impl Book {
+  /// Parses a book, like [`FromStr`], but with the given options.
+  pub fn parse_with(code: &str, opts: &ParseOpts) -> Result<Self, String> {
+    parse_eof(code, |parser| parser.parse_book(opts))
+  }
+
+  /// Parses a book, like [`Book::parse_with`], and also returns the lines of
+  /// the comments that aren't before a def; only those are kept, in
+  /// [`Book::docs`], so printing the book would drop the others.
+  pub fn parse_with_stray_comments(code: &str, opts: &ParseOpts) -> Result<(Self, Vec<usize>), String> {
+    parse_eof(code, |parser| Ok((parser.parse_book(opts)?, parser.stray_comment_lines())))
+  }
+}
+
+impl Net {
+  /// Parses a net, like [`FromStr`], but with the given options.
+  pub fn parse_with(code: &str, opts: &ParseOpts) -> Result<Self, String> {
+    parse_eof(code, |parser| parser.parse_net(&mut State::new(code, opts)))
+  }
+}
+
+impl Tree {
+  /// Parses a tree, like [`FromStr`], but with the given options.
+  pub fn parse_with(code: &str, opts: &ParseOpts) -> Result<Self, String> {
+    parse_eof(code, |parser| parser.parse_tree(&mut State::new(code, opts)))
+  }
+}
+
 impl FromStr for Book {
   type Err = String;
   fn from_str(str: &str) -> Result<Self, Self::Err> {
-    parse_eof(str, Hvm64Parser::parse_book)
+    Book::parse_with(str, &ParseOpts::default())
   }
 }
 
 impl FromStr for Net {
   type Err = String;
   fn from_str(str: &str) -> Result<Self, Self::Err> {
-    parse_eof(str, Hvm64Parser::parse_net)
+    Net::parse_with(str, &ParseOpts::default())
   }
 }
 
 impl FromStr for Tree {
   type Err = String;
   fn from_str(str: &str) -> Result<Self, Self::Err> {
-    parse_eof(str, Hvm64Parser::parse_tree)
+    Tree::parse_with(str, &ParseOpts::default())
+  }
+}
+
+#[test]
+fn test_list_or_num() {
+  use crate::{Encoding, PrettyOpts};
+
+  let is_num = |code: &str| matches!(Tree::from_str(code).unwrap(), Tree::Num(_));
+  for code in ["[+]", "[u24]", "[+1]", "[+ 1]", "[* 2]", "[:<< 1]", "[-1]", "[ +1]", "[ * 2]", "[\n  *\n  2\n]"] {
+    assert!(is_num(code), "{code}");
+  }
+  for code in ["[]", "[ ]", "[* *]", "[-1 2]", "[* 2 3]", "[a]"] {
+    assert!(!is_num(code), "{code}");
   }
+  assert_eq!(Tree::from_str("[ +1]").unwrap(), Tree::from_str("[+1]").unwrap());
+
+  let opts = PrettyOpts { literals: Some(Encoding::Scott), ..Default::default() };
+  for code in ["[* *]", "[-1 2]", "[* 2 3]"] {
+    assert_eq!(Tree::from_str(code).unwrap().pretty(&opts), code);
+  }
+}
+
+#[test]
+fn test_import_docs() {
+  let code = "// The standard library.\n@import \"std.hvm\" as std\n@import \"util.hvm\" as util\n\n// The entry point.\n@main = @std/id\n";
+  let (book, stray) = Book::parse_with_stray_comments(code, &ParseOpts::default()).unwrap();
+  assert_eq!(stray, Vec::<usize>::new());
+  assert_eq!(book.imports[0].doc, "// The standard library.");
+  assert_eq!(book.imports[1].doc, "");
+  assert_eq!(book.to_string() + "\n", code);
 }
@@ -0,0 +1,224 @@
+//! A configurable pretty-printer for books and nets.
+
+use super::*;
+
+use core::fmt::Write;
+
+/// Options for [`Book::pretty`], [`Net::pretty`], and [`Tree::pretty`].
+#[derive(Clone, Debug)]
+pub struct PrettyOpts {
+  /// The width lines should fit in. Trees which don't fit on the rest of their
+  /// line are broken up, with each of their children on its own line.
+  pub width: usize,
+  /// The number of spaces that redexes and the children of broken up trees are
+  /// indented by.
+  pub indent: usize,
+  /// Whether to pad the left side of the redexes of each net, so that their
+  /// `~`s line up.
+  pub align_redexes: bool,
+  /// If set, trees which are lists in this encoding (see [`encode_list`]) are
+  /// printed as list or string literals, such as `[1 2]` or `"hi"`.
+  pub literals: Option<Encoding>,
+}
+
+impl Default for PrettyOpts {
+  fn default() -> Self {
+    PrettyOpts { width: 100, indent: 2, align_redexes: false, literals: None }
+  }
+}
+
+impl Book {
+  /// Prints this book according to `opts`.
+  ///
+  /// With an unbounded width, an indent of 2, and unaligned redexes, this is
+  /// the same as the `Display` impl.
+  pub fn pretty(&self, opts: &PrettyOpts) -> String {
+    let mut printer = Printer { opts, names: self.label_names(), out: String::new() };
+    for import in &self.imports {
+      writeln!(printer.out, "{import}").unwrap();
+    }
+    if !self.imports.is_empty() && !self.is_empty() {
+      printer.out.push('\n');
+    }
+    for (i, (name, net)) in self.iter().enumerate() {
+      if i != 0 {
+        printer.out.push_str("\n\n");
+      }
+      if let Some(doc) = self.docs.get(name) {
+        writeln!(printer.out, "{doc}").unwrap();
+      }
+      write!(printer.out, "@{name} = ").unwrap();
+      printer.net(net);
+    }
+    printer.out
+  }
+}
+
+impl Net {
+  /// Prints this net according to `opts`; see [`Book::pretty`].
+  pub fn pretty(&self, opts: &PrettyOpts) -> String {
+    let mut printer = Printer { opts, names: BTreeMap::new(), out: String::new() };
+    printer.net(self);
+    printer.out
+  }
+}
+
+impl Tree {
+  /// Prints this tree according to `opts`; see [`Book::pretty`].
+  pub fn pretty(&self, opts: &PrettyOpts) -> String {
+    let mut printer = Printer { opts, names: BTreeMap::new(), out: String::new() };
+    printer.tree(self, 0);
+    printer.out
+  }
+}
+
+struct Printer<'a> {
+  opts: &'a PrettyOpts,
+  /// The names of the labels, by their tags.
+  names: BTreeMap<Lab, String>,
+  out: String,
+}
+
+impl Printer<'_> {
+  /// The column the next character will be printed at.
+  fn column(&self) -> usize {
+    self.out[self.out.rfind('\n').map_or(0, |i| i + 1) ..].chars().count()
+  }
+
+  fn newline(&mut self, indent: usize) {
+    self.out.push('\n');
+    self.out.extend(iter::repeat(' ').take(indent));
+  }
+
+  fn net(&mut self, net: &Net) {
+    self.tree(&net.root, 0);
+    let indent = self.opts.indent;
+    // the `~`s are aligned after the widest left side that fits on one line
+    let align = if self.opts.align_redexes {
+      let room = self.opts.width.saturating_sub(indent + 2);
+      net.redexes.iter().filter_map(|(a, _)| self.flat_width(a, room)).max().unwrap_or(0)
+    } else {
+      0
+    };
+    for (a, b) in &net.redexes {
+      self.newline(indent);
+      self.out.push_str("& ");
+      let start = self.out.len();
+      self.tree(a, indent);
+      if !self.out[start ..].contains('\n') {
+        let width = self.out[start ..].chars().count();
+        self.out.extend(iter::repeat(' ').take(align.saturating_sub(width)));
+      }
+      self.out.push_str(" ~ ");
+      self.tree(b, indent);
+    }
+  }
+
+  /// Prints `tree`, breaking it up if it doesn't fit on the current line.
+  /// `indent` is the indentation of the line the tree starts on.
+  fn tree(&mut self, tree: &Tree, indent: usize) {
+    maybe_grow(move || {
+      let room = self.opts.width.saturating_sub(self.column());
+      let broken = if self.flat_width(tree, room).is_some() {
+        None
+      } else {
+        match self.opts.literals.and_then(|encoding| literals::decode_literal(tree, encoding)) {
+          // strings are never broken up
+          Some(items) => literals::decode_string(&items).is_none().then(|| ("[".to_owned(), "]", items)),
+          None => self.delimiters(tree).map(|(open, close)| (open, close, tree.children().collect())),
+        }
+      };
+      let Some((open, close, children)) = broken else {
+        let tree = Printed { inner: tree, names: &self.names, literals: self.opts.literals };
+        return write!(self.out, "{tree}").unwrap();
+      };
+      self.out.push_str(&open);
+      let inner = indent + self.opts.indent;
+      for child in children {
+        self.newline(inner);
+        self.tree(child, inner);
+      }
+      self.newline(indent);
+      self.out.push_str(close);
+    })
+  }
+
+  /// The text printed before and after the children of `tree`, if it has any.
+  fn delimiters(&self, tree: &Tree) -> Option<(String, &'static str)> {
+    match tree {
+      Tree::Era | Tree::Num(_) | Tree::Ref(_) | Tree::Var(_) => None,
+      &Tree::Ctr { lab, .. } => Some(match (lab, self.names.get(&(lab / 2))) {
+        (_, Some(name)) if lab % 2 == 0 => (format!("#{name}("), ")"),
+        (_, Some(name)) => (format!("#{name}{{"), "}"),
+        (0, _) => ("(".to_owned(), ")"),
+        (1, _) => ("{".to_owned(), "}"),
+        _ if lab % 2 == 0 => (format!("#{}(", lab / 2), ")"),
+        _ => (format!("#{}{{", lab / 2), "}"),
+      }),
+      Tree::Op { .. } => Some(("$(".to_owned(), ")")),
+      Tree::Switch { .. } => Some(("?(".to_owned(), ")")),
+    }
+  }
+
+  /// The width of `tree` when printed on one line, if it is at most `limit`.
+  fn flat_width(&self, tree: &Tree, limit: usize) -> Option<usize> {
+    struct Counter {
+      width: usize,
+      limit: usize,
+    }
+
+    impl Write for Counter {
+      fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.width += s.chars().count();
+        if self.width > self.limit { Err(fmt::Error) } else { Ok(()) }
+      }
+    }
+
+    let mut counter = Counter { width: 0, limit };
+    write!(counter, "{}", Printed { inner: tree, names: &self.names, literals: self.opts.literals }).ok()?;
+    Some(counter.width)
+  }
+}
+
+#[test]
+#[cfg(feature = "parser")]
+fn test_pretty() {
+  let book: Book = "
+    /// Divides and takes the remainder.
+    @foo = (#1{$([/] $(a b)) $([%] $(c d))} (#2{a c} {b d}))
+    @main = r & @foo ~ (1 (2 r)) & @foo ~ (3 *)
+  "
+  .parse()
+  .unwrap();
+
+  let unbounded = PrettyOpts { width: usize::MAX, ..Default::default() };
+  assert_eq!(book.pretty(&unbounded), book.to_string());
+
+  let narrow = PrettyOpts { width: 30, ..Default::default() };
+  assert_eq!(
+    book.pretty(&narrow),
+    "/// Divides and takes the remainder.
+@foo = (
+  #1{
+    $([/] $(a b))
+    $([%] $(c d))
+  }
+  (#2{a c} {b d})
+)
+
+@main = r
+  & @foo ~ (1 (2 r))
+  & @foo ~ (3 *)"
+  );
+  assert_eq!(book.pretty(&narrow).parse::<Book>().unwrap(), book);
+
+  let aligned = PrettyOpts { indent: 4, align_redexes: true, ..Default::default() };
+  let net: Net = "r & @foo ~ (1 (2 r)) & (a b) ~ (a b) & @a_long_name ~ *".parse().unwrap();
+  assert_eq!(
+    net.pretty(&aligned),
+    "r
+    & @foo         ~ (1 (2 r))
+    & (a b)        ~ (a b)
+    & @a_long_name ~ *"
+  );
+}
@@ -0,0 +1,100 @@
+//! Serde support for the AST, enabled by the `serde` feature.
+//!
+//! The JSON schema is:
+//! - a [`Book`] is an object `{"nets": {...}, "docs": {...}, "imports": [...],
+//!   "labels": {...}}`, where:
+//!   - `nets` maps the name of each net (without the leading `@`) to the net
+//!   - `docs` maps the name of a net to its comments ([`Book::docs`])
+//!   - `imports` lists the unresolved imports, as `{"path": "...", "ns": "...",
+//!     "doc": "..."}` ([`Book::imports`]), where `doc` may be omitted
+//!   - `labels` maps the name of each named label to its label
+//!     ([`Book::labels`])
+//!
+//!   All but `nets` may be omitted when deserializing, and default to empty.
+//! - a [`Net`] is an object `{"root": <tree>, "redexes": [[<tree>, <tree>],
+//!   ...]}`
+//! - a [`Tree`] is one of:
+//!   - `"era"`
+//!   - `{"num": <num>}`, where `<num>` is a [`Num`], e.g. `{"u24": 1}`; see
+//!     `hvm64-num` for its schema
+//!   - `{"ref": "name"}`, without the leading `@`
+//!   - `{"ctr": {"lab": 0, "p1": <tree>, "p2": <tree>}}`
+//!   - `{"op": {"rhs": <tree>, "out": <tree>}}`
+//!   - `{"switch": {"arms": <tree>, "out": <tree>}}`
+//!   - `{"var": "name"}`
+//!
+//! For example, `@main = (a a) & @foo ~ $([+] 1)` is
+//! ```json
+//! {"nets": {"main": {
+//!   "root": {"ctr": {"lab": 0, "p1": {"var": "a"}, "p2": {"var": "a"}}},
+//!   "redexes": [[{"ref": "foo"}, {"op": {"rhs": {"num": {"sym": "+"}}, "out": {"num": {"u24": 1}}}}]]
+//! }}, "docs": {}, "imports": [], "labels": {}}
+//! ```
+//!
+//! Like [`Clone`] and [`Drop`], serializing and deserializing a [`Tree`] does
+//! not overflow the stack on deep trees. (Note, however, that `serde_json`
+//! limits the nesting depth of what it deserializes, unless its
+//! `unbounded_depth` feature is used.)
+
+use super::*;
+
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The serialized form of a [`Tree`], borrowing its contents.
+#[derive(Serialize)]
+#[serde(rename = "Tree", rename_all = "lowercase")]
+enum TreeRef<'a> {
+  Era,
+  Num(Num),
+  Ref(&'a str),
+  Ctr { lab: Lab, p1: &'a Tree, p2: &'a Tree },
+  Op { rhs: &'a Tree, out: &'a Tree },
+  Switch { arms: &'a Tree, out: &'a Tree },
+  Var(&'a str),
+}
+
+/// The deserialized form of a [`Tree`].
+#[derive(Deserialize)]
+#[serde(rename = "Tree", rename_all = "lowercase")]
+enum TreeDe {
+  Era,
+  Num(Num),
+  Ref(String),
+  Ctr { lab: Lab, p1: Box<Tree>, p2: Box<Tree> },
+  Op { rhs: Box<Tree>, out: Box<Tree> },
+  Switch { arms: Box<Tree>, out: Box<Tree> },
+  Var(String),
+}
+
+impl Serialize for Tree {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    maybe_grow(move || {
+      match self {
+        Tree::Era => TreeRef::Era,
+        Tree::Num(num) => TreeRef::Num(*num),
+        Tree::Ref(name) => TreeRef::Ref(name),
+        Tree::Ctr { lab, p1, p2 } => TreeRef::Ctr { lab: *lab, p1, p2 },
+        Tree::Op { rhs, out } => TreeRef::Op { rhs, out },
+        Tree::Switch { arms, out } => TreeRef::Switch { arms, out },
+        Tree::Var(name) => TreeRef::Var(name),
+      }
+      .serialize(serializer)
+    })
+  }
+}
+
+impl<'de> Deserialize<'de> for Tree {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    maybe_grow(move || {
+      Ok(match TreeDe::deserialize(deserializer)? {
+        TreeDe::Era => Tree::Era,
+        TreeDe::Num(num) => Tree::Num(num),
+        TreeDe::Ref(name) => Tree::Ref(name),
+        TreeDe::Ctr { lab, p1, p2 } => Tree::Ctr { lab, p1, p2 },
+        TreeDe::Op { rhs, out } => Tree::Op { rhs, out },
+        TreeDe::Switch { arms, out } => Tree::Switch { arms, out },
+        TreeDe::Var(name) => Tree::Var(name),
+      })
+    })
+  }
+}
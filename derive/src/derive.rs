@@ -0,0 +1,199 @@
+//! Derive macros for `hvm64_host::IntoNet` and `hvm64_host::FromNet`, and the
+//! `hvm64_ast::net!` and `hvm64_ast::book!` macros.
+//!
+//! A struct is encoded as a single constructor, and an enum as one constructor
+//! per variant, in declaration order; see the docs of `hvm64_host::value`.
+//!
+//! The generated code refers to `hvm64_ast` and `hvm64_host`, which must be
+//! dependencies of the crate using them.
+
+mod syntax;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Generics, Ident, Index};
+
+#[proc_macro_derive(IntoNet)]
+pub fn derive_into_net(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let name = &input.ident;
+  let generics = add_bounds(input.generics.clone(), quote!(::hvm64_host::IntoNet));
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+  let body = match &input.data {
+    Data::Struct(data) => {
+      let (pattern, encode) = encode_fields(&data.fields);
+      quote! {
+        let #name #pattern = self;
+        let fields = vec![#(#encode),*];
+        Ok(encoder.ctr(0, 1, fields))
+      }
+    }
+    Data::Enum(data) => {
+      let count = data.variants.len();
+      let arms = data.variants.iter().enumerate().map(|(index, variant)| {
+        let variant_name = &variant.ident;
+        let (pattern, encode) = encode_fields(&variant.fields);
+        quote! {
+          #name::#variant_name #pattern => {
+            let fields = vec![#(#encode),*];
+            Ok(encoder.ctr(#index, #count, fields))
+          }
+        }
+      });
+      quote! {
+        match self {
+          #(#arms)*
+        }
+      }
+    }
+    Data::Union(_) => {
+      return Error::new(Span::call_site(), "unions cannot derive `IntoNet`").into_compile_error().into();
+    }
+  };
+
+  quote! {
+    impl #impl_generics ::hvm64_host::IntoNet for #name #ty_generics #where_clause {
+      #[allow(unused_variables)]
+      fn into_net(
+        self,
+        encoder: &mut ::hvm64_host::ValueEncoder,
+      ) -> Result<::hvm64_ast::Tree, ::hvm64_host::EncodeError> {
+        #body
+      }
+    }
+  }
+  .into()
+}
+
+#[proc_macro_derive(FromNet)]
+pub fn derive_from_net(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let name = &input.ident;
+  let generics = add_bounds(input.generics.clone(), quote!(::hvm64_host::FromNet));
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+  let expected = name.to_string();
+
+  let (arities, arms) = match &input.data {
+    Data::Struct(data) => (vec![data.fields.len()], vec![decode_fields(quote!(#name), &data.fields)]),
+    Data::Enum(data) => data
+      .variants
+      .iter()
+      .map(|variant| {
+        let variant_name = &variant.ident;
+        (variant.fields.len(), decode_fields(quote!(#name::#variant_name), &variant.fields))
+      })
+      .unzip(),
+    Data::Union(_) => {
+      return Error::new(Span::call_site(), "unions cannot derive `FromNet`").into_compile_error().into();
+    }
+  };
+  let indices = 0 .. arms.len();
+
+  quote! {
+    impl #impl_generics ::hvm64_host::FromNet for #name #ty_generics #where_clause {
+      #[allow(unused_variables, unused_mut)]
+      fn from_net(
+        tree: &::hvm64_ast::Tree,
+        decoder: &::hvm64_host::ValueDecoder,
+      ) -> Result<Self, ::hvm64_host::DecodeError> {
+        let (index, fields) = decoder.ctr(tree, &[#(#arities),*], #expected)?;
+        let mut fields = fields.into_iter();
+        Ok(match index {
+          #(#indices => #arms,)*
+          _ => unreachable!(),
+        })
+      }
+    }
+  }
+  .into()
+}
+
+/// Parses a net in the textual syntax of hvm-64 at compile time, evaluating to
+/// a `hvm64_ast::Net`.
+///
+/// `#{expr}` interpolates a Rust value in place of a tree; it can be anything
+/// that implements `Into<Tree>`, such as a `Tree` or a `Num`. The vars in
+/// interpolated trees aren't checked.
+///
+/// ```
+/// use hvm64_ast::{net, Tree};
+///
+/// let arg = Tree::Ref("S3".to_owned());
+/// let net = net!(a & @GenGotIndex ~ (#{arg} a));
+/// assert_eq!(net, "a & @GenGotIndex ~ (@S3 a)".parse().unwrap());
+/// ```
+///
+/// Malformed syntax is a compile error:
+///
+/// ```compile_fail
+/// hvm64_ast::net!((a a) & *);
+/// ```
+///
+/// As is a var which isn't used exactly twice:
+///
+/// ```compile_fail
+/// hvm64_ast::net!((a b) & (b c) ~ *);
+/// ```
+#[proc_macro]
+pub fn net(input: TokenStream) -> TokenStream {
+  syntax::expand_net(input.into()).unwrap_or_else(syntax::compile_error).into()
+}
+
+/// Parses a book in the textual syntax of hvm-64 at compile time, evaluating to
+/// a `hvm64_ast::Book`; see [`net!`].
+///
+/// ```
+/// let book = hvm64_ast::book! {
+///   /// Doubles a number.
+///   @double = ($([*] $(2 a)) a)
+///   @main = a & @double ~ (21 a)
+/// };
+/// assert_eq!(book.docs["double"], "/// Doubles a number.");
+/// ```
+#[proc_macro]
+pub fn book(input: TokenStream) -> TokenStream {
+  syntax::expand_book(input.into()).unwrap_or_else(syntax::compile_error).into()
+}
+
+fn add_bounds(mut generics: Generics, bound: TokenStream2) -> Generics {
+  for param in generics.type_params_mut() {
+    param.bounds.push(parse_quote!(#bound));
+  }
+  generics
+}
+
+/// Returns a pattern binding the fields, and the expressions encoding them.
+fn encode_fields(fields: &Fields) -> (TokenStream2, Vec<TokenStream2>) {
+  let bindings: Vec<Ident> = (0 .. fields.len()).map(|i| format_ident!("field_{i}")).collect();
+  let pattern = match fields {
+    Fields::Named(fields) => {
+      let names = fields.named.iter().map(|field| &field.ident);
+      quote!({ #(#names: #bindings),* })
+    }
+    Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+    Fields::Unit => quote!(),
+  };
+  let encode = bindings.iter().map(|binding| quote!(encoder.encode(#binding)?)).collect();
+  (pattern, encode)
+}
+
+/// Returns an expression constructing `path` from the decoded `fields`.
+fn decode_fields(path: TokenStream2, fields: &Fields) -> TokenStream2 {
+  let decode = quote!(decoder.decode(fields.next().unwrap())?);
+  match fields {
+    Fields::Named(fields) => {
+      let names = fields.named.iter().map(|field| &field.ident);
+      quote!(#path { #(#names: #decode),* })
+    }
+    Fields::Unnamed(fields) => {
+      let decode = fields.unnamed.iter().enumerate().map(|(i, _)| {
+        let index = Index::from(i);
+        quote!(#index: #decode)
+      });
+      quote!(#path { #(#decode),* })
+    }
+    Fields::Unit => quote!(#path),
+  }
+}
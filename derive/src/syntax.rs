@@ -0,0 +1,478 @@
+//! The `net!` and `book!` macros, which parse the textual syntax of hvm-64 at
+//! compile time.
+//!
+//! Rust tokenizes the input before the macros see it, so the grammar is that
+//! of the parser in `hvm64_ast`, but over tokens: `@foo.bar` is `@`, `foo`,
+//! `.`, `bar`, and `[<<]` is a bracketed group of two `<`s. Comments are
+//! skipped by Rust, except for doc comments, which become `#[doc = ".."]`.
+//!
+//! The expansion constructs the AST directly; numbers and labels are resolved
+//! here, so that malformed ones are compile errors.
+
+use std::collections::HashSet;
+
+use hvm64_num::{Num, NumTag};
+use proc_macro2::{Delimiter, Group, Ident, Spacing, Span, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::{Error, Expr, ExprLit, Lit, MetaNameValue, Result};
+
+type Lab = u16;
+
+/// Book = (DocComment* "@" Name "=" Net)*
+pub fn expand_book(input: TokenStream2) -> Result<TokenStream2> {
+  let mut parser = NetParser::new(input.clone());
+  let mut tokens = Tokens::new(input, Span::call_site(), "the end of the input");
+  let book = Ident::new("book", Span::mixed_site());
+  let mut names = HashSet::new();
+  let mut nets = Vec::new();
+  while !tokens.is_empty() {
+    let doc = parse_doc(&mut tokens)?;
+    tokens.expect('@')?;
+    let span = tokens.span();
+    let name = parse_name(&mut tokens)?;
+    if !names.insert(name.clone()) {
+      return Err(Error::new(span, format!("duplicate definition of `@{name}`")));
+    }
+    tokens.expect('=')?;
+    let net = parser.parse_net(&mut tokens)?;
+    nets.push(quote!(#book.nets.insert(#name.into(), #net);));
+    if !doc.is_empty() {
+      nets.push(quote!(#book.docs.insert(#name.into(), #doc.into());));
+    }
+  }
+  let labels = parser.labels.iter().map(|(name, tag)| quote!(#book.labels.insert(#name.into(), #tag);));
+  Ok(quote! {{
+    let mut #book = ::hvm64_ast::Book::default();
+    #(#nets)*
+    #(#labels)*
+    #book
+  }})
+}
+
+pub fn expand_net(input: TokenStream2) -> Result<TokenStream2> {
+  let mut parser = NetParser::new(input.clone());
+  let mut tokens = Tokens::new(input, Span::call_site(), "the end of the input");
+  let net = parser.parse_net(&mut tokens)?;
+  if !tokens.is_empty() {
+    return tokens.expected("`&` or the end of the input");
+  }
+  Ok(net)
+}
+
+/// Expands to the `compile_error!`s of `error`, in a block, so that they can be
+/// used as an expression.
+pub fn compile_error(error: Error) -> TokenStream2 {
+  let error = error.into_compile_error();
+  quote!({ #error })
+}
+
+struct NetParser {
+  /// The named labels, such as `#dup_x{a b}`, and the tags they are interned
+  /// to.
+  labels: Vec<(String, Lab)>,
+  /// The tags which named labels must not be interned to; those of the numeric
+  /// labels in the input, and those already interned.
+  taken: HashSet<Lab>,
+  /// The number of vars created for list literals.
+  lit_vars: usize,
+  /// The vars of the current net, and the spans of their uses.
+  vars: Vec<(String, Vec<Span>)>,
+}
+
+impl NetParser {
+  fn new(input: TokenStream2) -> Self {
+    let mut taken = HashSet::from([0]);
+    numeric_tags(input, &mut taken);
+    NetParser { labels: Vec::new(), taken, lit_vars: 0, vars: Vec::new() }
+  }
+
+  /// Net = Tree ("&" Tree "~" Tree)*
+  fn parse_net(&mut self, tokens: &mut Tokens) -> Result<TokenStream2> {
+    self.vars.clear();
+    let root = self.parse_tree(tokens)?;
+    let mut redexes = Vec::new();
+    while tokens.consume('&') {
+      let tree1 = self.parse_tree(tokens)?;
+      tokens.expect('~')?;
+      let tree2 = self.parse_tree(tokens)?;
+      redexes.push(quote!((#tree1, #tree2)));
+    }
+    self.check_vars()?;
+    Ok(quote!(::hvm64_ast::Net { root: #root, redexes: ::hvm64_ast::__private::vec![#(#redexes),*] }))
+  }
+
+  fn parse_tree(&mut self, tokens: &mut Tokens) -> Result<TokenStream2> {
+    let Some(token) = tokens.peek().cloned() else { return tokens.expected("a tree") };
+    match token {
+      // Era = "*"
+      TokenTree::Punct(punct) if punct.as_char() == '*' => {
+        tokens.advance();
+        Ok(quote!(::hvm64_ast::Tree::Era))
+      }
+      // Ctr = ("#" (Num | Name))? ("(" Tree Tree+ ")" | "{" Tree Tree+ "}")
+      TokenTree::Group(group) if matches!(group.delimiter(), Delimiter::Parenthesis | Delimiter::Brace) => {
+        tokens.advance();
+        self.parse_ctr(0, &group)
+      }
+      TokenTree::Punct(punct) if punct.as_char() == '#' => {
+        tokens.advance();
+        let tag = match tokens.peek().cloned() {
+          // Interpolation = "#{" Expr "}"
+          Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+            tokens.advance();
+            let expr = group.stream();
+            return Ok(quote!(::core::convert::Into::<::hvm64_ast::Tree>::into(#expr)));
+          }
+          Some(TokenTree::Literal(literal)) => {
+            tokens.advance();
+            match literal.to_string().parse::<Lab>() {
+              Ok(tag) if tag <= Lab::MAX / 2 => tag,
+              _ => return Err(Error::new(literal.span(), "invalid label")),
+            }
+          }
+          Some(TokenTree::Ident(_)) => {
+            let span = tokens.span();
+            let name = parse_name(tokens)?;
+            self.intern(name, span)?
+          }
+          _ => return tokens.expected("a label or `{`"),
+        };
+        let group = tokens.group(&[Delimiter::Parenthesis, Delimiter::Brace], "`(` or `{`")?;
+        self.parse_ctr(tag, &group)
+      }
+      // Ref = "@" Name
+      TokenTree::Punct(punct) if punct.as_char() == '@' => {
+        tokens.advance();
+        let name = parse_name(tokens)?;
+        Ok(quote!(::hvm64_ast::Tree::Ref(#name.into())))
+      }
+      // Op = "$(" Tree Tree ")"
+      TokenTree::Punct(punct) if punct.as_char() == '$' => {
+        tokens.advance();
+        let (rhs, out) = self.parse_pair(tokens)?;
+        Ok(quote! {
+          ::hvm64_ast::Tree::Op {
+            rhs: ::hvm64_ast::__private::Box::new(#rhs),
+            out: ::hvm64_ast::__private::Box::new(#out),
+          }
+        })
+      }
+      // Switch = "?(" Tree Tree ")"
+      TokenTree::Punct(punct) if punct.as_char() == '?' => {
+        tokens.advance();
+        let (arms, out) = self.parse_pair(tokens)?;
+        Ok(quote! {
+          ::hvm64_ast::Tree::Switch {
+            arms: ::hvm64_ast::__private::Box::new(#arms),
+            out: ::hvm64_ast::__private::Box::new(#out),
+          }
+        })
+      }
+      TokenTree::Group(group) if group.delimiter() == Delimiter::Bracket => {
+        tokens.advance();
+        let mut inner = Tokens::within(&group);
+        if let Some(num) = parse_bracketed_num(&inner) {
+          return Ok(num_tree(num));
+        }
+        // List = "[" Tree* "]"
+        //
+        // (As in the parser, brackets holding just an operator or a type,
+        // optionally followed by a number, are a number instead.)
+        let mut items = Vec::new();
+        while !inner.is_empty() {
+          items.push(self.parse_tree(&mut inner)?);
+        }
+        Ok(self.list(items))
+      }
+      TokenTree::Literal(literal) => {
+        // String = a Rust string literal
+        if let Lit::Str(string) = Lit::new(literal) {
+          tokens.advance();
+          return Ok(self.list(string.value().chars().map(|c| num_tree(Num::new_u24(c as u32))).collect()));
+        }
+        Ok(num_tree(parse_num_lit(tokens)?))
+      }
+      TokenTree::Punct(punct) if matches!(punct.as_char(), '+' | '-') => Ok(num_tree(parse_num_lit(tokens)?)),
+      // Var = Name
+      TokenTree::Ident(_) => {
+        let span = tokens.span();
+        let name = parse_name(tokens)?;
+        match self.vars.iter_mut().find(|(var, _)| *var == name) {
+          Some((_, uses)) => uses.push(span),
+          None => self.vars.push((name.clone(), vec![span])),
+        }
+        Ok(quote!(::hvm64_ast::Tree::Var(#name.into())))
+      }
+      // e.g. a `$tree:tt` passed through a `macro_rules` macro
+      TokenTree::Group(group) => {
+        tokens.advance();
+        let mut inner = Tokens::within(&group);
+        let tree = self.parse_tree(&mut inner)?;
+        inner.expect_end()?;
+        Ok(tree)
+      }
+      TokenTree::Punct(_) => tokens.expected("a tree"),
+    }
+  }
+
+  /// Parses the ports of a combinator with the tag `tag`, delimited by `group`.
+  fn parse_ctr(&mut self, tag: Lab, group: &Group) -> Result<TokenStream2> {
+    let lab = tag * 2 + Lab::from(group.delimiter() == Delimiter::Brace);
+    let mut tokens = Tokens::within(group);
+    let mut ports = vec![self.parse_tree(&mut tokens)?, self.parse_tree(&mut tokens)?];
+    while !tokens.is_empty() {
+      ports.push(self.parse_tree(&mut tokens)?);
+    }
+    Ok(quote!(::hvm64_ast::Tree::n_ary(#lab, ::hvm64_ast::__private::vec![#(#ports),*])))
+  }
+
+  /// Parses `"(" Tree Tree ")"`, as in an op or a switch.
+  fn parse_pair(&mut self, tokens: &mut Tokens) -> Result<(TokenStream2, TokenStream2)> {
+    let group = tokens.group(&[Delimiter::Parenthesis], "`(`")?;
+    let mut tokens = Tokens::within(&group);
+    let a = self.parse_tree(&mut tokens)?;
+    let b = self.parse_tree(&mut tokens)?;
+    tokens.expect_end()?;
+    Ok((a, b))
+  }
+
+  /// Expands a list literal, as the parser does by default.
+  fn list(&mut self, items: Vec<TokenStream2>) -> TokenStream2 {
+    let lit_vars = self.lit_vars;
+    // a Scott-encoded list uses a var per item, plus one
+    self.lit_vars += items.len() + 1;
+    quote!(::hvm64_ast::__private::list(::hvm64_ast::__private::vec![#(#items),*], #lit_vars))
+  }
+
+  fn intern(&mut self, name: String, span: Span) -> Result<Lab> {
+    if let Some(&(_, tag)) = self.labels.iter().find(|(label, _)| *label == name) {
+      return Ok(tag);
+    }
+    let Some(tag) = (1 ..= Lab::MAX / 2).find(|tag| !self.taken.contains(tag)) else {
+      return Err(Error::new(span, "ran out of labels"));
+    };
+    self.taken.insert(tag);
+    self.labels.push((name, tag));
+    Ok(tag)
+  }
+
+  /// Checks that each var of the current net is used exactly twice.
+  fn check_vars(&self) -> Result<()> {
+    let mut errors = self.vars.iter().filter_map(|(name, uses)| match uses.len() {
+      2 => None,
+      1 => Some(Error::new(uses[0], format!("unpaired var `{name}`; vars must be used exactly twice"))),
+      _ => Some(Error::new(uses[2], format!("var `{name}` is used more than twice"))),
+    });
+    let Some(mut error) = errors.next() else { return Ok(()) };
+    errors.for_each(|other| error.combine(other));
+    Err(error)
+  }
+}
+
+/// Name = /[a-zA-Z0-9_.$\/]+/
+///
+/// A name such as `foo.bar` is several tokens; any `.`, `$` or `/` between two
+/// identifiers or literals is taken to be part of the name.
+fn parse_name(tokens: &mut Tokens) -> Result<String> {
+  let mut name = String::new();
+  loop {
+    match tokens.peek() {
+      Some(TokenTree::Ident(ident)) => name.push_str(&ident.to_string()),
+      Some(TokenTree::Literal(literal)) if literal.to_string().starts_with(|c: char| c.is_ascii_digit()) => {
+        name.push_str(&literal.to_string())
+      }
+      _ => return tokens.expected("a name"),
+    }
+    tokens.advance();
+    match (tokens.peek(), tokens.peek_nth(1)) {
+      (Some(TokenTree::Punct(punct)), Some(TokenTree::Ident(_) | TokenTree::Literal(_)))
+        if matches!(punct.as_char(), '.' | '$' | '/') =>
+      {
+        name.push(punct.as_char());
+        tokens.advance();
+      }
+      _ => return Ok(name),
+    }
+  }
+}
+
+/// Operator = one of the operators or types of hvm64 numbers, such as `+`, `<<`
+/// or `u24`.
+///
+/// Returns `None` without consuming anything if the next tokens aren't one.
+fn parse_operator(tokens: &mut Tokens) -> Option<NumTag> {
+  if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+    let op = ident.to_string().parse::<NumTag>().ok()?;
+    tokens.advance();
+    return Some(op);
+  }
+  // `:<<` is three joint tokens, and `-1` is `-` followed by a literal
+  let mut chars = String::new();
+  while let Some(TokenTree::Punct(punct)) = tokens.peek_nth(chars.len()) {
+    chars.push(punct.as_char());
+    if punct.spacing() == Spacing::Alone {
+      break;
+    }
+  }
+  let len = (1 ..= chars.len()).rev().find(|&len| chars[.. len].parse::<NumTag>().is_ok())?;
+  (0 .. len).for_each(|_| tokens.advance());
+  chars[.. len].parse().ok()
+}
+
+/// Num = "[" Operator NumLit? "]", given the tokens within the brackets; if
+/// they hold anything else, such as `* 2 3`, they are a list.
+fn parse_bracketed_num(tokens: &Tokens) -> Option<Num> {
+  let mut tokens = tokens.clone();
+  let op = parse_operator(&mut tokens)?;
+  let num = if op.is_ty() || tokens.is_empty() {
+    Num::new_sym(op)
+  } else {
+    Num::operate_unary(op, parse_num_lit(&mut tokens).ok()?)
+  };
+  tokens.is_empty().then_some(num)
+}
+
+/// NumLit = ("+" | "-")? Literal, or "+inf", etc.
+fn parse_num_lit(tokens: &mut Tokens) -> Result<Num> {
+  let span = tokens.span();
+  let mut text = String::new();
+  if let Some(TokenTree::Punct(punct)) = tokens.peek() {
+    if matches!(punct.as_char(), '+' | '-') {
+      text.push(punct.as_char());
+      tokens.advance();
+    }
+  }
+  match tokens.peek() {
+    Some(TokenTree::Literal(literal)) => text.push_str(&literal.to_string()),
+    Some(TokenTree::Ident(ident)) if !text.is_empty() => text.push_str(&ident.to_string()),
+    _ => return tokens.expected("a number"),
+  }
+  tokens.advance();
+  Num::parse_literal(&text).ok_or_else(|| Error::new(span, format!("invalid number `{text}`")))
+}
+
+fn num_tree(num: Num) -> TokenStream2 {
+  let raw = num.raw();
+  quote!(::hvm64_ast::__private::num(#raw))
+}
+
+/// DocComment = "///" /[^\n]*/, which Rust passes as `#[doc = ".."]`
+///
+/// Returns the doc comments before the next token, one per line, as they are
+/// stored in `Book::docs`.
+fn parse_doc(tokens: &mut Tokens) -> Result<String> {
+  let mut doc = String::new();
+  while tokens.consume('#') {
+    let group = tokens.group(&[Delimiter::Bracket], "`[`")?;
+    let attr = syn::parse2::<MetaNameValue>(group.stream())?;
+    let line = match &attr.value {
+      Expr::Lit(ExprLit { lit: Lit::Str(line), .. }) if attr.path.is_ident("doc") => line.value(),
+      _ => return Err(Error::new(group.span(), "expected a doc comment")),
+    };
+    if !doc.is_empty() {
+      doc.push('\n');
+    }
+    doc.push_str("///");
+    doc.push_str(line.trim_end());
+  }
+  Ok(doc)
+}
+
+/// Adds the tags of the numeric labels in `input`, such as the `1` of `#1{a
+/// b}`, to `tags`.
+fn numeric_tags(input: TokenStream2, tags: &mut HashSet<Lab>) {
+  let mut after_hash = false;
+  for token in input {
+    match &token {
+      TokenTree::Group(group) => numeric_tags(group.stream(), tags),
+      TokenTree::Literal(literal) if after_hash => {
+        if let Ok(tag) = literal.to_string().parse() {
+          tags.insert(tag);
+        }
+      }
+      _ => {}
+    }
+    after_hash = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '#');
+  }
+}
+
+/// A cursor over a sequence of tokens.
+#[derive(Clone)]
+struct Tokens {
+  tokens: Vec<TokenTree>,
+  index: usize,
+  /// Where the tokens end, for errors there, and a description of what ends
+  /// them.
+  end: (Span, &'static str),
+}
+
+impl Tokens {
+  fn new(stream: TokenStream2, span: Span, end: &'static str) -> Self {
+    Tokens { tokens: stream.into_iter().collect(), index: 0, end: (span, end) }
+  }
+
+  /// The tokens within `group`.
+  fn within(group: &Group) -> Self {
+    let end = match group.delimiter() {
+      Delimiter::Parenthesis => "`)`",
+      Delimiter::Brace => "`}`",
+      Delimiter::Bracket => "`]`",
+      Delimiter::None => "the end of the group",
+    };
+    Tokens::new(group.stream(), group.span_close(), end)
+  }
+
+  fn is_empty(&self) -> bool {
+    self.index == self.tokens.len()
+  }
+
+  fn peek(&self) -> Option<&TokenTree> {
+    self.peek_nth(0)
+  }
+
+  fn peek_nth(&self, n: usize) -> Option<&TokenTree> {
+    self.tokens.get(self.index + n)
+  }
+
+  fn advance(&mut self) {
+    self.index = (self.index + 1).min(self.tokens.len());
+  }
+
+  /// The span of the next token.
+  fn span(&self) -> Span {
+    self.peek().map_or(self.end.0, TokenTree::span)
+  }
+
+  /// Consumes the punctuation `char` if it is next.
+  fn consume(&mut self, char: char) -> bool {
+    let next = matches!(self.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == char);
+    if next {
+      self.advance();
+    }
+    next
+  }
+
+  fn expect(&mut self, char: char) -> Result<()> {
+    if self.consume(char) { Ok(()) } else { self.expected(&format!("`{char}`")) }
+  }
+
+  /// Consumes a group with one of the `delimiters`, described as `expected`.
+  fn group(&mut self, delimiters: &[Delimiter], expected: &str) -> Result<Group> {
+    match self.peek() {
+      Some(TokenTree::Group(group)) if delimiters.contains(&group.delimiter()) => {
+        let group = group.clone();
+        self.advance();
+        Ok(group)
+      }
+      _ => self.expected(expected),
+    }
+  }
+
+  fn expect_end(&self) -> Result<()> {
+    if self.is_empty() { Ok(()) } else { self.expected(self.end.1) }
+  }
+
+  fn expected<T>(&self, expected: &str) -> Result<T> {
+    Err(Error::new(self.span(), format!("expected {expected}")))
+  }
+}
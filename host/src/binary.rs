@@ -0,0 +1,558 @@
+//! The `.hvmb` binary format, which stores a book, and optionally the label
+//! sets and instructions of its defs, so that loading it does not require
+//! parsing, transforming, or encoding anything.
+//!
+//! All integers are LEB128 varints. A file consists of:
+//! - the magic bytes `HVMB`, a version byte, and a flags byte
+//! - a table of strings, which are referred to by their index
+//! - the number of nets, and each net, with the index of its name
+//! - if [`FLAG_DEFS`] is set, the label set and instructions of each net, in
+//!   the same order
+
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use core::str;
+
+use crate::{Host, MissingRefs};
+use hvm64_ast::{Book, Net, Tree};
+use hvm64_num::{Num, NumTag};
+use hvm64_runtime::{Def, Instruction, InterpretedDef, Lab, LabSet, Port, Tag, TrgId, MAX_TUP_ARITY};
+use hvm64_util::{maybe_grow, prelude::*};
+
+const MAGIC: &[u8; 4] = b"HVMB";
+const VERSION: u8 = 1;
+/// Set if the label sets and instructions of the defs are included.
+const FLAG_DEFS: u8 = 1;
+
+/// An error reading a `.hvmb` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+  /// The data does not start with the `.hvmb` magic bytes.
+  NotBinary,
+  /// The data was written by an incompatible version of hvm64.
+  UnsupportedVersion(u8),
+  /// The data ended unexpectedly.
+  UnexpectedEnd,
+  /// The data is malformed.
+  Invalid(&'static str),
+  /// The defs refer to defs that are in neither the book nor the host.
+  MissingRefs(MissingRefs),
+}
+
+impl fmt::Display for BinaryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      BinaryError::NotBinary => write!(f, "not a .hvmb file"),
+      BinaryError::UnsupportedVersion(v) => write!(f, "unsupported .hvmb version {v} (expected {VERSION})"),
+      BinaryError::UnexpectedEnd => write!(f, "unexpected end of .hvmb data"),
+      BinaryError::Invalid(what) => write!(f, "invalid .hvmb data: {what}"),
+      BinaryError::MissingRefs(e) => write!(f, "{e}"),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl Error for BinaryError {}
+
+/// Serializes `book` to the `.hvmb` format, without its defs.
+pub fn book_to_binary(book: &Book) -> Vec<u8> {
+  Writer::default().finish(book, None)
+}
+
+/// Deserializes a book from the `.hvmb` format, ignoring its defs, if any.
+pub fn book_from_binary(bytes: &[u8]) -> Result<Book, BinaryError> {
+  Ok(Reader::new(bytes)?.book()?.0)
+}
+
+impl Host {
+  /// Serializes `book` to the `.hvmb` format, along with the label sets and
+  /// instructions of its defs in this host, into which it must have been
+  /// inserted.
+  pub fn to_binary(&self, book: &Book) -> Vec<u8> {
+    Writer::default().finish(book, Some(self))
+  }
+
+  /// Deserializes a book from the `.hvmb` format, inserts it into the host, and
+  /// returns it.
+  ///
+  /// If the data includes the defs of the book, they are inserted directly,
+  /// skipping [`Host::encode_def`] and the label set calculation. Refs to defs
+  /// outside of the book are assumed to have the same label sets as when the
+  /// data was written.
+  pub fn load_binary(&mut self, bytes: &[u8]) -> Result<Book, BinaryError> {
+    let mut reader = Reader::new(bytes)?;
+    let (book, names) = reader.book()?;
+    if reader.flags & FLAG_DEFS == 0 {
+      self.try_insert_book(&book).map_err(BinaryError::MissingRefs)?;
+      return Ok(book);
+    }
+
+    let mut defs = vec![];
+    for _ in &names {
+      defs.push(reader.def()?);
+    }
+    if reader.pos != reader.bytes.len() {
+      return Err(BinaryError::Invalid("trailing data"));
+    }
+
+    let mut missing = Vec::<(String, Vec<String>)>::new();
+    for (name, (_, _, refs)) in names.iter().zip(&defs) {
+      for (_, r) in refs {
+        let r = &reader.strings[*r];
+        if book.contains_key(r) || self.defs.contains_key(r) {
+          continue;
+        }
+        match missing.iter_mut().find(|(m, _)| m == r) {
+          Some((_, referrers)) if referrers.contains(name) => {}
+          Some((_, referrers)) => referrers.push(name.clone()),
+          None => missing.push((r.clone(), vec![name.clone()])),
+        }
+      }
+    }
+    if !missing.is_empty() {
+      missing.sort();
+      return Err(BinaryError::MissingRefs(MissingRefs(missing)));
+    }
+
+    // As in `insert_book_with_default`, the defs are first inserted without
+    // instructions, so that refs to them can be created.
+    for (name, (labs, ..)) in names.iter().zip(&mut defs) {
+      self.swap_def(name, Box::new(Def::new(mem::take(labs), InterpretedDef::default())));
+    }
+    for (name, (_, mut data, refs)) in names.iter().zip(defs) {
+      for (i, r) in refs {
+        *data.instr[i].const_port_mut().unwrap() = Port::new_ref(&self.defs[&reader.strings[r]]);
+      }
+      self.get_mut::<InterpretedDef>(name).data = data;
+    }
+    self.update_inserted_label_nodes(&book);
+
+    Ok(book)
+  }
+}
+
+#[derive(Default)]
+struct Writer {
+  strings: Vec<String>,
+  indices: Map<String, usize>,
+  out: Vec<u8>,
+}
+
+impl Writer {
+  fn finish(mut self, book: &Book, host: Option<&Host>) -> Vec<u8> {
+    self.varint(book.len());
+    for (name, net) in book.iter() {
+      self.string(name);
+      self.net(net);
+    }
+    if let Some(host) = host {
+      for name in book.keys() {
+        let def = Def::downcast_ref::<InterpretedDef>(&host.defs[name]).unwrap();
+        self.def(host, &def.labs, &def.data);
+      }
+    }
+
+    let body = mem::take(&mut self.out);
+    self.out.extend_from_slice(MAGIC);
+    self.out.push(VERSION);
+    self.out.push(if host.is_some() { FLAG_DEFS } else { 0 });
+    self.varint(self.strings.len());
+    for string in mem::take(&mut self.strings) {
+      self.varint(string.len());
+      self.out.extend_from_slice(string.as_bytes());
+    }
+    self.out.extend_from_slice(&body);
+    self.out
+  }
+
+  fn varint(&mut self, mut value: usize) {
+    while value >= 0x80 {
+      self.out.push(value as u8 | 0x80);
+      value >>= 7;
+    }
+    self.out.push(value as u8);
+  }
+
+  fn string(&mut self, string: &str) {
+    let index = match self.indices.get(string) {
+      Some(&index) => index,
+      None => {
+        self.strings.push(string.to_owned());
+        self.indices.insert(string.to_owned(), self.strings.len() - 1);
+        self.strings.len() - 1
+      }
+    };
+    self.varint(index);
+  }
+
+  fn num(&mut self, num: Num) {
+    self.out.push(num.tag() as u8);
+    self.varint(num.payload() as usize);
+  }
+
+  fn net(&mut self, net: &Net) {
+    self.varint(net.redexes.len());
+    self.tree(&net.root);
+    for (a, b) in &net.redexes {
+      self.tree(a);
+      self.tree(b);
+    }
+  }
+
+  fn tree(&mut self, tree: &Tree) {
+    maybe_grow(|| match tree {
+      Tree::Era => self.out.push(0),
+      Tree::Num(num) => {
+        self.out.push(1);
+        self.num(*num);
+      }
+      Tree::Ref(name) => {
+        self.out.push(2);
+        self.string(name);
+      }
+      Tree::Ctr { lab, p1, p2 } => {
+        self.out.push(3);
+        self.varint(*lab as usize);
+        self.tree(p1);
+        self.tree(p2);
+      }
+      Tree::Op { rhs, out } => {
+        self.out.push(4);
+        self.tree(rhs);
+        self.tree(out);
+      }
+      Tree::Switch { arms, out } => {
+        self.out.push(5);
+        self.tree(arms);
+        self.tree(out);
+      }
+      Tree::Var(name) => {
+        self.out.push(6);
+        self.string(name);
+      }
+    })
+  }
+
+  fn def(&mut self, host: &Host, labs: &LabSet, def: &InterpretedDef) {
+    if labs == &LabSet::ALL {
+      self.out.push(1);
+    } else {
+      self.out.push(0);
+      self.varint(labs.bits().len());
+      for &word in labs.bits() {
+        self.out.extend_from_slice(&word.to_le_bytes());
+      }
+    }
+    self.varint(def.trgs);
+    self.varint(def.instr.len());
+    for instr in &def.instr {
+      match instr {
+        Instruction::Const { trg, port } => {
+          self.out.push(0);
+          self.trg(*trg);
+          self.port(host, port);
+        }
+        Instruction::Link { a, b } => {
+          self.out.push(1);
+          self.trg(*a);
+          self.trg(*b);
+        }
+        Instruction::LinkConst { trg, port } => {
+          self.out.push(2);
+          self.trg(*trg);
+          self.port(host, port);
+        }
+        Instruction::Ctr { lab, trg, p1, p2 } => {
+          self.out.push(3);
+          self.varint(*lab as usize);
+          self.trgs(&[*trg, *p1, *p2]);
+        }
+        Instruction::Op { op, trg, rhs, out } => {
+          self.out.push(4);
+          self.out.push(*op as u8);
+          self.trgs(&[*trg, *rhs, *out]);
+        }
+        Instruction::OpNum { op, trg, rhs, out } => {
+          self.out.push(5);
+          self.out.push(*op as u8);
+          self.num(*rhs);
+          self.trgs(&[*trg, *out]);
+        }
+        Instruction::Switch { trg, arms, out } => {
+          self.out.push(6);
+          self.trgs(&[*trg, *arms, *out]);
+        }
+        Instruction::Wires { av, aw, bv, bw } => {
+          self.out.push(7);
+          self.trgs(&[*av, *aw, *bv, *bw]);
+        }
+        Instruction::Tup { lab, trg, ports } => {
+          self.out.push(8);
+          self.varint(*lab as usize);
+          self.trg(*trg);
+          self.varint(ports.len());
+          self.trgs(ports);
+        }
+      }
+    }
+  }
+
+  fn trg(&mut self, trg: TrgId) {
+    self.varint(trg.index());
+  }
+
+  fn trgs(&mut self, trgs: &[TrgId]) {
+    for &trg in trgs {
+      self.trg(trg);
+    }
+  }
+
+  fn port(&mut self, host: &Host, port: &Port) {
+    if port.is_era() {
+      self.out.push(0);
+    } else if port.tag() == Tag::Ref {
+      self.out.push(2);
+      self.string(&host.back[&port.addr()]);
+    } else {
+      self.out.push(1);
+      self.num(port.num());
+    }
+  }
+}
+
+/// The label set, instructions, and refs of a def; each ref is the index of an
+/// instruction and the string index of the name of the def it refers to.
+type RawDef = (LabSet, InterpretedDef, Vec<(usize, usize)>);
+
+struct Reader<'a> {
+  bytes: &'a [u8],
+  pos: usize,
+  flags: u8,
+  strings: Vec<String>,
+}
+
+impl<'a> Reader<'a> {
+  fn new(bytes: &'a [u8]) -> Result<Self, BinaryError> {
+    if !bytes.starts_with(MAGIC) {
+      return Err(BinaryError::NotBinary);
+    }
+    let mut reader = Reader { bytes, pos: MAGIC.len(), flags: 0, strings: vec![] };
+    let version = reader.byte()?;
+    if version != VERSION {
+      return Err(BinaryError::UnsupportedVersion(version));
+    }
+    reader.flags = reader.byte()?;
+    for _ in 0 .. reader.varint()? {
+      let len = reader.varint()?;
+      let bytes = reader.bytes.get(reader.pos .. reader.pos.saturating_add(len)).ok_or(BinaryError::UnexpectedEnd)?;
+      reader.pos += len;
+      let string = str::from_utf8(bytes).map_err(|_| BinaryError::Invalid("string is not utf-8"))?;
+      reader.strings.push(string.to_owned());
+    }
+    Ok(reader)
+  }
+
+  fn byte(&mut self) -> Result<u8, BinaryError> {
+    let byte = *self.bytes.get(self.pos).ok_or(BinaryError::UnexpectedEnd)?;
+    self.pos += 1;
+    Ok(byte)
+  }
+
+  fn varint(&mut self) -> Result<usize, BinaryError> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+      let byte = self.byte()?;
+      if shift >= usize::BITS || (byte as usize & 0x7f) << shift >> shift != byte as usize & 0x7f {
+        return Err(BinaryError::Invalid("varint is too large"));
+      }
+      value |= (byte as usize & 0x7f) << shift;
+      shift += 7;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+  }
+
+  fn string_index(&mut self) -> Result<usize, BinaryError> {
+    let index = self.varint()?;
+    if index >= self.strings.len() {
+      return Err(BinaryError::Invalid("string index is out of bounds"));
+    }
+    Ok(index)
+  }
+
+  fn string(&mut self) -> Result<String, BinaryError> {
+    let index = self.string_index()?;
+    Ok(self.strings[index].clone())
+  }
+
+  fn lab(&mut self) -> Result<Lab, BinaryError> {
+    Lab::try_from(self.varint()?).map_err(|_| BinaryError::Invalid("label is too large"))
+  }
+
+  fn num_tag(&mut self) -> Result<NumTag, BinaryError> {
+    NumTag::try_from(self.byte()?).map_err(|_| BinaryError::Invalid("invalid number tag"))
+  }
+
+  fn num(&mut self) -> Result<Num, BinaryError> {
+    let tag = self.num_tag()?;
+    let payload = u32::try_from(self.varint()?).ok().filter(|&p| p < 1 << 24);
+    let payload = payload.ok_or(BinaryError::Invalid("number is too large"))?;
+    if tag == NumTag::Sym && u8::try_from(payload).ok().and_then(|p| NumTag::try_from(p).ok()).is_none() {
+      return Err(BinaryError::Invalid("invalid number tag"));
+    }
+    Ok(unsafe { Num::new(tag, payload) })
+  }
+
+  /// Reads the book, and the names of its nets, in order.
+  fn book(&mut self) -> Result<(Book, Vec<String>), BinaryError> {
+    let mut book = Book::default();
+    let mut names = vec![];
+    for _ in 0 .. self.varint()? {
+      let name = self.string()?;
+      let net = self.net()?;
+      if book.nets.insert(name.clone(), net).is_some() {
+        return Err(BinaryError::Invalid("duplicate net"));
+      }
+      names.push(name);
+    }
+    Ok((book, names))
+  }
+
+  fn net(&mut self) -> Result<Net, BinaryError> {
+    let redexes = self.varint()?;
+    let root = self.tree()?;
+    let mut net = Net { root, redexes: vec![] };
+    for _ in 0 .. redexes {
+      net.redexes.push((self.tree()?, self.tree()?));
+    }
+    Ok(net)
+  }
+
+  fn tree(&mut self) -> Result<Tree, BinaryError> {
+    maybe_grow(|| {
+      Ok(match self.byte()? {
+        0 => Tree::Era,
+        1 => Tree::Num(self.num()?),
+        2 => Tree::Ref(self.string()?),
+        3 => Tree::Ctr { lab: self.lab()?, p1: Box::new(self.tree()?), p2: Box::new(self.tree()?) },
+        4 => Tree::Op { rhs: Box::new(self.tree()?), out: Box::new(self.tree()?) },
+        5 => Tree::Switch { arms: Box::new(self.tree()?), out: Box::new(self.tree()?) },
+        6 => Tree::Var(self.string()?),
+        _ => return Err(BinaryError::Invalid("invalid tree tag")),
+      })
+    })
+  }
+
+  fn def(&mut self) -> Result<RawDef, BinaryError> {
+    let labs = match self.byte()? {
+      0 => {
+        let mut labs = LabSet::default();
+        let words = self.varint()?;
+        if words > 1024 {
+          return Err(BinaryError::Invalid("label set is too large"));
+        }
+        for i in 0 .. words {
+          let bytes = self.bytes.get(self.pos .. self.pos + 8).ok_or(BinaryError::UnexpectedEnd)?;
+          self.pos += 8;
+          let word = u64::from_le_bytes(bytes.try_into().unwrap());
+          for bit in 0 .. 64 {
+            if word & 1 << bit != 0 {
+              labs.add(
+                Lab::try_from(i * 64 + bit)
+                  .ok()
+                  .filter(|&l| l < Lab::MAX)
+                  .ok_or(BinaryError::Invalid("label is too large"))?,
+              );
+            }
+          }
+        }
+        labs
+      }
+      1 => LabSet::ALL,
+      _ => return Err(BinaryError::Invalid("invalid label set")),
+    };
+
+    // Which registers have been written and not yet read; the interpreter
+    // relies on registers only being read after they are written.
+    let mut live = vec![false; self.varint()?];
+    *live.first_mut().ok_or(BinaryError::Invalid("def has no registers"))? = true;
+    let mut def = InterpretedDef { instr: vec![], trgs: live.len() };
+    let mut refs = vec![];
+    let l = &mut live;
+    for _ in 0 .. self.varint()? {
+      let i = def.instr.len();
+      let instr = match self.byte()? {
+        0 => Instruction::Const { trg: self.trg(l, true)?, port: self.port(&mut refs, i)? },
+        1 => Instruction::Link { a: self.trg(l, false)?, b: self.trg(l, false)? },
+        2 => Instruction::LinkConst { trg: self.trg(l, false)?, port: self.port(&mut refs, i)? },
+        3 => Instruction::Ctr {
+          lab: self.lab()?,
+          trg: self.trg(l, false)?,
+          p1: self.trg(l, true)?,
+          p2: self.trg(l, true)?,
+        },
+        4 => Instruction::Op {
+          op: self.num_tag()?,
+          trg: self.trg(l, false)?,
+          rhs: self.trg(l, true)?,
+          out: self.trg(l, true)?,
+        },
+        5 => Instruction::OpNum {
+          op: self.num_tag()?,
+          rhs: self.num()?,
+          trg: self.trg(l, false)?,
+          out: self.trg(l, true)?,
+        },
+        6 => Instruction::Switch { trg: self.trg(l, false)?, arms: self.trg(l, true)?, out: self.trg(l, true)? },
+        7 => Instruction::Wires {
+          av: self.trg(l, true)?,
+          aw: self.trg(l, true)?,
+          bv: self.trg(l, true)?,
+          bw: self.trg(l, true)?,
+        },
+        8 => {
+          let lab = self.lab()?;
+          let trg = self.trg(l, false)?;
+          let arity = self.varint()?;
+          if !(3 ..= MAX_TUP_ARITY).contains(&arity) {
+            return Err(BinaryError::Invalid("invalid tuple arity"));
+          }
+          let ports = (0 .. arity).map(|_| self.trg(l, true)).collect::<Result<_, _>>()?;
+          Instruction::Tup { lab, trg, ports }
+        }
+        _ => return Err(BinaryError::Invalid("invalid instruction")),
+      };
+      def.instr.push(instr);
+    }
+    Ok((labs, def, refs))
+  }
+
+  /// Reads a register, which is written to by the instruction if `write` is
+  /// set, and read from otherwise.
+  fn trg(&mut self, live: &mut [bool], write: bool) -> Result<TrgId, BinaryError> {
+    let index = self.varint()?;
+    let live = live.get_mut(index).ok_or(BinaryError::Invalid("register is out of bounds"))?;
+    if *live == write {
+      return Err(BinaryError::Invalid("register is used before it is written"));
+    }
+    *live = write;
+    Ok(TrgId::new(index))
+  }
+
+  /// Reads a port; refs are read as `Port::ERA`, and recorded in `refs`, to be
+  /// filled in once all of the defs have been inserted.
+  fn port(&mut self, refs: &mut Vec<(usize, usize)>, instr: usize) -> Result<Port, BinaryError> {
+    Ok(match self.byte()? {
+      0 => Port::ERA,
+      1 => Port::new_num(self.num()?),
+      2 => {
+        refs.push((instr, self.string_index()?));
+        Port::ERA
+      }
+      _ => return Err(BinaryError::Invalid("invalid port")),
+    })
+  }
+}
@@ -117,6 +117,174 @@ enum LabelState {
   Cycle(usize),
 }
 
+/// The dependencies between the label sets of the interpreted defs of a
+/// [`Host`], which allow updating them incrementally when defs are replaced.
+#[derive(Default)]
+pub(crate) struct LabelGraph {
+  /// for each interpreted def, the labels it uses directly, and the names of
+  /// the defs it refers to
+  nodes: Map<String, (LabSet, Vec<String>)>,
+  /// for each def, the names of the interpreted defs that refer to it
+  referrers: Map<String, Set<String>>,
+}
+
+impl LabelGraph {
+  /// The names of the interpreted defs that refer to the def `name`.
+  pub(crate) fn referrers<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> + 'a {
+    self.referrers.get(name).into_iter().flatten().map(String::as_str)
+  }
+}
+
+impl Host {
+  /// Updates the node of the def `name` in the label graph from its
+  /// instructions, without changing any label sets.
+  pub(crate) fn update_label_node(&mut self, name: &str) {
+    let node = self.defs.get(name).and_then(|def| Def::downcast_ref::<InterpretedDef>(def)).map(|def| {
+      let mut own = LabSet::default();
+      let mut refs = vec![];
+      for instr in def.data.instructions() {
+        match instr {
+          Instruction::Ctr { lab, .. } | Instruction::Tup { lab, .. } => own.add(*lab),
+          _ => match instr.const_port() {
+            Some(port) if port.tag() == Tag::Ref && *port != Port::ERA => match self.back.get(&port.addr()) {
+              Some(r) => refs.push(r.clone()),
+              // a ref to a def that the host does not know of could use any
+              // label
+              None => own = LabSet::ALL,
+            },
+            _ => {}
+          },
+        }
+      }
+      refs.sort();
+      refs.dedup();
+      (own, refs)
+    });
+
+    if let Some((_, refs)) = self.labels.nodes.remove(name) {
+      for r in refs {
+        let Entry::Occupied(mut e) = self.labels.referrers.entry(r) else { unreachable!() };
+        e.get_mut().remove(name);
+        if e.get().is_empty() {
+          e.remove();
+        }
+      }
+    }
+    if let Some((own, refs)) = node {
+      for r in &refs {
+        self.labels.referrers.entry(r.clone()).or_default().insert(name.to_owned());
+      }
+      self.labels.nodes.insert(name.to_owned(), (own, refs));
+    }
+  }
+
+  /// Recalculates the label sets of the defs in `changed`, and of all
+  /// interpreted defs that transitively refer to them; all other label sets are
+  /// assumed to be up-to-date. As ref ports include information about the label
+  /// set of the def they refer to, the ref ports in the instructions of the
+  /// affected defs are then updated.
+  ///
+  /// The affected defs are processed one strongly connected component at a
+  /// time, with each component after all of the components it refers to, so
+  /// each def is only visited once.
+  pub(crate) fn update_label_sets(&mut self, changed: Vec<String>) {
+    for name in &changed {
+      self.update_label_node(name);
+    }
+
+    let mut affected = Set::new();
+    let mut stack = changed;
+    while let Some(name) = stack.pop() {
+      if !affected.contains(&name) {
+        stack.extend(self.labels.referrers(&name).map(ToOwned::to_owned));
+        affected.insert(name);
+      }
+    }
+    let mut tarjan =
+      Tarjan { graph: &self.labels, affected: &affected, index: Map::new(), stack: vec![], sccs: vec![] };
+    for name in &affected {
+      if !tarjan.index.contains_key(name.as_str()) {
+        tarjan.visit(name);
+      }
+    }
+
+    let mut labs = Map::<&str, LabSet>::new();
+    for scc in tarjan.sccs {
+      let mut scc_labs = LabSet::default();
+      for &member in &scc {
+        let Some((own, refs)) = self.labels.nodes.get(member) else { continue };
+        scc_labs.union(own);
+        for r in refs.iter().map(String::as_str).filter(|r| !scc.contains(r)) {
+          match labs.get(r) {
+            Some(r) => scc_labs.union(r),
+            None => scc_labs.union(self.defs.get(r).map_or(&LabSet::ALL, |def| &def.labs)),
+          }
+        }
+      }
+      for member in scc {
+        if self.labels.nodes.contains_key(member) {
+          labs.insert(member, scc_labs.clone());
+        }
+      }
+    }
+
+    let mut ports = Map::<Addr, Port>::new();
+    for (name, labs) in labs {
+      let def = self.defs.get_mut(name).unwrap();
+      def.labs = labs;
+      ports.insert(Port::new_ref(def).addr(), Port::new_ref(def));
+    }
+    for name in &affected {
+      let Some(def) = self.defs.get_mut(name) else { continue };
+      let Some(def) = Def::downcast_mut::<InterpretedDef>(def) else { continue };
+      for port in def.data.instr.iter_mut().filter_map(Instruction::const_port_mut) {
+        if let Some(new) = ports.get(&port.addr()).filter(|_| port.tag() == Tag::Ref) {
+          *port = new.clone();
+        }
+      }
+    }
+  }
+}
+
+/// Tarjan's algorithm for finding the strongly connected components of the
+/// subgraph of a [`LabelGraph`] induced by `affected`.
+struct Tarjan<'a> {
+  graph: &'a LabelGraph,
+  affected: &'a Set<String>,
+  /// the index of each visited node, or `None` once its component is complete
+  index: Map<&'a str, Option<usize>>,
+  stack: Vec<&'a str>,
+  /// the components, each after all of the components it refers to
+  sccs: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Tarjan<'a> {
+  /// Returns the lowest index reachable from `name` within its component.
+  fn visit(&mut self, name: &'a str) -> usize {
+    let index = self.index.len();
+    self.index.insert(name, Some(index));
+    self.stack.push(name);
+    let mut low = index;
+    let graph = self.graph;
+    let refs = graph.nodes.get(name).into_iter().flat_map(|(_, refs)| refs);
+    for r in refs.filter(|r| self.affected.contains(*r)) {
+      low = low.min(match self.index.get(r.as_str()) {
+        None => maybe_grow(|| self.visit(r)),
+        Some(&Some(index)) => index,
+        Some(None) => continue,
+      });
+    }
+    if low == index {
+      let scc = self.stack.split_off(self.stack.iter().rposition(|&n| n == name).unwrap());
+      for &member in &scc {
+        self.index.insert(member, None);
+      }
+      self.sccs.push(scc);
+    }
+    low
+  }
+}
+
 /// All of these methods share a similar signature:
 /// - `depth` is optional; `None` indicates that this is the second processing
 ///   pass (where the depth is irrelevant, as all cycles have been detected)
@@ -3,7 +3,7 @@ use hvm64_util::prelude::*;
 use crate::Host;
 use hvm64_ast::{Lab, Net as AstNet, Tree};
 use hvm64_num::{Num, NumTag};
-use hvm64_runtime::{Instruction, InterpretedDef, Net, Port, Trg, TrgId};
+use hvm64_runtime::{Instruction, InterpretedDef, Net, Port, Trg, TrgId, MAX_TUP_ARITY};
 use hvm64_util::maybe_grow;
 
 impl Host {
@@ -71,9 +71,28 @@ impl<'a, E: Encoder> State<'a, E> {
       Tree::Num(num) => self.encoder.link_const(trg, Port::new_num(*num)),
       Tree::Ref(name) => self.encoder.link_const(trg, Port::new_ref(&self.host.defs[name])),
       Tree::Ctr { lab, p1, p2 } => {
-        let (l, r) = self.encoder.ctr(*lab, trg);
-        self.visit_tree(p1, l);
-        self.visit_tree(p2, r);
+        // nested constructors with the same label are encoded as a single
+        // n-ary node, so that they annihilate in one interaction
+        let mut ports = vec![&**p1];
+        let mut rest = &**p2;
+        while let Tree::Ctr { lab: l, p1, p2 } = rest {
+          if *l != *lab || ports.len() + 2 > MAX_TUP_ARITY {
+            break;
+          }
+          ports.push(p1);
+          rest = p2;
+        }
+        ports.push(rest);
+        if let [p1, p2] = ports[..] {
+          let (l, r) = self.encoder.ctr(*lab, trg);
+          self.visit_tree(p1, l);
+          self.visit_tree(p2, r);
+        } else {
+          let trgs = self.encoder.tup(*lab, trg, ports.len());
+          for (port, trg) in ports.into_iter().zip(trgs) {
+            self.visit_tree(port, trg);
+          }
+        }
       }
       Tree::Op { rhs, out } => {
         let (op, rhs, out) = match (&**rhs, &**out) {
@@ -110,6 +129,7 @@ trait Encoder {
   fn link(&mut self, a: Self::Trg, b: Self::Trg);
   fn make_const(&mut self, port: Port) -> Self::Trg;
   fn ctr(&mut self, lab: Lab, trg: Self::Trg) -> (Self::Trg, Self::Trg);
+  fn tup(&mut self, lab: Lab, trg: Self::Trg, arity: usize) -> Vec<Self::Trg>;
   fn op(&mut self, op: NumTag, trg: Self::Trg) -> (Self::Trg, Self::Trg);
   fn op_num(&mut self, op: NumTag, trg: Self::Trg, rhs: Num) -> Self::Trg;
   fn switch(&mut self, trg: Self::Trg) -> (Self::Trg, Self::Trg);
@@ -135,6 +155,11 @@ impl Encoder for InterpretedDef {
     self.instr.push(Instruction::Ctr { lab, trg, p1, p2 });
     (p1, p2)
   }
+  fn tup(&mut self, lab: Lab, trg: Self::Trg, arity: usize) -> Vec<Self::Trg> {
+    let ports = (0 .. arity).map(|_| self.new_trg_id()).collect::<Vec<_>>();
+    self.instr.push(Instruction::Tup { lab, trg, ports: ports.clone() });
+    ports
+  }
   fn op(&mut self, op: NumTag, trg: Self::Trg) -> (Self::Trg, Self::Trg) {
     let rhs = self.new_trg_id();
     let out = self.new_trg_id();
@@ -177,6 +202,11 @@ impl<'a> Encoder for Net<'a> {
   fn ctr(&mut self, lab: Lab, trg: Self::Trg) -> (Self::Trg, Self::Trg) {
     self.do_ctr(lab, trg)
   }
+  fn tup(&mut self, lab: Lab, trg: Self::Trg, arity: usize) -> Vec<Self::Trg> {
+    let mut ports = Vec::with_capacity(arity);
+    self.do_tup(lab, trg, arity, |trg| ports.push(trg));
+    ports
+  }
   fn op(&mut self, op: NumTag, trg: Self::Trg) -> (Self::Trg, Self::Trg) {
     self.do_op(op, trg)
   }
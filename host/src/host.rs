@@ -3,14 +3,47 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use hvm64_ast::{Book, Tree};
-use hvm64_runtime::{Addr, Def, DynDef, InterpretedDef, LabSet, Port, Tag, Wire};
+use hvm64_runtime::{Addr, Def, DynDef, Instruction, InterpretedDef, LabSet, Native, NativeDef, Port, Tag, Wire};
 use hvm64_util::prelude::*;
 
+mod binary;
 mod calc_labels;
 mod encode;
+mod lambda;
 mod readback;
+mod stdlib;
+mod value;
 
-use calc_labels::calculate_label_sets;
+use calc_labels::{calculate_label_sets, LabelGraph};
+
+pub use binary::{book_from_binary, book_to_binary, BinaryError};
+pub use hvm64_derive::{FromNet, IntoNet};
+pub use lambda::{decode_data, decode_lambda};
+pub use readback::{ElidedSubtrees, ReadbackDiagnostics};
+pub use value::{
+  CallError, DecodeError, EncodeError, Encoding, FromNet, IntoArgs, IntoNet, ValueDecoder, ValueEncoder,
+};
+
+/// The refs of a book that are in neither the book nor the host, each with the
+/// names of the defs that refer to it; see [`Host::try_insert_book`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingRefs(pub Vec<(String, Vec<String>)>);
+
+impl fmt::Display for MissingRefs {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "missing definitions:")?;
+    for (name, referrers) in &self.0 {
+      write!(f, "\n  @{name}, referenced by ")?;
+      for (i, referrer) in referrers.iter().enumerate() {
+        if i != 0 {
+          write!(f, ", ")?;
+        }
+        write!(f, "@{referrer}")?;
+      }
+    }
+    Ok(())
+  }
+}
 
 /// Stores a bidirectional mapping between names and runtime defs.
 #[derive(Default)]
@@ -19,6 +52,8 @@ pub struct Host {
   pub defs: Map<String, Box<DynDef>>,
   /// the backward mapping, from the address of a runtime def to the name
   pub back: Map<Addr, String>,
+  /// the dependencies between the label sets of the interpreted defs
+  labels: LabelGraph,
 }
 
 impl Host {
@@ -35,6 +70,39 @@ impl Host {
     self.insert_book_with_default(book, &mut |x| panic!("Found reference {x:?}, which is not in the book!"))
   }
 
+  /// Like `insert_book`, but rather than panicking if the book has refs that
+  /// are not in the book or the host, returns all of them, and does not insert
+  /// anything.
+  pub fn try_insert_book(&mut self, book: &Book) -> Result<(), MissingRefs> {
+    let mut missing = Vec::<(String, Vec<String>)>::new();
+    for (name, net) in book.iter() {
+      let mut trees = net.trees().collect::<Vec<_>>();
+      while let Some(tree) = trees.pop() {
+        trees.extend(tree.children());
+        let Tree::Ref(r) = tree else { continue };
+        if book.contains_key(r) || self.defs.contains_key(r) {
+          continue;
+        }
+        let referrers = match missing.iter_mut().find(|(m, _)| m == r) {
+          Some((_, referrers)) => referrers,
+          None => {
+            missing.push((r.clone(), vec![]));
+            &mut missing.last_mut().unwrap().1
+          }
+        };
+        if !referrers.contains(name) {
+          referrers.push(name.clone());
+        }
+      }
+    }
+    if !missing.is_empty() {
+      missing.sort();
+      return Err(MissingRefs(missing));
+    }
+    self.insert_book(book);
+    Ok(())
+  }
+
   /// Like `insert_book`, but allows specifying a function (`default_def`) that
   /// will be run when the name of a definition is not found in the book.
   /// The return value of the function will be inserted into the host.
@@ -61,21 +129,124 @@ impl Host {
     .into_iter()
     {
       let def = Box::new(Def::new(labs, InterpretedDef::default()));
-      self.insert_def(name, def);
+      self.swap_def(name, def);
     }
 
     // Now that `defs` is fully populated, we can fill in the instructions of
     // each of the new defs.
     for (name, net) in book.iter() {
-      let data = self.encode_def(net);
+      let mut data = self.encode_def(net);
+      data.optimize();
       self.get_mut::<InterpretedDef>(name).data = data;
     }
+
+    self.update_inserted_label_nodes(book);
+  }
+
+  /// Updates the label graph after the defs of `book` were inserted with
+  /// correct label sets; defs outside of the book may have referred to defs
+  /// that were replaced, so their label sets are recalculated.
+  fn update_inserted_label_nodes(&mut self, book: &Book) {
+    for name in book.keys() {
+      self.update_label_node(name);
+    }
+    let outside = book
+      .keys()
+      .flat_map(|name| self.labels.referrers(name))
+      .filter(|referrer| !book.contains_key(*referrer))
+      .map(ToOwned::to_owned)
+      .collect::<Vec<_>>();
+    if !outside.is_empty() {
+      self.update_label_sets(outside);
+    }
   }
 
   /// Inserts a singular def into the mapping.
+  ///
+  /// This must not be used to overwrite an existing def, as other defs may
+  /// still refer to it; use [`Host::replace_def`] instead.
   pub fn insert_def(&mut self, name: &str, def: Box<DynDef>) {
     self.back.insert(Port::new_ref(&def).addr(), name.to_owned());
     self.defs.insert(name.to_owned(), def);
+    self.update_label_node(name);
+  }
+
+  /// Inserts a native def, which is called for every agent it interacts with;
+  /// see [`NativeDef`]. As with [`Host::insert_def`], this must not be used to
+  /// overwrite an existing def.
+  pub fn insert_native(&mut self, name: &str, def: impl NativeDef) {
+    self.insert_def(name, Box::new(Def::new(LabSet::ALL, Native(def))));
+  }
+
+  /// Replaces the def named `name` with `def`, or inserts it if there is no
+  /// such def.
+  ///
+  /// All interpreted defs that referred to the old def are updated to refer to
+  /// the new one, and the label sets of the new def and of all interpreted defs
+  /// that depend on it are recalculated.
+  ///
+  /// The old def is returned; it must not be dropped while any net that may
+  /// refer to it is still being reduced.
+  pub fn replace_def(&mut self, name: &str, def: Box<DynDef>) -> Option<Box<DynDef>> {
+    let old = self.swap_def(name, def);
+    self.update_label_sets(vec![name.to_owned()]);
+    old
+  }
+
+  /// Inserts `def`, redirecting all refs to the def it replaces, if any, to it.
+  fn swap_def(&mut self, name: &str, def: Box<DynDef>) -> Option<Box<DynDef>> {
+    let new = Port::new_ref(&def);
+    let old = self.defs.insert(name.to_owned(), def);
+    self.back.insert(new.addr(), name.to_owned());
+    if let Some(old) = &old {
+      let old = Port::new_ref(old).addr();
+      self.back.remove(&old);
+      let referrers = self.labels.referrers(name).chain([name]);
+      for referrer in referrers {
+        let Some(def) = self.defs.get_mut(referrer) else { continue };
+        let Some(def) = Def::downcast_mut::<InterpretedDef>(def) else { continue };
+        for port in def.data.instr.iter_mut().filter_map(Instruction::const_port_mut) {
+          if port.tag() == Tag::Ref && port.addr() == old {
+            *port = new.clone();
+          }
+        }
+      }
+    }
+    old
+  }
+
+  /// Removes the def named `name`, and returns it, or `None` if there is no
+  /// such def. If any other interpreted def refers to it, nothing is removed,
+  /// and their names are returned instead.
+  ///
+  /// As with [`Host::replace_def`], the returned def must not be dropped while
+  /// any net that may refer to it is still being reduced.
+  pub fn remove_def(&mut self, name: &str) -> Result<Option<Box<DynDef>>, Vec<String>> {
+    let mut referrers = self.labels.referrers(name).filter(|&r| r != name).map(ToOwned::to_owned).collect::<Vec<_>>();
+    if !referrers.is_empty() {
+      referrers.sort();
+      return Err(referrers);
+    }
+    let Some(def) = self.defs.remove(name) else { return Ok(None) };
+    self.back.remove(&Port::new_ref(&def).addr());
+    self.update_label_node(name);
+    Ok(Some(def))
+  }
+
+  /// Returns a listing of the instructions of the def named `name`, with refs
+  /// shown by name; see [`InterpretedDef::fmt_with`]. Returns `None` if there
+  /// is no such def, or if it is not an interpreted def.
+  pub fn disasm<'a>(&'a self, name: &str) -> Option<impl fmt::Display + 'a> {
+    struct Disasm<'a>(&'a Host, &'a InterpretedDef);
+
+    impl fmt::Display for Disasm<'_> {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.1.fmt_with(f, &|addr| self.0.back.get(&addr).map(String::as_str))
+      }
+    }
+
+    let def = Def::downcast_ref::<InterpretedDef>(self.defs.get(name)?)?;
+    Some(Disasm(self, &def.data))
   }
 
   /// Returns a mutable [`Def`] named `name`.
@@ -0,0 +1,210 @@
+//! Decoding nets as λ-terms, such as the results of programs compiled by the
+//! λ-calculus front end; see [`Program`](hvm64_ast::Program).
+//!
+//! Each tree is either read as a term, or as the consumer of a term:
+//! - as a term, `(x body)` is `λx body`, and numbers and refs are themselves.
+//! - as the consumer of a term `t`, a var binds `t` (so that its other
+//!   occurrence is read as `t`), `*` erases `t`, `(a r)` applies `t` to `a`,
+//!   with `r` consuming the result, and `{a b}` (with any label) shares `t`
+//!   between `a` and `b`.
+//!
+//! A redex is read as a term, on the left, consumed by the tree on the right.
+//!
+//! [`decode_data`] further decodes Church numerals, Scott lists, and booleans.
+
+use hvm64_util::prelude::*;
+
+use hvm64_ast::{Net, Term, Tree};
+use hvm64_num::{Num, NumTag};
+use hvm64_util::{create_var, maybe_grow};
+
+use crate::DecodeError;
+
+/// Decodes `net` as a λ-term; see the [module docs](self).
+///
+/// Fails if part of the net can't be read as a term, such as a switch node.
+pub fn decode_lambda(net: &Net) -> Result<Term, DecodeError> {
+  let mut decoder = LambdaDecoder { lams: 0, wires: Map::new() };
+  let root = decoder.term(&net.root)?;
+  for (a, b) in &net.redexes {
+    let (term, consumer) =
+      if matches!(a, Tree::Var(_)) || matches!(b, Tree::Era | Tree::Num(_) | Tree::Ref(_)) { (b, a) } else { (a, b) };
+    let term = decoder.term(term)?;
+    decoder.consume(consumer, term)?;
+  }
+  Ok(decoder.resolve(root, &mut vec![]))
+}
+
+struct LambdaDecoder {
+  /// The number of lambdas read so far, used to name their vars.
+  lams: usize,
+  /// The terms bound to each var of the net.
+  wires: Map<String, Term>,
+}
+
+impl LambdaDecoder {
+  /// Reads `tree` as a term. The vars of the net are read as `#name`, until
+  /// they are resolved.
+  fn term(&mut self, tree: &Tree) -> Result<Term, DecodeError> {
+    maybe_grow(move || match tree {
+      Tree::Ctr { lab: 0, p1, p2 } => {
+        let var = create_var(self.lams);
+        self.lams += 1;
+        self.consume(p1, Term::Var(var.clone()))?;
+        Ok(Term::Lam { var, body: Box::new(self.term(p2)?) })
+      }
+      Tree::Var(name) => Ok(Term::Var(format!("#{name}"))),
+      Tree::Num(num) => Ok(Term::Num(*num)),
+      Tree::Ref(name) => Ok(Term::Ref(name.clone())),
+      _ => Err(DecodeError::new("a λ-term", tree)),
+    })
+  }
+
+  /// Reads `tree` as the consumer of `term`.
+  fn consume(&mut self, tree: &Tree, term: Term) -> Result<(), DecodeError> {
+    maybe_grow(move || match tree {
+      Tree::Era => Ok(()),
+      Tree::Var(name) => {
+        self.wires.insert(name.clone(), term);
+        Ok(())
+      }
+      Tree::Ctr { lab: 0, p1, p2 } => {
+        let arg = Box::new(self.term(p1)?);
+        self.consume(p2, Term::App { fun: Box::new(term), arg })
+      }
+      Tree::Ctr { lab, p1, p2 } if lab % 2 == 1 => {
+        self.consume(p1, term.clone())?;
+        self.consume(p2, term)
+      }
+      // `$([op] $(rhs out))`, as the compiler and readback produce
+      Tree::Op { rhs: op, out } => match (&**op, &**out) {
+        (Tree::Num(op), Tree::Op { rhs, out }) if op.tag() == NumTag::Sym => {
+          let op = unsafe { op.get_sym() };
+          let rhs = Box::new(self.term(rhs)?);
+          self.consume(out, Term::Op { op, lhs: Box::new(term), rhs })
+        }
+        _ => Err(DecodeError::new("a λ-term consumer", tree)),
+      },
+      _ => Err(DecodeError::new("a λ-term consumer", tree)),
+    })
+  }
+
+  /// Replaces the vars of the net in `term` with the terms bound to them.
+  /// `resolving` contains the vars being replaced, so that vicious circles are
+  /// left as they are.
+  fn resolve(&self, term: Term, resolving: &mut Vec<String>) -> Term {
+    maybe_grow(move || match term {
+      Term::Var(name) => {
+        let bound = if resolving.contains(&name) {
+          None
+        } else {
+          name.strip_prefix('#').and_then(|wire| self.wires.get(wire)).cloned()
+        };
+        let Some(bound) = bound else { return Term::Var(name) };
+        resolving.push(name);
+        let term = self.resolve(bound, resolving);
+        resolving.pop();
+        term
+      }
+      Term::Lam { var, body } => Term::Lam { var, body: Box::new(self.resolve(*body, resolving)) },
+      Term::App { fun, arg } => {
+        Term::App { fun: Box::new(self.resolve(*fun, resolving)), arg: Box::new(self.resolve(*arg, resolving)) }
+      }
+      Term::Let { var, val, body } => {
+        Term::Let { var, val: Box::new(self.resolve(*val, resolving)), body: Box::new(self.resolve(*body, resolving)) }
+      }
+      Term::Op { op, lhs, rhs } => {
+        Term::Op { op, lhs: Box::new(self.resolve(*lhs, resolving)), rhs: Box::new(self.resolve(*rhs, resolving)) }
+      }
+      Term::List(items) => Term::List(items.into_iter().map(|item| self.resolve(item, resolving)).collect()),
+      term @ (Term::Ref(_) | Term::Num(_)) => term,
+    })
+  }
+}
+
+/// Replaces the Church numerals, Scott lists, and Church booleans in `term`
+/// with numbers, lists, and `true`.
+///
+/// As `λa λb b` is the numeral zero, as well as `false` and the empty list, it
+/// is always decoded as `0`; lists must have at least one item.
+pub fn decode_data(term: Term) -> Term {
+  maybe_grow(move || {
+    if let Some(n) = church_numeral(&term) {
+      return Term::Num(Num::new_u24(n));
+    }
+    if let Some(items) = scott_list(&term) {
+      return Term::List(items.into_iter().cloned().map(decode_data).collect());
+    }
+    if let Term::Lam { var: t, body } = &term {
+      if let Term::Lam { body, .. } = &**body {
+        if matches!(&**body, Term::Var(v) if v == t) {
+          return Term::Var("true".to_owned());
+        }
+      }
+    }
+    match term {
+      Term::Lam { var, body } => Term::Lam { var, body: Box::new(decode_data(*body)) },
+      Term::App { fun, arg } => Term::App { fun: Box::new(decode_data(*fun)), arg: Box::new(decode_data(*arg)) },
+      Term::Let { var, val, body } => {
+        Term::Let { var, val: Box::new(decode_data(*val)), body: Box::new(decode_data(*body)) }
+      }
+      Term::Op { op, lhs, rhs } => Term::Op { op, lhs: Box::new(decode_data(*lhs)), rhs: Box::new(decode_data(*rhs)) },
+      Term::List(items) => Term::List(items.into_iter().map(decode_data).collect()),
+      term @ (Term::Var(_) | Term::Ref(_) | Term::Num(_)) => term,
+    }
+  })
+}
+
+/// `λf λx (f (f .. x))`
+fn church_numeral(term: &Term) -> Option<u32> {
+  let Term::Lam { var: f, body } = term else { return None };
+  let Term::Lam { var: x, body } = &**body else { return None };
+  let mut n = 0;
+  let mut body = &**body;
+  loop {
+    match body {
+      Term::Var(v) if v == x => return Some(n),
+      Term::App { fun, arg } if matches!(&**fun, Term::Var(v) if v == f) => {
+        n += 1;
+        body = &**arg;
+      }
+      _ => return None,
+    }
+  }
+}
+
+/// `λc λn (c head tail)`, ending with `λc λn n`; returns the items if there are
+/// any.
+fn scott_list(term: &Term) -> Option<Vec<&Term>> {
+  let mut items = vec![];
+  let mut term = term;
+  loop {
+    let Term::Lam { var: c, body } = term else { return None };
+    let Term::Lam { var: n, body } = &**body else { return None };
+    match &**body {
+      Term::Var(v) if v == n => return (!items.is_empty()).then_some(items),
+      Term::App { fun, arg: tail } => {
+        let Term::App { fun, arg: head } = &**fun else { return None };
+        if !matches!(&**fun, Term::Var(v) if v == c) || [c, n].iter().any(|v| occurs(v, head) || occurs(v, tail)) {
+          return None;
+        }
+        items.push(&**head);
+        term = &**tail;
+      }
+      _ => return None,
+    }
+  }
+}
+
+/// Whether the var `var` occurs in `term`.
+fn occurs(var: &str, term: &Term) -> bool {
+  maybe_grow(|| match term {
+    Term::Var(v) => v == var,
+    Term::Ref(_) | Term::Num(_) => false,
+    Term::Lam { body, .. } => occurs(var, body),
+    Term::App { fun: a, arg: b } | Term::Let { val: a, body: b, .. } | Term::Op { lhs: a, rhs: b, .. } => {
+      occurs(var, a) || occurs(var, b)
+    }
+    Term::List(items) => items.iter().any(|item| occurs(var, item)),
+  })
+}
@@ -3,46 +3,285 @@ use hvm64_util::prelude::*;
 use super::{Addr, Host, Port, Tag, Wire};
 
 use core::ops::RangeFrom;
+#[cfg(feature = "std")]
+use std::io;
 
 use hvm64_ast::{Net, Tree};
 use hvm64_num::{Num, NumTag};
+#[cfg(feature = "std")]
+use hvm64_runtime::Lab;
 use hvm64_util::{create_var, maybe_grow};
 
 impl Host {
+  /// The name given to refs to defs that are not in this host when they are
+  /// read back. Such refs can only be found in the unreachable parts of a net
+  /// reported by [`Host::readback_checked`].
+  pub const UNKNOWN_REF: &'static str = "$unknown";
+
+  /// The prefix of the names given to the refs that stand for the subtrees
+  /// elided by [`Host::readback_limited`], which are followed by the number of
+  /// nodes in the subtree, as in `@…12`.
+  pub const ELIDED_REF: &'static str = "…";
+
   /// Creates an ast tree from a wire in a runtime net.
   pub fn readback_tree(&self, wire: &Wire) -> Tree {
-    ReadbackState { host: self, vars: Default::default(), var_id: 0 .. }.read_wire(wire.clone())
+    ReadbackState::new(self).read_wire(wire.clone())
   }
 
   /// Creates an ast net from a runtime net.
   ///
+  /// Chains of [`Tag::Red`] redirects are followed to their real targets, so
+  /// this is valid for any net state, not only fully-normalized ones.
+  ///
   /// Note that vicious circles and disconnected subnets will not be in the
   /// resulting ast net, as it is impossible to read these back from the runtime
   /// net representation. In the case of vicious circles, this may result in
-  /// unbound variables.
+  /// unbound variables. Use [`Host::readback_checked`] to find these.
   pub fn readback(&self, rt_net: &hvm64_runtime::Net) -> Net {
-    let mut state = ReadbackState { host: self, vars: Default::default(), var_id: 0 .. };
-    let mut net = Net::default();
+    ReadbackState::new(self).read_net(rt_net)
+  }
 
-    net.root = state.read_wire(rt_net.root.clone());
-    for (a, b) in rt_net.redexes.iter() {
-      net.redexes.push((state.read_port(a.clone(), None), state.read_port(b.clone(), None)))
+  /// Like [`Host::readback`], but writes the textual form of the net directly
+  /// to `out`, rather than building an ast net.
+  ///
+  /// The output is identical to that of displaying the result of
+  /// [`Host::readback`], but is produced by walking the runtime net with an
+  /// explicit stack, so only memory proportional to the depth of the net and
+  /// the number of unclosed vars is used.
+  #[cfg(feature = "std")]
+  pub fn readback_to(&self, rt_net: &hvm64_runtime::Net, out: &mut impl io::Write) -> io::Result<()> {
+    ReadbackState::new(self).write_net(rt_net, out)
+  }
+
+  /// Like [`Host::readback`], but reads back at most `max_nodes` nodes, and no
+  /// nodes nested more than `max_depth` nodes deep.
+  ///
+  /// Subtrees beyond these limits are elided, and replaced by a ref to
+  /// [`Host::ELIDED_REF`] followed by the number of nodes in the subtree, such
+  /// as `@…12`; the [`ElidedSubtrees`] returned alongside the net lists these
+  /// counts. Elided subtrees are walked to count their nodes, but are never
+  /// read back into memory.
+  ///
+  /// Note that the vars of an elided subtree are not read back, so vars which
+  /// are bound within it will be unbound in the resulting net.
+  pub fn readback_limited(
+    &self,
+    rt_net: &hvm64_runtime::Net,
+    max_nodes: usize,
+    max_depth: usize,
+  ) -> (Net, ElidedSubtrees) {
+    let mut state = ReadbackState::new(self);
+    state.max_nodes = max_nodes;
+    state.max_depth = max_depth;
+    let net = state.read_net(rt_net);
+    (net, ElidedSubtrees { subtrees: state.elided })
+  }
+
+  /// Like [`Host::readback`], but additionally walks the heap of `rt_net` to
+  /// find the live nodes that are not reachable from the root or the redexes,
+  /// reporting them, along with any unbound variables, in a
+  /// [`ReadbackDiagnostics`].
+  ///
+  /// This scans every word that has ever been allocated in the heap, so it is
+  /// much slower than a plain readback, and is meant for debugging.
+  pub fn readback_checked(&self, rt_net: &hvm64_runtime::Net) -> (Net, ReadbackDiagnostics) {
+    let mut state = ReadbackState::new(self);
+    state.visited = Some(Set::new());
+    let net = state.read_net(rt_net);
+
+    // Every node has its principal port held by exactly one slot, so any
+    // unreached node is held by a slot that was not reached either.
+    let mut held = Vec::new();
+    let (mut lo, mut hi) = (Addr(usize::MAX), Addr::NULL);
+    for addr in rt_net.allocated_words() {
+      (lo, hi) = (lo.min(addr), hi.max(addr));
+      let port = Wire::new(addr).load_target();
+      if port.is_full_node() && port != Port::GONE && !state.is_visited(addr) {
+        held.push((addr, port));
+      }
     }
+    held.retain(|(_, port)| (lo ..= hi).contains(&port.addr()) && !state.is_visited(port.addr()));
+    let holders = held.iter().map(|(slot, port)| (port.addr(), *slot)).collect::<Map<_, _>>();
 
-    net
+    let mut diagnostics = ReadbackDiagnostics::default();
+    state.nodes = 0;
+    for &(slot, _) in &held {
+      // Climb to the outermost holder of this node, so that each component is
+      // read back as a single tree where possible.
+      let mut slot = slot;
+      let mut seen = Set::from([slot]);
+      while let Some(&holder) = holders.get(&slot.left_half()) {
+        if !seen.insert(holder) {
+          break;
+        }
+        slot = holder;
+      }
+      if state.is_visited(slot) {
+        continue;
+      }
+      // The slot is cut, so that the aux port it belongs to is read back as a
+      // var, which also breaks vicious circles.
+      let var = state.var_id.next().unwrap();
+      state.cuts.insert(slot, var);
+      state.visited.as_mut().unwrap().insert(slot);
+      let tree = state.read_port(Wire::new(slot).load_target(), None);
+      diagnostics.components.push((create_var(var), tree));
+    }
+
+    diagnostics.unreachable_nodes = state.nodes;
+    let mut unbound = state.vars.into_values().collect::<Vec<_>>();
+    unbound.sort();
+    diagnostics.unbound_vars = unbound.into_iter().map(create_var).collect();
+
+    (net, diagnostics)
+  }
+}
+
+/// Problems found in a runtime net by [`Host::readback_checked`].
+#[derive(Debug, Default, Clone)]
+pub struct ReadbackDiagnostics {
+  /// The number of live nodes not reachable from the root or the redexes.
+  pub unreachable_nodes: usize,
+  /// The disconnected subnets made up of these nodes, each as a var and the
+  /// tree connected to it. The var stands for the aux port holding the tree's
+  /// principal port, which may occur within another component (or within the
+  /// tree itself, in the case of a vicious circle).
+  pub components: Vec<(String, Tree)>,
+  /// The variables that occur only once across the net and the components.
+  pub unbound_vars: Vec<String>,
+}
+
+impl ReadbackDiagnostics {
+  /// The number of components shown when displaying the diagnostics.
+  const SAMPLE_COMPONENTS: usize = 4;
+
+  /// Whether the net was read back in its entirety.
+  pub fn is_clean(&self) -> bool {
+    self.unreachable_nodes == 0 && self.unbound_vars.is_empty()
+  }
+}
+
+/// The subtrees left out by [`Host::readback_limited`].
+#[derive(Debug, Default, Clone)]
+pub struct ElidedSubtrees {
+  /// The number of nodes in each elided subtree, in the order their refs
+  /// occur in the net.
+  pub subtrees: Vec<usize>,
+}
+
+impl ElidedSubtrees {
+  /// The total number of nodes elided.
+  pub fn nodes(&self) -> usize {
+    self.subtrees.iter().sum()
+  }
+
+  /// Whether the net was read back in its entirety.
+  pub fn is_empty(&self) -> bool {
+    self.subtrees.is_empty()
   }
 }
 
+impl fmt::Display for ElidedSubtrees {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if !self.subtrees.is_empty() {
+      writeln!(f, "{} node(s) elided in {} subtree(s)", self.nodes(), self.subtrees.len())?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for ReadbackDiagnostics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.unreachable_nodes != 0 {
+      writeln!(
+        f,
+        "{} unreachable node(s) in {} disconnected component(s):",
+        self.unreachable_nodes,
+        self.components.len()
+      )?;
+      for (var, tree) in self.components.iter().take(Self::SAMPLE_COMPONENTS) {
+        writeln!(f, "  {var} ~ {tree}")?;
+      }
+      if self.components.len() > Self::SAMPLE_COMPONENTS {
+        writeln!(f, "  ... and {} more", self.components.len() - Self::SAMPLE_COMPONENTS)?;
+      }
+    }
+    if !self.unbound_vars.is_empty() {
+      writeln!(f, "unbound variable(s): {}", self.unbound_vars.join(" "))?;
+    }
+    Ok(())
+  }
+}
+
+/// A pending step of [`ReadbackState::write_tree`].
+#[cfg(feature = "std")]
+enum WriteFrame {
+  Wire(Wire),
+  Port(Port),
+  Text(&'static str),
+  /// The opening of a constructor with the given label.
+  Open(Lab),
+}
+
 /// See [`Host::readback`].
 struct ReadbackState<'a> {
   host: &'a Host,
   vars: Map<Addr, usize>,
   var_id: RangeFrom<usize>,
+  /// The slots read so far; only tracked by [`Host::readback_checked`].
+  visited: Option<Set<Addr>>,
+  /// Slots to be read back as the given var instead of their target.
+  cuts: Map<Addr, usize>,
+  /// The number of nodes read so far.
+  nodes: usize,
+  /// See [`Host::readback_limited`].
+  max_nodes: usize,
+  max_depth: usize,
+  /// The number of nodes the current subtree is nested in.
+  depth: usize,
+  /// The node count of each elided subtree.
+  elided: Vec<usize>,
 }
 
 impl<'a> ReadbackState<'a> {
+  fn new(host: &'a Host) -> Self {
+    ReadbackState {
+      host,
+      vars: Default::default(),
+      var_id: 0 ..,
+      visited: None,
+      cuts: Default::default(),
+      nodes: 0,
+      max_nodes: usize::MAX,
+      max_depth: usize::MAX,
+      depth: 0,
+      elided: vec![],
+    }
+  }
+
+  fn read_net(&mut self, rt_net: &hvm64_runtime::Net) -> Net {
+    let mut net = Net::default();
+
+    net.root = self.read_wire(rt_net.root.clone());
+    for (a, b) in rt_net.redexes.iter() {
+      net.redexes.push((self.read_port(a.clone(), None), self.read_port(b.clone(), None)))
+    }
+
+    net
+  }
+
+  fn is_visited(&self, addr: Addr) -> bool {
+    self.visited.as_ref().is_some_and(|visited| visited.contains(&addr))
+  }
+
   /// Reads a tree out from a given `wire`.
   fn read_wire(&mut self, wire: Wire) -> Tree {
+    if let Some(visited) = &mut self.visited {
+      visited.insert(wire.addr());
+      if let Some(&var) = self.cuts.get(&wire.addr()) {
+        return Tree::Var(create_var(var));
+      }
+    }
     let port = wire.load_target();
     self.read_port(port, Some(wire))
   }
@@ -51,19 +290,25 @@ impl<'a> ReadbackState<'a> {
   /// `wire` this port was reached from must be supplied to key into the
   /// `vars` map.
   fn read_port(&mut self, port: Port, wire: Option<Wire>) -> Tree {
+    if port.is_full_node() && (self.nodes >= self.max_nodes || self.depth >= self.max_depth) {
+      let nodes = Self::count_nodes(port);
+      self.elided.push(nodes);
+      return Tree::Ref(format!("{}{nodes}", Host::ELIDED_REF));
+    }
+    self.depth += 1;
+    let tree = self.read_port_unlimited(port, wire);
+    self.depth -= 1;
+    tree
+  }
+
+  fn read_port_unlimited(&mut self, port: Port, wire: Option<Wire>) -> Tree {
     maybe_grow(move || match port.tag() {
-      Tag::Var | Tag::Red => {
-        // todo: resolve redirects
-        let key = wire.unwrap().addr().min(port.addr());
-        Tree::Var(create_var(match self.vars.entry(key) {
-          Entry::Occupied(e) => e.remove(),
-          Entry::Vacant(e) => *e.insert(self.var_id.next().unwrap()),
-        }))
-      }
+      Tag::Var | Tag::Red => Tree::Var(create_var(self.read_var(port, wire))),
       Tag::Ref if port == Port::ERA => Tree::Era,
-      Tag::Ref => Tree::Ref(self.host.back[&port.addr()].clone()),
+      Tag::Ref => Tree::Ref(self.ref_name(&port).to_owned()),
       Tag::Num => Tree::Num(port.num()),
       Tag::Op => {
+        self.nodes += 1;
         let op = port.op();
         let node = port.traverse_node();
         let node = Tree::Op { rhs: Box::new(self.read_wire(node.p1)), out: Box::new(self.read_wire(node.p2)) };
@@ -74,10 +319,17 @@ impl<'a> ReadbackState<'a> {
         }
       }
       Tag::Ctr => {
+        self.nodes += 1;
         let node = port.traverse_node();
         Tree::Ctr { lab: node.lab, p1: Box::new(self.read_wire(node.p1)), p2: Box::new(self.read_wire(node.p2)) }
       }
+      Tag::Tup => {
+        let tup = port.traverse_tup();
+        self.nodes += tup.ports.len() - 1;
+        Tree::n_ary(tup.lab, tup.ports.into_iter().map(|wire| self.read_wire(wire)).collect())
+      }
       Tag::Switch => {
+        self.nodes += 1;
         let node = port.traverse_node();
         let arms = self.read_wire(node.p1);
         let out = self.read_wire(node.p2);
@@ -85,4 +337,174 @@ impl<'a> ReadbackState<'a> {
       }
     })
   }
+
+  /// Returns the id of the var read from a var or redirect `port`, reached from
+  /// `wire`.
+  fn read_var(&mut self, port: Port, wire: Option<Wire>) -> usize {
+    let port = self.resolve_redirects(port);
+    let key = wire.unwrap().addr().min(port.addr());
+    match self.vars.entry(key) {
+      Entry::Occupied(e) => e.remove(),
+      Entry::Vacant(e) => *e.insert(self.var_id.next().unwrap()),
+    }
+  }
+
+  fn ref_name(&self, port: &Port) -> &'a str {
+    // defs that are not in the host, such as those used internally by the
+    // runtime, can only be found in unreachable parts of the net
+    self.host.back.get(&port.addr()).map_or(Host::UNKNOWN_REF, |name| name)
+  }
+
+  /// See [`Host::readback_to`].
+  #[cfg(feature = "std")]
+  fn write_net(&mut self, rt_net: &hvm64_runtime::Net, out: &mut impl io::Write) -> io::Result<()> {
+    self.write_tree(WriteFrame::Wire(rt_net.root.clone()), out)?;
+    for (a, b) in rt_net.redexes.iter() {
+      out.write_all(b"\n  & ")?;
+      self.write_tree(WriteFrame::Port(a.clone()), out)?;
+      out.write_all(b" ~ ")?;
+      self.write_tree(WriteFrame::Port(b.clone()), out)?;
+    }
+    Ok(())
+  }
+
+  /// Writes out the tree starting at `frame`, keeping the rest of the tree to
+  /// be written in an explicit stack.
+  #[cfg(feature = "std")]
+  fn write_tree(&mut self, frame: WriteFrame, out: &mut impl io::Write) -> io::Result<()> {
+    let mut stack = vec![frame];
+    while let Some(frame) = stack.pop() {
+      let (port, wire) = match frame {
+        WriteFrame::Text(text) => {
+          out.write_all(text.as_bytes())?;
+          continue;
+        }
+        WriteFrame::Open(lab) => {
+          Self::write_open(lab, out)?;
+          continue;
+        }
+        WriteFrame::Port(port) => (port, None),
+        WriteFrame::Wire(wire) => (wire.load_target(), Some(wire)),
+      };
+      match port.tag() {
+        Tag::Var | Tag::Red => write!(out, "{}", create_var(self.read_var(port, wire)))?,
+        Tag::Ref if port == Port::ERA => out.write_all(b"*")?,
+        Tag::Ref => write!(out, "@{}", self.ref_name(&port))?,
+        Tag::Num => write!(out, "{}", port.num())?,
+        Tag::Op => {
+          let op = port.op();
+          let node = port.traverse_node();
+          if op == NumTag::Sym {
+            out.write_all(b"$(")?;
+            stack.push(WriteFrame::Text(")"));
+          } else {
+            write!(out, "$({} $(", Num::new_sym(op))?;
+            stack.push(WriteFrame::Text("))"));
+          }
+          stack.extend([WriteFrame::Wire(node.p2), WriteFrame::Text(" "), WriteFrame::Wire(node.p1)]);
+        }
+        Tag::Ctr => {
+          let node = port.traverse_node();
+          Self::write_open(node.lab, out)?;
+          stack.extend([
+            WriteFrame::Text(Self::close(node.lab)),
+            WriteFrame::Wire(node.p2),
+            WriteFrame::Text(" "),
+            WriteFrame::Wire(node.p1),
+          ]);
+        }
+        Tag::Tup => {
+          // written as the nested binary constructors it is equivalent to
+          let tup = port.traverse_tup();
+          let (lab, mut ports) = (tup.lab, tup.ports);
+          Self::write_open(lab, out)?;
+          stack.extend((1 .. ports.len()).map(|_| WriteFrame::Text(Self::close(lab))));
+          stack.push(WriteFrame::Wire(ports.pop().unwrap()));
+          while let Some(wire) = ports.pop() {
+            stack.extend([WriteFrame::Text(" "), WriteFrame::Wire(wire)]);
+            if !ports.is_empty() {
+              stack.push(WriteFrame::Open(lab));
+            }
+          }
+        }
+        Tag::Switch => {
+          let node = port.traverse_node();
+          out.write_all(b"?(")?;
+          stack.extend([
+            WriteFrame::Text(")"),
+            WriteFrame::Wire(node.p2),
+            WriteFrame::Text(" "),
+            WriteFrame::Wire(node.p1),
+          ]);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Writes the opening of a constructor with label `lab`.
+  #[cfg(feature = "std")]
+  fn write_open(lab: Lab, out: &mut impl io::Write) -> io::Result<()> {
+    if lab > 1 {
+      write!(out, "#{}", lab / 2)?;
+    }
+    out.write_all(if lab % 2 == 0 { b"(" } else { b"{" })
+  }
+
+  /// The closing of a constructor with label `lab`.
+  #[cfg(feature = "std")]
+  fn close(lab: Lab) -> &'static str {
+    if lab % 2 == 0 { ")" } else { "}" }
+  }
+
+  /// Counts the nodes in the tree whose root is `port`, without reading it
+  /// back.
+  fn count_nodes(port: Port) -> usize {
+    let mut count = 0;
+    let mut stack = vec![port];
+    while let Some(port) = stack.pop() {
+      if port.is(Tag::Tup) {
+        let tup = port.traverse_tup();
+        count += tup.ports.len() - 1;
+        stack.extend(tup.ports.iter().map(Wire::load_target));
+      } else if port.is_full_node() {
+        count += 1;
+        let node = port.traverse_node();
+        stack.push(node.p1.load_target());
+        stack.push(node.p2.load_target());
+      }
+    }
+    count
+  }
+
+  /// Given a var or redirect `port`, follows the chain of redirects starting at
+  /// its target, returning a var port to the aux port at the end of the chain.
+  ///
+  /// If the wire `port` points to has been redirected, its target will be a
+  /// `Red` port to the next wire in the chain, and so on until a wire whose
+  /// target is not a redirect is reached; this is the real other side of the
+  /// wire.
+  ///
+  /// Placeholders such as [`Port::LOCK`], which may be left behind by vicious
+  /// circles, are returned as-is.
+  fn resolve_redirects(&self, mut port: Port) -> Port {
+    if port.is(Tag::Red) && !Self::is_redirect(&port) {
+      return port;
+    }
+    loop {
+      let target = port.wire().load_target();
+      if !Self::is_redirect(&target) {
+        return Port::new_var(port.addr());
+      }
+      port = target;
+    }
+  }
+
+  /// Whether `port` is a redirect, as opposed to a placeholder, or a link in
+  /// the allocator's free list (which a var left dangling by a vicious circle
+  /// may lead into). Free list links are indistinguishable from redirects, but
+  /// the list is always ended by a null link.
+  fn is_redirect(port: &Port) -> bool {
+    port.is(Tag::Red) && *port != Port::LOCK && *port != Port::FREE && port.addr() != Addr::NULL
+  }
 }
@@ -0,0 +1,221 @@
+//! Side-effecting IO for hvm64 programs.
+//!
+//! A program that performs IO reduces to an *action*, which is either finished,
+//! holding the program's result, or a call to an IO operation, holding the
+//! operation's argument and a continuation. The continuation is applied to the
+//! result of the operation to obtain the next action. Actions are built with
+//! the defs inserted by [`Host::insert_io`]:
+//!
+//! | def             | argument             | result              |
+//! |-----------------|----------------------|---------------------|
+//! | `@IO/done`      | the program's result |                     |
+//! | `@IO/print`     | a string             | `*`                 |
+//! | `@IO/read_line` | ignored              | the line, if any    |
+//! | `@IO/read_file` | a path               | the file's contents |
+//! | `@IO/write_file`| `(path contents)`    | `*`                 |
+//!
+//! All but `@IO/done` are applied to an argument and then a continuation.
+//!
+//! Strings are Scott-encoded lists of unicode codepoints; the empty string is
+//! `(* (a a))`, and a string with head `h` and tail `t` is `((h (t r)) (* r))`.
+//! `@IO/read_line` results in `((line r) (* r))`, or in `(* (r r))` at the end
+//! of the input, as with an [`Option`] in [`crate::value`]. So this program
+//! reads a line, prints it back, and finishes with `0`, or finishes with `1` if
+//! there is no line:
+//! ```text
+//! @main = a & @IO/read_line ~ (* (@echo a))
+//! @echo = (line a) & line ~ (@print (@eof a))
+//! @print = (line a) & @IO/print ~ (line ((* b) a)) & @IO/done ~ (0 b)
+//! @eof = a & @IO/done ~ (1 a)
+//! ```
+//!
+//! Effects are performed by the host in between reductions, in
+//! [`Host::run_io`], so each reduction may be done in parallel while the
+//! effects are still performed in order.
+#![cfg(feature = "std")]
+
+use hvm64_util::prelude::*;
+
+use std::{
+  fs,
+  io::{self, BufRead, Write},
+};
+
+use crate::{
+  value::{ctr, same_var, un_ctr},
+  Encoding, Host, ValueDecoder, ValueEncoder,
+};
+use hvm64_ast::{Book, Net, Tree};
+use hvm64_num::Num;
+use hvm64_runtime::{self as run, Addr, Port, Tag, Trg, Wire};
+
+const PRINT: u32 = 1;
+const READ_LINE: u32 = 2;
+const READ_FILE: u32 = 3;
+const WRITE_FILE: u32 = 4;
+
+impl Host {
+  /// Inserts the IO defs described in the [module docs](self) into the host.
+  pub fn insert_io(&mut self) {
+    let mut book = Book::default();
+    // `(x ((x r) (* r)))`
+    book.insert("IO/done".to_owned(), Net {
+      root: ctr(var("x"), ctr(ctr(var("x"), var("r")), ctr(Tree::Era, var("r")))),
+      redexes: vec![],
+    });
+    for (name, op) in [("print", PRINT), ("read_line", READ_LINE), ("read_file", READ_FILE), ("write_file", WRITE_FILE)]
+    {
+      // `(a (k (* ((op (a (k r))) r))))`
+      let call = ctr(Tree::Num(Num::new_u24(op)), ctr(var("a"), ctr(var("k"), var("r"))));
+      book.insert(format!("IO/{name}"), Net {
+        root: ctr(var("a"), ctr(var("k"), ctr(Tree::Era, ctr(call, var("r"))))),
+        redexes: vec![],
+      });
+    }
+    self.insert_book(&book);
+  }
+
+  /// Runs `net` as an IO program, performing the actions it reduces to until it
+  /// finishes, and returning its result.
+  ///
+  /// The program is reduced in a single [`run::Net`] in `heap`, by calling
+  /// `normal` on it. After each action, only its argument is read back, and its
+  /// continuation is applied to the result of the operation in place.
+  pub fn run_io(
+    &self,
+    heap: &run::Heap,
+    net: Net,
+    stdin: &mut impl BufRead,
+    stdout: &mut impl Write,
+    mut normal: impl FnMut(&mut run::Net),
+  ) -> io::Result<Net> {
+    let mut rnet = run::Net::new(heap);
+    let root = Trg::port(Port::new_var(rnet.root.addr()));
+    self.encode_net(&mut rnet, root, &net);
+    loop {
+      normal(&mut rnet);
+      let Some((op, arg, k)) = take_call(&mut rnet) else {
+        let Net { root, redexes } = self.readback(&rnet);
+        return match parse_done(&root) {
+          Some(result) => Ok(Net { root: result.clone(), redexes }),
+          None => Err(invalid_data(format!("expected an IO action, found {root}"))),
+        };
+      };
+      let arg_tree = self.readback_tree(&arg);
+      // the argument may share vars with the continuation, so it is erased
+      // rather than dropped
+      rnet.link_wire_port(arg, Port::ERA);
+
+      let result = match op {
+        PRINT => {
+          stdout.write_all(decode_string(&arg_tree)?.as_bytes())?;
+          stdout.flush()?;
+          Tree::Era
+        }
+        READ_LINE => {
+          let mut line = String::new();
+          let line = match stdin.read_line(&mut line)? {
+            0 => None,
+            _ => Some(line.strip_suffix('\n').map_or(&*line, |line| line.strip_suffix('\r').unwrap_or(line))),
+          };
+          // strings can always be encoded, as every char fits in 24 bits
+          ValueEncoder::new(Encoding::Scott).encode(line).unwrap()
+        }
+        READ_FILE => encode_string(&fs::read_to_string(decode_string(&arg_tree)?)?),
+        WRITE_FILE => {
+          let Some((path, contents)) = un_ctr(&arg_tree) else {
+            return Err(invalid_data(format!("expected `(path contents)`, found {arg_tree}")));
+          };
+          fs::write(decode_string(path)?, decode_string(contents)?)?;
+          Tree::Era
+        }
+        _ => return Err(invalid_data(format!("unknown IO operation {op}"))),
+      };
+
+      // `k ~ (result root)`
+      let (result_trg, root) = rnet.do_ctr(0, Trg::wire(k));
+      self.encode_tree(&mut rnet, result_trg, &result);
+      let root_var = Trg::port(Port::new_var(rnet.root.addr()));
+      rnet.link_trg(root, root_var);
+    }
+  }
+}
+
+/// Takes apart the call action, `(* ((op (a (k r))) r))`, at the root of
+/// `rnet`, if there is one,
+/// freeing the nodes that make it up, and returns its operation, and the wires
+/// leading to its argument and continuation. Otherwise, `rnet` is left as is.
+fn take_call(rnet: &mut run::Net) -> Option<(u32, Wire, Wire)> {
+  let (era, ctrs) = ctr_wires(&rnet.root)?;
+  let (call, r2) = ctr_wires(&ctrs)?;
+  let (op, rest) = ctr_wires(&call)?;
+  let (arg, rest2) = ctr_wires(&rest)?;
+  let (k, r1) = ctr_wires(&rest2)?;
+  let (op_port, (other_end, redirects)) = (op.load_target(), var_end(&r1)?);
+  if era.load_target() != Port::ERA || !op_port.is_num() || other_end != r2.addr() {
+    return None;
+  }
+  for wire in [era, ctrs, call, r2, op, rest, rest2, r1].into_iter().chain(redirects) {
+    rnet.half_free(wire.addr());
+  }
+  Some((op_port.num().get_u24(), arg, k))
+}
+
+/// The wires leading to the aux ports of the constructor with label `0` that
+/// `wire` leads to, if it leads to one.
+fn ctr_wires(wire: &Wire) -> Option<(Wire, Wire)> {
+  let mut port = wire.load_target();
+  if port.is(Tag::Tup) {
+    port = port.unfold_tup();
+    wire.set_target(port.clone());
+  }
+  if port.tag() != Tag::Ctr || port.lab() != 0 {
+    return None;
+  }
+  let node = port.traverse_node();
+  Some((node.p1, node.p2))
+}
+
+/// The slot at the other end of the var that `wire` leads to, if it leads to
+/// one, along with the redirects on the way there.
+fn var_end(wire: &Wire) -> Option<(Addr, Vec<Wire>)> {
+  let is_redirect =
+    |port: &Port| port.is(Tag::Red) && *port != Port::LOCK && *port != Port::FREE && port.addr() != Addr::NULL;
+  let mut port = wire.load_target();
+  if !port.is(Tag::Var) {
+    return None;
+  }
+  let mut redirects = vec![];
+  loop {
+    let target = port.wire().load_target();
+    if !is_redirect(&target) {
+      return Some((port.addr(), redirects));
+    }
+    redirects.push(port.wire());
+    port = target;
+  }
+}
+
+/// The result of a finished action, `((x r) (* r))`.
+fn parse_done(tree: &Tree) -> Option<&Tree> {
+  let (a, b) = un_ctr(tree)?;
+  let ((x, r1), (era, r2)) = (un_ctr(a)?, un_ctr(b)?);
+  (*era == Tree::Era && same_var(r1, r2)).then_some(x)
+}
+
+fn decode_string(tree: &Tree) -> io::Result<String> {
+  ValueDecoder::new(Encoding::Scott).decode(tree).map_err(|e| invalid_data(e.to_string()))
+}
+
+fn encode_string(string: &str) -> Tree {
+  // strings can always be encoded, as every char fits in 24 bits
+  ValueEncoder::new(Encoding::Scott).encode(string).unwrap()
+}
+
+fn var(name: &str) -> Tree {
+  Tree::Var(name.to_owned())
+}
+
+fn invalid_data(msg: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg)
+}
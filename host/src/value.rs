@@ -0,0 +1,488 @@
+//! Conversions between Rust values and the trees that represent them, for
+//! hosts embedding hvm64.
+//!
+//! Numbers are represented as the corresponding hvm64 numbers. Integers are
+//! represented with 24 bits, and encoding one which does not fit is an error;
+//! floats are rounded to 24 bits. Every other type is
+//! represented as a lambda-encoded constructor: the `i`th of `n` constructors,
+//! with fields `f0 .. fk`, is `λc0 .. λcn. ci f0 .. fk`, which is the tree
+//! `(c0 (c1 .. (cn r)))`, where `ci` is `(f0 (.. (fk r)))` and every other
+//! constructor is `*`. For example, `Some(1)` is `((1 r) (* r))`, and `(1, 2)`
+//! is `((1 (2 r)) r)`.
+//!
+//! Lists, which include `Vec`s and `String`s, can either be [`Scott`] or
+//! [`Church`] encoded, in the same way as list literals are (see
+//! [`hvm64_ast::encode_list`]); the Scott encoding of a list is the same as
+//! that of the constructors `cons(head, tail)` and `nil`.
+//!
+//! The encoding of all other types does not depend on the [`Encoding`] used.
+//! Types deriving [`IntoNet`] and [`FromNet`] are encoded as constructors, and
+//! their recursive fields are encoded by their own impls, so a recursive type
+//! is always Scott encoded.
+//!
+//! [`Scott`]: Encoding::Scott
+//! [`Church`]: Encoding::Church
+
+use hvm64_util::prelude::*;
+
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use hvm64_ast::{decode_list, encode_list, Net, Tree};
+use hvm64_num::{Num, NumTag};
+use hvm64_runtime as run;
+
+use crate::Host;
+
+/// Types which can be converted into a tree; see the [module docs](self).
+pub trait IntoNet {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError>;
+}
+
+/// Types which can be converted from a tree; see the [module docs](self).
+pub trait FromNet: Sized {
+  fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError>;
+}
+
+pub use hvm64_ast::Encoding;
+
+/// Builds the trees for values, generating the names of their vars.
+pub struct ValueEncoder {
+  pub encoding: Encoding,
+  vars: usize,
+}
+
+impl ValueEncoder {
+  pub fn new(encoding: Encoding) -> Self {
+    ValueEncoder { encoding, vars: 0 }
+  }
+
+  pub fn encode<T: IntoNet>(&mut self, value: T) -> Result<Tree, EncodeError> {
+    value.into_net(self)
+  }
+
+  /// Returns a new var; the returned tree should be used twice.
+  pub fn var(&mut self) -> Tree {
+    self.vars += 1;
+    Tree::Var(format!("v{}", self.vars))
+  }
+
+  /// Encodes the `index`th of `count` constructors, with the given fields.
+  pub fn ctr(&mut self, index: usize, count: usize, fields: Vec<Tree>) -> Tree {
+    let r = self.var();
+    let mut tree = r.clone();
+    for field in fields.into_iter().rev() {
+      tree = Tree::Ctr { lab: 0, p1: Box::new(field), p2: Box::new(tree) };
+    }
+    let mut ctr = Some(tree);
+    let mut tree = r;
+    for i in (0 .. count).rev() {
+      let ctr = if i == index { ctr.take().unwrap() } else { Tree::Era };
+      tree = Tree::Ctr { lab: 0, p1: Box::new(ctr), p2: Box::new(tree) };
+    }
+    tree
+  }
+
+  /// Encodes a list, using [`Self::encoding`].
+  pub fn list<T: IntoNet>(&mut self, items: Vec<T>) -> Result<Tree, EncodeError> {
+    let items = items.into_iter().map(|item| self.encode(item)).collect::<Result<_, _>>()?;
+    Ok(encode_list(items, self.encoding, &mut || {
+      self.vars += 1;
+      format!("v{}", self.vars)
+    }))
+  }
+}
+
+/// Reads values from their trees.
+pub struct ValueDecoder {
+  pub encoding: Encoding,
+}
+
+impl ValueDecoder {
+  pub fn new(encoding: Encoding) -> Self {
+    ValueDecoder { encoding }
+  }
+
+  pub fn decode<T: FromNet>(&self, tree: &Tree) -> Result<T, DecodeError> {
+    T::from_net(tree, self)
+  }
+
+  /// Decodes a constructor, where constructor `i` has `arities[i]` fields;
+  /// returns the index of the constructor and its fields.
+  pub fn ctr<'t>(&self, tree: &'t Tree, arities: &[usize], name: &str) -> Result<(usize, Vec<&'t Tree>), DecodeError> {
+    let err = || DecodeError::new(name, tree);
+    let mut ctrs = Vec::with_capacity(arities.len());
+    let mut rest = tree;
+    for _ in arities {
+      let (c, r) = un_ctr(rest).ok_or_else(err)?;
+      ctrs.push(c);
+      rest = r;
+    }
+    let r = rest;
+    let mut found = None;
+    for (index, (&ctr, &arity)) in ctrs.iter().zip(arities).enumerate() {
+      if *ctr == Tree::Era {
+        continue;
+      }
+      if found.is_some() {
+        return Err(err());
+      }
+      let mut fields = Vec::with_capacity(arity);
+      let mut rest = ctr;
+      for _ in 0 .. arity {
+        let (field, r) = un_ctr(rest).ok_or_else(err)?;
+        fields.push(field);
+        rest = r;
+      }
+      if !same_var(rest, r) {
+        return Err(err());
+      }
+      found = Some((index, fields));
+    }
+    found.ok_or_else(err)
+  }
+
+  /// Decodes a list, using [`Self::encoding`].
+  pub fn list<T: FromNet>(&self, tree: &Tree, name: &str) -> Result<Vec<T>, DecodeError> {
+    let items = decode_list(tree, self.encoding).ok_or_else(|| DecodeError::new(name, tree))?;
+    items.into_iter().map(|item| self.decode(item)).collect()
+  }
+}
+
+/// An error decoding a value from a tree.
+#[derive(Debug, Clone)]
+pub struct DecodeError {
+  pub expected: String,
+  pub found: Tree,
+}
+
+impl DecodeError {
+  pub fn new(expected: &str, found: &Tree) -> Self {
+    DecodeError { expected: expected.to_owned(), found: found.clone() }
+  }
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "expected {}, found {}", self.expected, self.found)
+  }
+}
+
+#[cfg(feature = "std")]
+impl Error for DecodeError {}
+
+/// An error encoding a value as a tree.
+#[derive(Debug, Clone)]
+pub struct EncodeError {
+  pub expected: String,
+  pub found: String,
+}
+
+impl EncodeError {
+  pub fn new(expected: &str, found: impl fmt::Display) -> Self {
+    EncodeError { expected: expected.to_owned(), found: found.to_string() }
+  }
+}
+
+impl fmt::Display for EncodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "expected {}, found {}", self.expected, self.found)
+  }
+}
+
+#[cfg(feature = "std")]
+impl Error for EncodeError {}
+
+/// An error from [`Host::call`].
+#[derive(Debug, Clone)]
+pub enum CallError {
+  /// There is no def with this name.
+  UnknownDef(String),
+  /// The heap to reduce in could not be allocated.
+  OutOfMemory,
+  /// One of the arguments could not be encoded.
+  Encode(EncodeError),
+  /// The result could not be decoded.
+  Decode(DecodeError),
+}
+
+impl From<EncodeError> for CallError {
+  fn from(err: EncodeError) -> Self {
+    CallError::Encode(err)
+  }
+}
+
+impl From<DecodeError> for CallError {
+  fn from(err: DecodeError) -> Self {
+    CallError::Decode(err)
+  }
+}
+
+impl fmt::Display for CallError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CallError::UnknownDef(name) => write!(f, "there is no def named `@{name}`"),
+      CallError::OutOfMemory => write!(f, "failed to allocate the heap"),
+      CallError::Encode(err) => err.fmt(f),
+      CallError::Decode(err) => err.fmt(f),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl Error for CallError {}
+
+/// The arguments of a def, which are applied to it in order; implemented for
+/// tuples of [`IntoNet`] values.
+pub trait IntoArgs {
+  fn into_args(self, encoder: &mut ValueEncoder) -> Result<Vec<Tree>, EncodeError>;
+}
+
+macro_rules! impl_tuples {
+  ($($n:literal: ($($T:ident)*);)*) => {$(
+    impl<$($T: IntoNet),*> IntoNet for ($($T,)*) {
+      #[allow(non_snake_case)]
+      fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+        let ($($T,)*) = self;
+        let fields = vec![$(encoder.encode($T)?),*];
+        Ok(encoder.ctr(0, 1, fields))
+      }
+    }
+
+    impl<$($T: FromNet),*> FromNet for ($($T,)*) {
+      #[allow(unused_variables, unused_mut)]
+      fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError> {
+        let (_, fields) = decoder.ctr(tree, &[$n], "a tuple")?;
+        let mut fields = fields.into_iter();
+        Ok(($(decoder.decode::<$T>(fields.next().unwrap())?,)*))
+      }
+    }
+
+    impl<$($T: IntoNet),*> IntoArgs for ($($T,)*) {
+      #[allow(non_snake_case, unused_variables)]
+      fn into_args(self, encoder: &mut ValueEncoder) -> Result<Vec<Tree>, EncodeError> {
+        let ($($T,)*) = self;
+        Ok(vec![$(encoder.encode($T)?),*])
+      }
+    }
+  )*};
+}
+
+impl_tuples! {
+  0: ();
+  1: (A);
+  2: (A B);
+  3: (A B C);
+  4: (A B C D);
+  5: (A B C D E);
+  6: (A B C D E F);
+}
+
+macro_rules! impl_ints {
+  ($($T:ident: $tag:ident $new:ident $get:ident;)*) => {$(
+    impl IntoNet for $T {
+      fn into_net(self, _: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+        let num = Num::$new(self as _);
+        if num.$get() as i128 != self as i128 {
+          return Err(EncodeError::new(concat!("a 24-bit ", stringify!($T)), self));
+        }
+        Ok(Tree::Num(num))
+      }
+    }
+
+    impl FromNet for $T {
+      fn from_net(tree: &Tree, _: &ValueDecoder) -> Result<Self, DecodeError> {
+        match tree {
+          Tree::Num(num) if num.tag() == NumTag::$tag => {
+            num.$get().try_into().map_err(|_| DecodeError::new(stringify!($T), tree))
+          }
+          _ => Err(DecodeError::new(stringify!($T), tree)),
+        }
+      }
+    }
+  )*};
+}
+
+impl_ints! {
+  u8: U24 new_u24 get_u24;
+  u16: U24 new_u24 get_u24;
+  u32: U24 new_u24 get_u24;
+  u64: U24 new_u24 get_u24;
+  usize: U24 new_u24 get_u24;
+  i8: I24 new_i24 get_i24;
+  i16: I24 new_i24 get_i24;
+  i32: I24 new_i24 get_i24;
+  i64: I24 new_i24 get_i24;
+  isize: I24 new_i24 get_i24;
+}
+
+macro_rules! impl_floats {
+  ($($T:ident)*) => {$(
+    impl IntoNet for $T {
+      fn into_net(self, _: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+        Ok(Tree::Num(Num::new_f24(self as f32)))
+      }
+    }
+
+    impl FromNet for $T {
+      fn from_net(tree: &Tree, _: &ValueDecoder) -> Result<Self, DecodeError> {
+        match tree {
+          Tree::Num(num) if num.tag() == NumTag::F24 => Ok(num.get_f24() as $T),
+          _ => Err(DecodeError::new(stringify!($T), tree)),
+        }
+      }
+    }
+  )*};
+}
+
+impl_floats! { f32 f64 }
+
+impl IntoNet for char {
+  fn into_net(self, _: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    Ok(Tree::Num(Num::new_u24(self as u32)))
+  }
+}
+
+impl FromNet for char {
+  fn from_net(tree: &Tree, _: &ValueDecoder) -> Result<Self, DecodeError> {
+    match tree {
+      Tree::Num(num) if num.tag() == NumTag::U24 => {
+        char::from_u32(num.get_u24()).ok_or_else(|| DecodeError::new("char", tree))
+      }
+      _ => Err(DecodeError::new("char", tree)),
+    }
+  }
+}
+
+impl IntoNet for bool {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    Ok(encoder.ctr(if self { 0 } else { 1 }, 2, vec![]))
+  }
+}
+
+impl FromNet for bool {
+  fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError> {
+    Ok(decoder.ctr(tree, &[0, 0], "bool")?.0 == 0)
+  }
+}
+
+impl<T: IntoNet> IntoNet for Option<T> {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    match self {
+      Some(value) => {
+        let value = encoder.encode(value)?;
+        Ok(encoder.ctr(0, 2, vec![value]))
+      }
+      None => Ok(encoder.ctr(1, 2, vec![])),
+    }
+  }
+}
+
+impl<T: FromNet> FromNet for Option<T> {
+  fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError> {
+    match decoder.ctr(tree, &[1, 0], "an option")? {
+      (0, fields) => Ok(Some(decoder.decode(fields[0])?)),
+      _ => Ok(None),
+    }
+  }
+}
+
+impl<T: IntoNet> IntoNet for Box<T> {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    encoder.encode(*self)
+  }
+}
+
+impl<T: FromNet> FromNet for Box<T> {
+  fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError> {
+    decoder.decode(tree).map(Box::new)
+  }
+}
+
+impl<T: IntoNet> IntoNet for Vec<T> {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    encoder.list(self)
+  }
+}
+
+impl<T: FromNet> FromNet for Vec<T> {
+  fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError> {
+    decoder.list(tree, "a list")
+  }
+}
+
+impl IntoNet for &str {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    encoder.list(self.chars().collect::<Vec<_>>())
+  }
+}
+
+impl IntoNet for String {
+  fn into_net(self, encoder: &mut ValueEncoder) -> Result<Tree, EncodeError> {
+    encoder.encode(&*self)
+  }
+}
+
+impl FromNet for String {
+  fn from_net(tree: &Tree, decoder: &ValueDecoder) -> Result<Self, DecodeError> {
+    Ok(decoder.list::<char>(tree, "a string")?.into_iter().collect())
+  }
+}
+
+impl Host {
+  /// The size in bytes of the heap [`Host::call`] allocates.
+  pub const CALL_MEMORY: usize = 1 << 26;
+
+  /// Applies the def `name` to `args`, reduces the result, and decodes it,
+  /// using the Scott encoding for lists.
+  ///
+  /// Each call allocates a heap of [`Host::CALL_MEMORY`] bytes to reduce in;
+  /// use [`Host::call_in`] to reuse a heap, or to reduce in a heap of another
+  /// size.
+  pub fn call<T: FromNet>(&self, name: &str, args: impl IntoArgs) -> Result<T, CallError> {
+    let heap = run::Heap::new(Some(Self::CALL_MEMORY)).ok_or(CallError::OutOfMemory)?;
+    self.call_in(&heap, Encoding::Scott, name, args)
+  }
+
+  /// Like [`Host::call`], but reduces in `heap`, and uses `encoding` for lists.
+  pub fn call_in<T: FromNet>(
+    &self,
+    heap: &run::Heap,
+    encoding: Encoding,
+    name: &str,
+    args: impl IntoArgs,
+  ) -> Result<T, CallError> {
+    if !self.defs.contains_key(name) {
+      return Err(CallError::UnknownDef(name.to_owned()));
+    }
+    let mut encoder = ValueEncoder::new(encoding);
+    let out = encoder.var();
+    let args = args.into_args(&mut encoder)?.into_iter().rev().fold(out.clone(), |out, arg| ctr(arg, out));
+    let net = Net { root: out, redexes: vec![(Tree::Ref(name.to_owned()), args)] };
+
+    let mut rnet = run::Net::new(heap);
+    let root = run::Trg::port(run::Port::new_var(rnet.root.addr()));
+    self.encode_net(&mut rnet, root, &net);
+    rnet.normal();
+    let net = self.readback(&rnet);
+    if let Some((a, _)) = net.redexes.first() {
+      return Err(DecodeError::new("a net without redexes", a).into());
+    }
+    Ok(ValueDecoder::new(encoding).decode(&net.root)?)
+  }
+}
+
+pub(crate) fn un_ctr(tree: &Tree) -> Option<(&Tree, &Tree)> {
+  match tree {
+    Tree::Ctr { lab: 0, p1, p2 } => Some((p1, p2)),
+    _ => None,
+  }
+}
+
+pub(crate) fn same_var(a: &Tree, b: &Tree) -> bool {
+  matches!(a, Tree::Var(_)) && a == b
+}
+
+pub(crate) fn ctr(p1: Tree, p2: Tree) -> Tree {
+  Tree::Ctr { lab: 0, p1: Box::new(p1), p2: Box::new(p2) }
+}
@@ -6,6 +6,9 @@ use core::hint::unreachable_unchecked;
 
 use hvm64_util::bi_enum;
 
+#[cfg(feature = "serde")]
+mod serde;
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Num {
   raw: u32,
@@ -89,6 +92,30 @@ impl Num {
     unsafe { Self::new(NumTag::F24, shifted_bits) }
   }
 
+  /// Parses a number literal, such as `1`, `+0x10`, `-2`, `1.5` or `-inf`, as
+  /// in the text syntax of nets; the `[+1]` form is not a literal.
+  pub fn parse_literal(text: &str) -> Option<Self> {
+    fn parse_int(text: &str) -> Option<u64> {
+      if let Some(rest) = text.strip_prefix("0x") {
+        u64::from_str_radix(rest, 16).ok()
+      } else if let Some(rest) = text.strip_prefix("0b") {
+        u64::from_str_radix(rest, 2).ok()
+      } else {
+        text.parse().ok()
+      }
+    }
+
+    if text.contains('.') || text.contains("inf") || text.contains("NaN") {
+      Some(Num::new_f24(text.parse().ok()?))
+    } else if let Some(rest) = text.strip_prefix('-') {
+      Some(Num::new_i24(-(parse_int(rest)? as i32)))
+    } else if let Some(rest) = text.strip_prefix('+') {
+      Some(Num::new_i24(parse_int(rest)? as i32))
+    } else {
+      Some(Num::new_u24(parse_int(text)? as u32))
+    }
+  }
+
   #[inline(always)]
   pub const fn new_sym(val: NumTag) -> Self {
     unsafe { Self::new(NumTag::Sym, val as u32) }
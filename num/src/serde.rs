@@ -0,0 +1,196 @@
+//! Serde support for [`Num`] and [`NumTag`], enabled by the `serde` feature.
+//!
+//! A [`NumTag`] is serialized as its name: `"u24"`, `"i24"`, `"f24"`, `"sym"`,
+//! or the operator itself, e.g. `"+"`, `":-"` or `"<<"`.
+//!
+//! A [`Num`] is serialized as an object with a single key, the name of its
+//! tag, whose value depends on the tag:
+//! - `{"u24": 5}` and `{"i24": -5}` hold the integer
+//! - `{"f24": 1.5}` holds the float; since JSON cannot represent them,
+//!   infinities and NaN are the strings `"+inf"`, `"-inf"` and `"+NaN"`
+//! - `{"sym": "+"}` holds the name of the operator (or type)
+//! - `{"+": 5}` is a partially applied operator, and holds the raw 24-bit
+//!   payload of its left operand
+
+use super::*;
+
+use ::serde::{
+  de::{self, EnumAccess, Unexpected, VariantAccess, Visitor},
+  ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// The name of each [`NumTag`], indexed by its value.
+const TAGS: &[&str] = &[
+  "u24", "i24", "f24", "sym", "+", "-", ":-", "*", "/", ":/", "%", ":%", "&", "|", "^", "<<", ":<<", ">>", ":>>", "=",
+  "!", "<", ">",
+];
+
+impl Serialize for NumTag {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_unit_variant("NumTag", *self as u32, TAGS[*self as usize])
+  }
+}
+
+impl<'de> Deserialize<'de> for NumTag {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct NumTagVisitor;
+
+    impl<'de> Visitor<'de> for NumTagVisitor {
+      type Value = NumTag;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number tag")
+      }
+
+      fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<NumTag, A::Error> {
+        let (TagName(tag), variant) = data.variant()?;
+        variant.unit_variant()?;
+        Ok(tag)
+      }
+    }
+
+    deserializer.deserialize_enum("NumTag", TAGS, NumTagVisitor)
+  }
+}
+
+/// A [`NumTag`] deserialized from a variant identifier.
+struct TagName(NumTag);
+
+impl<'de> Deserialize<'de> for TagName {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct TagNameVisitor;
+
+    impl<'de> Visitor<'de> for TagNameVisitor {
+      type Value = TagName;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number tag")
+      }
+
+      fn visit_u64<E: de::Error>(self, value: u64) -> Result<TagName, E> {
+        u8::try_from(value)
+          .ok()
+          .and_then(|value| NumTag::try_from(value).ok())
+          .map(TagName)
+          .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(value), &self))
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<TagName, E> {
+        match TAGS.iter().position(|&name| name == value) {
+          Some(index) => self.visit_u64(index as u64),
+          None => Err(E::unknown_variant(value, TAGS)),
+        }
+      }
+    }
+
+    deserializer.deserialize_identifier(TagNameVisitor)
+  }
+}
+
+impl Serialize for Num {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let tag = self.tag();
+    let (index, name) = (tag as u32, TAGS[tag as usize]);
+    match tag {
+      NumTag::U24 => serializer.serialize_newtype_variant("Num", index, name, &self.get_u24()),
+      NumTag::I24 => serializer.serialize_newtype_variant("Num", index, name, &self.get_i24()),
+      NumTag::F24 => {
+        let val = self.get_f24();
+        if val.is_finite() {
+          serializer.serialize_newtype_variant("Num", index, name, &val)
+        } else {
+          let val = if val.is_nan() {
+            "+NaN"
+          } else if val > 0.0 {
+            "+inf"
+          } else {
+            "-inf"
+          };
+          serializer.serialize_newtype_variant("Num", index, name, val)
+        }
+      }
+      NumTag::Sym => {
+        let sym = u8::try_from(self.payload()).ok().and_then(|sym| NumTag::try_from(sym).ok());
+        let sym = sym.ok_or_else(|| <S::Error as ser::Error>::custom("invalid symbol"))?;
+        serializer.serialize_newtype_variant("Num", index, name, &sym)
+      }
+      _ => serializer.serialize_newtype_variant("Num", index, name, &self.payload()),
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for Num {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct NumVisitor;
+
+    impl<'de> Visitor<'de> for NumVisitor {
+      type Value = Num;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number")
+      }
+
+      fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Num, A::Error> {
+        const U24_MAX: u32 = (1 << 24) - 1;
+        const I24_MIN: i32 = (-1) << 23;
+        const I24_MAX: i32 = (1 << 23) - 1;
+
+        let (TagName(tag), value) = data.variant()?;
+        Ok(match tag {
+          NumTag::I24 => match value.newtype_variant()? {
+            val @ I24_MIN ..= I24_MAX => Num::new_i24(val),
+            val => Err(de::Error::invalid_value(Unexpected::Signed(val.into()), &"a 24-bit signed integer"))?,
+          },
+          NumTag::F24 => Num::new_f24(value.newtype_variant::<F24>()?.0),
+          NumTag::Sym => Num::new_sym(value.newtype_variant()?),
+          _ => match value.newtype_variant()? {
+            val @ 0 ..= U24_MAX => unsafe { Num::new(tag, val) },
+            val => Err(de::Error::invalid_value(Unexpected::Unsigned(val.into()), &"a 24-bit unsigned integer"))?,
+          },
+        })
+      }
+    }
+
+    deserializer.deserialize_enum("Num", TAGS, NumVisitor)
+  }
+}
+
+/// The value of an `f24`; either a number or the name of a non-finite float.
+struct F24(f32);
+
+impl<'de> Deserialize<'de> for F24 {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct F24Visitor;
+
+    impl<'de> Visitor<'de> for F24Visitor {
+      type Value = F24;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a float, \"+inf\", \"-inf\" or \"+NaN\"")
+      }
+
+      fn visit_f64<E: de::Error>(self, value: f64) -> Result<F24, E> {
+        Ok(F24(value as f32))
+      }
+
+      fn visit_i64<E: de::Error>(self, value: i64) -> Result<F24, E> {
+        Ok(F24(value as f32))
+      }
+
+      fn visit_u64<E: de::Error>(self, value: u64) -> Result<F24, E> {
+        Ok(F24(value as f32))
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<F24, E> {
+        match value {
+          "+inf" => Ok(F24(f32::INFINITY)),
+          "-inf" => Ok(F24(f32::NEG_INFINITY)),
+          "+NaN" => Ok(F24(f32::NAN)),
+          _ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+        }
+      }
+    }
+
+    deserializer.deserialize_any(F24Visitor)
+  }
+}
@@ -38,7 +38,7 @@ impl Addr {
   /// Given an address to one word of a two-word allocation, returns the address
   /// of the first word of that allocation.
   #[inline(always)]
-  pub(super) fn left_half(&self) -> Self {
+  pub fn left_half(&self) -> Self {
     Addr(self.0 & !Addr::HALF_MASK)
   }
 
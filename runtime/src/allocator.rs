@@ -1,7 +1,7 @@
 use super::*;
 
 // Really, rust?
-use alloc::alloc::alloc;
+use alloc::alloc::alloc_zeroed;
 
 /// The memory behind a two-word allocation.
 ///
@@ -37,6 +37,10 @@ impl Heap {
     None
   }
   /// Allocates a new heap with exactly the given size in words.
+  ///
+  /// The heap is zeroed, so words that have never been allocated hold a null
+  /// [`Tag::Red`] port rather than arbitrary data; see
+  /// [`Allocator::allocated_words`].
   #[inline]
   pub fn new_exact(words: usize) -> Option<Box<Self>> {
     let nodes = words / 2;
@@ -44,7 +48,7 @@ impl Heap {
       return None;
     }
     unsafe {
-      let ptr = alloc(Layout::array::<Node>(nodes).unwrap()) as *mut Node;
+      let ptr = alloc_zeroed(Layout::array::<Node>(nodes).unwrap()) as *mut Node;
       if ptr.is_null() {
         return None;
       }
@@ -109,6 +113,13 @@ impl<'h> Allocator<'h> {
     addr
   }
 
+  /// Returns the addresses of every word in the part of the heap that has been
+  /// handed out by this allocator, whether or not it is currently in use.
+  pub fn allocated_words(&self) -> impl Iterator<Item = Addr> + '_ {
+    let nodes = &self.heap.0[.. self.next.min(self.heap.0.len())];
+    nodes.iter().flat_map(|node| [Addr(&node.0 as *const _ as _), Addr(&node.1 as *const _ as _)])
+  }
+
   #[inline(always)]
   pub(crate) fn free_wire(&mut self, wire: Wire) {
     self.half_free(wire.addr());
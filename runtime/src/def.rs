@@ -183,6 +183,10 @@ impl<'a> Net<'a> {
       return self.comm02(port, trg);
     }
 
+    if trg.tag() == Tup && !def.labs.has(trg.lab()) {
+      return self.comm0_tup(port, trg);
+    }
+
     self.rwts.dref += 1;
 
     unsafe { Def::call(port.addr().0 as *const _, self, trg) }
@@ -213,6 +217,54 @@ impl InterpretedDef {
   }
 }
 
+impl InterpretedDef {
+  /// Writes the instructions of this def, one per line. Refs are shown as
+  /// `@name` if `ref_name` returns a name for their address, and as `@`
+  /// followed by their address otherwise.
+  ///
+  /// Each line starts with the name of the instruction, followed by the
+  /// registers and constants it reads from, and then, after a `->`, the
+  /// registers it writes to.
+  pub fn fmt_with<'a>(&self, f: &mut fmt::Formatter<'_>, ref_name: &dyn Fn(Addr) -> Option<&'a str>) -> fmt::Result {
+    let port = |port: &Port| {
+      if port.is_era() {
+        "*".to_owned()
+      } else if port.tag() == Tag::Ref {
+        match ref_name(port.addr()) {
+          Some(name) => format!("@{name}"),
+          None => format!("@{:?}", port.addr()),
+        }
+      } else {
+        port.num().to_string()
+      }
+    };
+    for instr in &self.instr {
+      match instr {
+        Instruction::Const { trg, port: p } => writeln!(f, "{:<10} {} -> {trg}", "const", port(p)),
+        Instruction::Link { a, b } => writeln!(f, "{:<10} {a} {b}", "link"),
+        Instruction::LinkConst { trg, port: p } => writeln!(f, "{:<10} {trg} {}", "link_const", port(p)),
+        Instruction::Ctr { lab, trg, p1, p2 } => writeln!(f, "{:<10} {trg} {lab} -> {p1} {p2}", "ctr"),
+        Instruction::Tup { lab, trg, ports } => {
+          write!(f, "{:<10} {trg} {lab} ->", "tup")?;
+          ports.iter().try_for_each(|p| write!(f, " {p}"))?;
+          writeln!(f)
+        }
+        Instruction::Op { op, trg, rhs, out } => writeln!(f, "{:<10} {trg} [{op}] -> {rhs} {out}", "op"),
+        Instruction::OpNum { op, trg, rhs, out } => writeln!(f, "{:<10} {trg} [{op}] {rhs} -> {out}", "op_num"),
+        Instruction::Switch { trg, arms, out } => writeln!(f, "{:<10} {trg} -> {arms} {out}", "switch"),
+        Instruction::Wires { av, aw, bv, bw } => writeln!(f, "{:<10} -> {av} {aw} {bv} {bw}", "wires"),
+      }?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for InterpretedDef {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.fmt_with(f, &|_| None)
+  }
+}
+
 impl AsDef for InterpretedDef {
   unsafe fn call(def: *const Def<InterpretedDef>, net: &mut Net, trg: Port) {
     let def = unsafe { &*def };
@@ -262,6 +314,11 @@ impl AsDef for InterpretedDef {
             trgs.set_trg(p1, t1);
             trgs.set_trg(p2, t2);
           }
+          Instruction::Tup { lab, trg, ref ports } => {
+            let trg = trgs.get_trg(trg);
+            let mut ports = ports.iter();
+            net.do_tup(lab, trg, ports.len(), |t| trgs.set_trg(*ports.next().unwrap(), t));
+          }
           Instruction::Op { op, trg, rhs, out } => {
             let (r, o) = net.do_op(op, trgs.get_trg(trg));
             trgs.set_trg(rhs, r);
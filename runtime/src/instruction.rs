@@ -49,6 +49,11 @@ pub enum Instruction {
   /// let (p1, p2) = net.do_ctr(lab, trg);
   /// ```
   Ctr { lab: Lab, trg: TrgId, p1: TrgId, p2: TrgId },
+  /// See [`Net::do_tup`].
+  /// ```rust,ignore
+  /// let [p1, .., pn] = net.do_tup(lab, trg, n, ..);
+  /// ```
+  Tup { lab: Lab, trg: TrgId, ports: Vec<TrgId> },
   /// See [`Net::do_op`].
   /// ```rust,ignore
   /// let (rhs, out) = net.do_op(lab, trg);
@@ -71,6 +76,23 @@ pub enum Instruction {
   Wires { av: TrgId, aw: TrgId, bv: TrgId, bw: TrgId },
 }
 
+impl Instruction {
+  /// The port used by a [`Instruction::Const`] or [`Instruction::LinkConst`].
+  pub fn const_port(&self) -> Option<&Port> {
+    match self {
+      Instruction::Const { port, .. } | Instruction::LinkConst { port, .. } => Some(port),
+      _ => None,
+    }
+  }
+
+  pub fn const_port_mut(&mut self) -> Option<&mut Port> {
+    match self {
+      Instruction::Const { port, .. } | Instruction::LinkConst { port, .. } => Some(port),
+      _ => None,
+    }
+  }
+}
+
 /// An index to a [`Trg`] in an [`Instruction`]. These essentially serve the
 /// function of registers.
 ///
@@ -111,7 +133,10 @@ impl<'a> Net<'a> {
   /// `trg ~ {#lab x y}`
   #[inline(always)]
   pub fn do_ctr(&mut self, lab: Lab, trg: Trg) -> (Trg, Trg) {
-    let port = trg.target();
+    let mut port = trg.target();
+    if port.tag() == Tup && port.lab() == lab {
+      port = port.unfold_tup();
+    }
     #[allow(clippy::overly_complex_bool_expr)]
     if port.tag() == Ctr && port.lab() == lab {
       trace!(self.tracer, "fast");
@@ -13,6 +13,12 @@ impl<'a> Net<'a> {
       (Ref, Ref | Num) if !a.is_skippable() => self.call(a, b),
       (Ref | Num, Ref) if !b.is_skippable() => self.call(b, a),
       (Num | Ref, Num | Ref) => self.rwts.eras += 1,
+      // n-ary
+      (Tup, Tup | Ctr) | (Ctr, Tup) if a.lab() == b.lab() => self.anni_tup(a, b),
+      (Ref, Tup) if b.lab() >= a.lab() => self.comm0_tup(a, b),
+      (Tup, Ref) if a.lab() >= b.lab() => self.comm0_tup(b, a),
+      (Num, Tup) => self.comm0_tup(a, b),
+      (Tup, Num) => self.comm0_tup(b, a),
       // comm 2/2
       (Ctr, Switch) if a.lab() != 0 => self.comm22(a, b),
       (Switch, Ctr) if b.lab() != 0 => self.comm22(a, b),
@@ -35,6 +41,10 @@ impl<'a> Net<'a> {
       (Num, Op) => self.op_num(b, a),
       (Switch, Num) => self.switch_num(a, b),
       (Num, Switch) => self.switch_num(b, a),
+      // anything else interacts with the binary combinator an n-ary one
+      // unfolds into
+      (Tup, _) => self.interact(a.unfold_tup(), b),
+      (_, Tup) => self.interact(a, b.unfold_tup()),
       // todo: what should the semantics of these be?
       (Switch, Ctr) // b.lab() == 0
       | (Ctr, Switch) // a.lab() == 0
@@ -346,6 +346,6 @@ impl RedexQueue {
 // Returns whether a redex does not allocate memory
 fn redex_would_shrink(a: &Port, b: &Port) -> bool {
   (*a == Port::ERA || *b == Port::ERA)
-    || (a.tag() == Tag::Ctr && b.tag() == Tag::Ctr && a.lab() == b.lab())
+    || (matches!(a.tag(), Tag::Ctr | Tag::Tup) && matches!(b.tag(), Tag::Ctr | Tag::Tup) && a.lab() == b.lab())
     || (!(a.tag() == Tag::Ref || b.tag() == Tag::Ref) && (a.is_num() || b.is_num()))
 }
@@ -0,0 +1,207 @@
+use super::*;
+
+use core::marker::PhantomData;
+
+/// A [`Def`] implemented natively, through a safe interface.
+///
+/// Implementing [`AsDef`] directly requires manipulating ports and wires by
+/// hand; a `NativeDef` instead receives its argument as an [`Arg`], and builds
+/// its result through a [`NativeNet`], which only exposes operations that keep
+/// the net well-formed.
+///
+/// To use a `NativeDef` as a `Def`, wrap it in [`Native`], or insert it with
+/// `Host::insert_native`:
+/// ```rust,ignore
+/// host.insert_def("name", Box::new(Def::new(LabSet::ALL, Native(def))));
+/// ```
+pub trait NativeDef: Send + Sync + 'static {
+  /// Called when this def interacts with `arg`.
+  ///
+  /// Every [`Handle`] in `arg`, or returned by `net`, can be used at most once,
+  /// and only during this call; those that are dropped are linked to an
+  /// eraser once the call returns.
+  fn call<'n>(&self, net: &mut NativeNet<'n, '_>, arg: Arg<'n>);
+}
+
+impl<F: for<'n, 'h> Fn(&mut NativeNet<'n, 'h>, Arg<'n>) + Send + Sync + 'static> NativeDef for F {
+  fn call<'n>(&self, net: &mut NativeNet<'n, '_>, arg: Arg<'n>) {
+    self(net, arg)
+  }
+}
+
+/// Adapts a [`NativeDef`] into an [`AsDef`].
+///
+/// Note that, as with any other `Def`, the def will only be called for
+/// constructors whose label is in the def's [`LabSet`], and only be called for
+/// numbers and other refs if the set is [`LabSet::ALL`].
+pub struct Native<T>(pub T);
+
+impl<T: NativeDef> AsDef for Native<T> {
+  unsafe fn call(slf: *const Def<Self>, net: &mut Net, port: Port) {
+    let def = unsafe { &(*slf).data.0 };
+    let mut net = NativeNet { net, handles: vec![], brand: PhantomData };
+    let arg = net.arg(port);
+    def.call(&mut net, arg);
+    for trg in net.handles.into_iter().flatten() {
+      net.net.link_trg_port(trg, Port::ERA);
+    }
+  }
+}
+
+/// The argument a [`NativeDef`] is called with.
+#[must_use]
+pub enum Arg<'n> {
+  /// A number.
+  Num(Num),
+  /// A binary constructor with label `lab`, whose auxiliary ports are `p1` and
+  /// `p2`.
+  Ctr { lab: Lab, p1: Handle<'n>, p2: Handle<'n> },
+  /// Any other agent, which can only be linked to as a whole.
+  Other(Handle<'n>),
+}
+
+/// A free port of the net being built by a [`NativeDef`].
+///
+/// A handle is consumed when it is used, and its lifetime ties it to the call
+/// it was created in, so it can't be used twice, or kept for a later call:
+/// ```compile_fail
+/// # use hvm64_runtime::*;
+/// # use std::sync::Mutex;
+/// static STASH: Mutex<Vec<Handle<'static>>> = Mutex::new(vec![]);
+/// fn stash<'n>(net: &mut NativeNet<'n, '_>, arg: Arg<'n>) {
+///   let Arg::Other(handle) = arg else { return };
+///   STASH.lock().unwrap().push(handle);
+/// }
+/// ```
+#[must_use]
+pub struct Handle<'n> {
+  /// The index of the target of this handle in [`NativeNet::handles`].
+  index: usize,
+  brand: Brand<'n>,
+}
+
+/// Makes `'n` invariant, so that a handle can only be used with the
+/// [`NativeNet`] it came from.
+type Brand<'n> = PhantomData<fn(&'n ()) -> &'n ()>;
+
+impl fmt::Debug for Handle<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Handle({})", self.index)
+  }
+}
+
+/// The view of a [`Net`] given to a [`NativeDef`].
+///
+/// Each operation mirrors the [`Instruction`] of the same name, but consumes
+/// and returns [`Handle`]s rather than [`Trg`]s.
+pub struct NativeNet<'n, 'h> {
+  net: &'n mut Net<'h>,
+  /// The target of each handle, until it is used.
+  handles: Vec<Option<Trg>>,
+  brand: Brand<'n>,
+}
+
+impl<'n, 'h> NativeNet<'n, 'h> {
+  fn handle(&mut self, trg: Trg) -> Handle<'n> {
+    self.handles.push(Some(trg));
+    Handle { index: self.handles.len() - 1, brand: PhantomData }
+  }
+
+  fn take(&mut self, handle: Handle<'n>) -> Trg {
+    // each handle is consumed when it is taken, so its target is still here
+    self.handles[handle.index].take().unwrap()
+  }
+
+  fn arg(&mut self, port: Port) -> Arg<'n> {
+    let port = if port.is(Tag::Tup) { port.unfold_tup() } else { port };
+    match port.tag() {
+      Tag::Num => Arg::Num(port.num()),
+      Tag::Ctr => {
+        let node = port.consume_node();
+        Arg::Ctr { lab: node.lab, p1: self.handle(Trg::wire(node.p1)), p2: self.handle(Trg::wire(node.p2)) }
+      }
+      _ => Arg::Other(self.handle(Trg::port(port))),
+    }
+  }
+
+  /// `trg ~ {#lab x y}`; see [`Net::do_ctr`].
+  pub fn ctr(&mut self, lab: Lab, trg: Handle<'n>) -> (Handle<'n>, Handle<'n>) {
+    let trg = self.take(trg);
+    let (p1, p2) = self.net.do_ctr(lab, trg);
+    (self.handle(p1), self.handle(p2))
+  }
+
+  /// `trg ~ {#lab x1 .. xn}`, where `arity` is `n`; see [`Net::do_tup`].
+  pub fn tup(&mut self, lab: Lab, trg: Handle<'n>, arity: usize) -> Vec<Handle<'n>> {
+    let trg = self.take(trg);
+    let mut ports = Vec::with_capacity(arity);
+    self.net.do_tup(lab, trg, arity, |trg| ports.push(trg));
+    ports.into_iter().map(|trg| self.handle(trg)).collect()
+  }
+
+  /// `trg ~ <op x y>`; see [`Net::do_op`].
+  pub fn op(&mut self, op: NumTag, trg: Handle<'n>) -> (Handle<'n>, Handle<'n>) {
+    let trg = self.take(trg);
+    let (rhs, out) = self.net.do_op(op, trg);
+    (self.handle(rhs), self.handle(out))
+  }
+
+  /// `trg ~ <op #rhs x>`; see [`Net::do_op_num`].
+  pub fn op_num(&mut self, op: NumTag, trg: Handle<'n>, rhs: Num) -> Handle<'n> {
+    let trg = self.take(trg);
+    let out = self.net.do_op_num(op, trg, rhs);
+    self.handle(out)
+  }
+
+  /// `trg ~ ?<x y>`; see [`Net::do_switch`].
+  pub fn switch(&mut self, trg: Handle<'n>) -> (Handle<'n>, Handle<'n>) {
+    let trg = self.take(trg);
+    let (arms, out) = self.net.do_switch(trg);
+    (self.handle(arms), self.handle(out))
+  }
+
+  /// Creates two wires, for linking two subnets to each other; see
+  /// [`Instruction::Wires`].
+  pub fn wires(&mut self) -> (Handle<'n>, Handle<'n>, Handle<'n>, Handle<'n>) {
+    let (av, aw, bv, bw) = self.net.do_wires();
+    (self.handle(av), self.handle(aw), self.handle(bv), self.handle(bw))
+  }
+
+  /// Links two handles to each other.
+  pub fn link(&mut self, a: Handle<'n>, b: Handle<'n>) {
+    let (a, b) = (self.take(a), self.take(b));
+    self.net.link_trg(a, b);
+  }
+
+  /// Links a handle to a number.
+  pub fn link_num(&mut self, trg: Handle<'n>, num: Num) {
+    let trg = self.take(trg);
+    self.net.link_trg_port(trg, Port::new_num(num));
+  }
+
+  /// Links a handle to an eraser.
+  pub fn link_era(&mut self, trg: Handle<'n>) {
+    let trg = self.take(trg);
+    self.net.link_trg_port(trg, Port::ERA);
+  }
+
+  /// Links a handle to a reference to `def`.
+  ///
+  /// As `def` lives forever, the reference stays valid in any net; such a def
+  /// can be made with [`Box::leak`], or put in a `static`.
+  pub fn link_ref(&mut self, trg: Handle<'n>, def: &'static Def) {
+    // SAFETY: `def` is never moved or dropped
+    unsafe { self.link_ref_unchecked(trg, def) }
+  }
+
+  /// Like [`NativeNet::link_ref`], but for a def that doesn't live forever.
+  ///
+  /// # Safety
+  /// `def` must not be moved or dropped while the reference may still be in
+  /// any net. For example, it may be a def owned by the same host as this
+  /// native def, which is not removed while the host's nets are in use.
+  pub unsafe fn link_ref_unchecked(&mut self, trg: Handle<'n>, def: &Def) {
+    let trg = self.take(trg);
+    self.net.link_trg_port(trg, Port::new_ref(def));
+  }
+}
@@ -81,6 +81,7 @@ impl ExpandDef {
 
 impl AsDef for ExpandDef {
   unsafe fn call(def: *const Def<Self>, net: &mut Net, port: Port) {
+    let port = if port.is(Tag::Tup) { port.unfold_tup() } else { port };
     if port.tag() == Tag::Ref && port != Port::ERA {
       let other: *const Def = port.addr().def() as *const _;
       if let Some(other) = Def::downcast_ptr::<Self>(other) {
@@ -93,7 +94,7 @@ impl AsDef for ExpandDef {
     }
     let def = *Box::from_raw(def as *mut Def<Self>);
     match port.tag() {
-      Tag::Red => {
+      Tag::Red | Tag::Tup => {
         unreachable!()
       }
       Tag::Ref | Tag::Num | Tag::Var => net.link_port_port(def.data.out, port),
@@ -0,0 +1,280 @@
+//! A peephole optimizer for the instructions of an [`InterpretedDef`].
+//!
+//! Every register of an instruction stream is written exactly once (or, for
+//! `t0`, is the def's input) and read exactly once. This makes rewriting the
+//! stream simple: each register has a single defining instruction and a single
+//! use, and replacing one register with another never affects any other
+//! instruction.
+//!
+//! The optimizer performs the following, until none of them apply:
+//! - a `Const` whose register is used by a `Link` is folded into a `LinkConst`
+//! - an `Op` whose `rhs` register is linked to a number is folded into an
+//!   `OpNum`
+//! - a `Wires` whose halves are only linked to each other and to a register or
+//!   constant is removed, and the register or constant is used directly; this
+//!   avoids a wire-wire link, which needs to lock both wires
+//!
+//! Then, each `Const` is moved to just before its use, and each `LinkConst` to
+//! just after the definition of its register, so that registers are live for as
+//! short a time as possible. A `LinkConst` of a register defined by a `Wires`
+//! is left in place, as the halves of a `Wires` must be linked in the order
+//! given by [`Instruction`]. Finally, the registers are renumbered, reusing the
+//! indices of registers that have already been read, so that `trgs` (and thus
+//! `net.trgs`) is as small as possible.
+
+use super::*;
+
+impl InterpretedDef {
+  /// Optimizes the instructions of this def, without changing the net it
+  /// creates; see the module documentation.
+  pub fn optimize(&mut self) {
+    let mut instr = mem::take(&mut self.instr).into_iter().map(Some).collect::<Vec<_>>();
+    while fold_consts(&mut instr) | fold_op_nums(&mut instr) | fold_wires(&mut instr) {}
+    self.instr = schedule(instr.into_iter().flatten().collect());
+    self.trgs = compact(&mut self.instr);
+  }
+}
+
+/// Calls `f` with each register read by `instr`, then with each register
+/// written by it.
+fn regs_mut(instr: &mut Instruction, mut f: impl FnMut(&mut TrgId, bool)) {
+  match instr {
+    Instruction::Const { trg, .. } => f(trg, true),
+    Instruction::Link { a, b } => {
+      f(a, false);
+      f(b, false);
+    }
+    Instruction::LinkConst { trg, .. } => f(trg, false),
+    Instruction::Ctr { trg, p1, p2, .. } => {
+      f(trg, false);
+      f(p1, true);
+      f(p2, true);
+    }
+    Instruction::Tup { trg, ports, .. } => {
+      f(trg, false);
+      for p in ports {
+        f(p, true);
+      }
+    }
+    Instruction::Op { trg, rhs, out, .. } => {
+      f(trg, false);
+      f(rhs, true);
+      f(out, true);
+    }
+    Instruction::OpNum { trg, out, .. } => {
+      f(trg, false);
+      f(out, true);
+    }
+    Instruction::Switch { trg, arms, out } => {
+      f(trg, false);
+      f(arms, true);
+      f(out, true);
+    }
+    Instruction::Wires { av, aw, bv, bw } => {
+      f(av, true);
+      f(aw, true);
+      f(bv, true);
+      f(bw, true);
+    }
+  }
+}
+
+/// Like [`regs_mut`], but without modifying `instr`.
+fn regs(instr: &Instruction, mut f: impl FnMut(TrgId, bool)) {
+  regs_mut(&mut instr.clone(), |trg, out| f(*trg, out))
+}
+
+/// The index of the instruction defining each register, and of the instruction
+/// using each register. `t0` is not defined by any instruction.
+fn positions(instr: &[Option<Instruction>]) -> (Map<TrgId, usize>, Map<TrgId, usize>) {
+  let mut defs = Map::new();
+  let mut uses = Map::new();
+  for (i, instr) in instr.iter().enumerate() {
+    if let Some(instr) = instr {
+      regs(instr, |trg, out| {
+        if out {
+          defs.insert(trg, i)
+        } else {
+          uses.insert(trg, i)
+        };
+      });
+    }
+  }
+  (defs, uses)
+}
+
+/// Replaces the register `from` read by `instr` with `to`.
+fn replace_use(instr: &mut Instruction, from: TrgId, to: TrgId) {
+  regs_mut(instr, |trg, out| {
+    if !out && *trg == from {
+      *trg = to;
+    }
+  });
+}
+
+/// Returns the other register of a `Link` that reads `trg`.
+fn linked_to(instr: &Option<Instruction>, trg: TrgId) -> Option<TrgId> {
+  match *instr {
+    Some(Instruction::Link { a, b }) if a == trg => Some(b),
+    Some(Instruction::Link { a, b }) if b == trg => Some(a),
+    _ => None,
+  }
+}
+
+/// `t = port; a ~ t` becomes `a ~ port`.
+fn fold_consts(instr: &mut [Option<Instruction>]) -> bool {
+  let (_, uses) = positions(instr);
+  let mut changed = false;
+  for i in 0 .. instr.len() {
+    let Some(Instruction::Const { trg, port }) = instr[i].clone() else { continue };
+    let j = uses[&trg];
+    if let Some(other) = linked_to(&instr[j], trg) {
+      instr[j] = Some(Instruction::LinkConst { trg: other, port });
+      instr[i] = None;
+      changed = true;
+    }
+  }
+  changed
+}
+
+/// `(rhs, out) = op(trg); rhs ~ num` becomes `out = op_num(trg, num)`.
+fn fold_op_nums(instr: &mut [Option<Instruction>]) -> bool {
+  let (_, uses) = positions(instr);
+  let mut changed = false;
+  for i in 0 .. instr.len() {
+    let Some(Instruction::Op { op, trg, rhs, out }) = instr[i] else { continue };
+    let j = uses[&rhs];
+    let Some(Instruction::LinkConst { port, .. }) = &instr[j] else { continue };
+    if port.tag() == Tag::Num {
+      instr[i] = Some(Instruction::OpNum { op, trg, rhs: port.num(), out });
+      instr[j] = None;
+      changed = true;
+    }
+  }
+  changed
+}
+
+/// Removes a `Wires` whose `aw` and `bw` are linked to each other, and whose
+/// `bv` (or `av`) is linked to a register defined before `av` (or `bv`) is
+/// used, or to a constant. The use of `av` (or `bv`) then uses that register or
+/// constant directly.
+fn fold_wires(instr: &mut [Option<Instruction>]) -> bool {
+  let (defs, mut uses) = positions(instr);
+  let mut changed = false;
+  for i in 0 .. instr.len() {
+    let Some(Instruction::Wires { av, aw, bv, bw }) = instr[i] else { continue };
+    let j = uses[&aw];
+    if linked_to(&instr[j], aw) != Some(bw) {
+      continue;
+    }
+    for (v, w) in [(av, bv), (bv, av)] {
+      let k = uses[&w];
+      let v_use = uses[&v];
+      if let Some(other) = linked_to(&instr[k], w) {
+        // `t0` is defined before any instruction
+        if other != v && defs.get(&other).map_or(true, |&d| d < v_use) {
+          replace_use(instr[v_use].as_mut().unwrap(), v, other);
+          uses.insert(other, v_use);
+          (instr[i], instr[j], instr[k]) = (None, None, None);
+          changed = true;
+          break;
+        }
+      } else if let Some(Instruction::LinkConst { port, .. }) = instr[k].clone() {
+        instr[i] = Some(Instruction::Const { trg: v, port });
+        (instr[j], instr[k]) = (None, None);
+        changed = true;
+        break;
+      }
+    }
+  }
+  changed
+}
+
+/// Moves each `Const` to just before its use, and each `LinkConst` not of a
+/// register defined by a `Wires` to just after the definition of its register.
+fn schedule(instr: Vec<Instruction>) -> Vec<Instruction> {
+  let mut out = Vec::with_capacity(instr.len());
+  let mut consts = Map::new();
+  let mut const_order = vec![];
+  let mut link_consts = Map::new();
+  let mut rest = vec![];
+  let mut wires = Set::new();
+  for instr in &instr {
+    if let Instruction::Wires { av, aw, bv, bw } = *instr {
+      wires.extend([av, aw, bv, bw]);
+    }
+  }
+  for instr in instr {
+    match instr {
+      Instruction::Const { trg, .. } => {
+        const_order.push(trg);
+        consts.insert(trg, instr);
+      }
+      Instruction::LinkConst { trg, .. } if !wires.contains(&trg) => {
+        link_consts.insert(trg, instr);
+      }
+      _ => rest.push(instr),
+    }
+  }
+
+  fn push(
+    out: &mut Vec<Instruction>,
+    consts: &mut Map<TrgId, Instruction>,
+    link_consts: &mut Map<TrgId, Instruction>,
+    instr: Instruction,
+  ) {
+    let mut defined = vec![];
+    regs(&instr, |trg, is_out| {
+      if is_out {
+        defined.push(trg);
+      } else if let Some(c) = consts.remove(&trg) {
+        out.push(c);
+      }
+    });
+    out.push(instr);
+    for trg in defined {
+      if let Some(link) = link_consts.remove(&trg) {
+        push(out, consts, link_consts, link);
+      }
+    }
+  }
+
+  if let Some(link) = link_consts.remove(&TrgId::new(0)) {
+    push(&mut out, &mut consts, &mut link_consts, link);
+  }
+  for instr in rest {
+    push(&mut out, &mut consts, &mut link_consts, instr);
+  }
+  // consts linked directly to other consts
+  for trg in const_order {
+    if let Some(c) = consts.remove(&trg) {
+      push(&mut out, &mut consts, &mut link_consts, c);
+    }
+  }
+  out
+}
+
+/// Renumbers the registers of `instr`, reusing the indices of registers after
+/// they are read, and returns the number of registers used.
+fn compact(instr: &mut [Instruction]) -> usize {
+  let mut map = Map::<TrgId, TrgId>::new();
+  let mut free = Vec::<usize>::new();
+  let mut next = 1;
+  map.insert(TrgId::new(0), TrgId::new(0));
+  for instr in instr {
+    regs_mut(instr, |trg, out| {
+      if out {
+        let index = free.pop().unwrap_or_else(|| {
+          next += 1;
+          next - 1
+        });
+        map.insert(*trg, TrgId::new(index));
+        *trg = TrgId::new(index);
+      } else {
+        *trg = map.remove(trg).unwrap();
+        free.push(trg.index());
+      }
+    });
+  }
+  next
+}
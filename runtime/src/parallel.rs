@@ -62,7 +62,9 @@ impl<'h> Net<'h> {
     let barry = Arc::new(Barrier::new(tids)); // global barrier
 
     // Perform parallel reductions
-    thread::scope(|s| {
+    let heap_start = self.heap.0.as_ptr() as usize;
+    let next = thread::scope(|s| {
+      let mut threads = Vec::with_capacity(tids);
       for net in self.fork(tids) {
         let mut ctx = ThreadContext {
           tid: net.tid,
@@ -75,10 +77,21 @@ impl<'h> Net<'h> {
           total: &total,
           barry: Arc::clone(&barry),
         };
-        thread::Builder::new().name(format!("t{:02x?}", ctx.net.tid)).spawn_scoped(s, move || main(&mut ctx)).unwrap();
+        let thread = thread::Builder::new().name(format!("t{:02x?}", ctx.net.tid)).spawn_scoped(s, move || {
+          main(&mut ctx);
+          // the extent of the heap used by this thread, relative to the whole heap
+          (ctx.net.heap.0.as_ptr() as usize - heap_start) / mem::size_of::<Node>() + ctx.net.next
+        });
+        threads.push(thread.unwrap());
       }
+      threads.into_iter().map(|thread| thread.join().unwrap()).max().unwrap_or(0)
     });
 
+    // Each thread allocated from its own slice of the heap, so the free list is
+    // stale, and everything up to the furthest extent may now be in use.
+    self.next = self.next.max(next);
+    self.head = Addr::NULL;
+
     delta.add_to(&mut self.rwts);
 
     // Main reduction loop
@@ -55,6 +55,16 @@ bi_enum! {
     /// The bottom 32 bits of this port are a `Num` (including the tag bits!),
     /// and can be accessed with `.num()`.
     Num = 3,
+    /// A `Tup` port represents the principal port of an n-ary interaction
+    /// combinator node, with at least three auxiliary ports.
+    ///
+    /// The label of this port is the label of the combinator, which behaves
+    /// exactly like nested binary combinators with that label.
+    ///
+    /// The address of this port is the address of the first of a chain of
+    /// two-word allocations, storing the targets of the wires connected to the
+    /// auxiliary ports of the node; see the `tup` module for their layout.
+    Tup = 4,
     /// An `Op` port represents the principal port of an Op node.
     ///
     /// The label of this port is the corresponding operation, which can be
@@ -72,7 +82,7 @@ bi_enum! {
     ///
     /// The label of this port is currently unused and always 0.
     Switch = 6,
-    /// A `Ctr` port represents the principal port of an binary interaction
+    /// A `Ctr` port represents the principal port of a binary interaction
     /// combinator node.
     ///
     /// The label of this port is the label of the combinator; two combinators
@@ -96,7 +106,7 @@ impl fmt::Debug for Port {
       _ => match self.tag() {
         Num => write!(f, "[Num {:?}]", self.num()),
         Var | Red | Switch => write!(f, "[{:?} {:?}]", self.tag(), self.addr()),
-        Op | Ctr | Ref => write!(f, "[{:?} {:?} {:?}]", self.tag(), self.lab(), self.addr()),
+        Op | Ctr | Tup | Ref => write!(f, "[{:?} {:?} {:?}]", self.tag(), self.lab(), self.addr()),
       },
     }
   }
@@ -49,11 +49,14 @@ mod def;
 mod instruction;
 mod interact;
 mod linker;
+mod native;
 mod net;
 mod node;
+mod optimize;
 mod parallel;
 mod port;
 pub mod trace;
+mod tup;
 mod wire;
 
 pub use addr::*;
@@ -61,15 +64,18 @@ pub use allocator::*;
 pub use def::*;
 pub use instruction::*;
 pub use linker::*;
+pub use native::*;
 pub use net::*;
 pub use node::*;
 pub use port::*;
+pub use tup::*;
 pub use wire::*;
 
 pub type Lab = u16;
 
 /// Tracks the number of rewrites, categorized by type.
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rewrites<T = u64> {
   pub anni: T,
   pub comm: T,
@@ -0,0 +1,162 @@
+//! Native n-ary combinators.
+//!
+//! A [`Tup`] port is the principal port of an n-ary combinator, with at least
+//! three aux ports, which behaves exactly like `n - 1` binary combinators with
+//! the same label nested in their second aux ports; i.e. `(a b c d)` behaves
+//! like `(a (b (c d)))`. Two n-ary combinators with the same label annihilate
+//! in a single interaction, however many ports they have, and erasing or
+//! copying a nilary agent into one is also a single interaction.
+//!
+//! In any other interaction, the n-ary combinator is first *unfolded* into a
+//! binary combinator whose second aux port holds the rest, which is again an
+//! n-ary combinator, or a binary one if only two ports are left. This is done
+//! in place, without allocating, so it costs nothing.
+//!
+//! An n-ary combinator with `n` ports is stored in `n - 1` two-word
+//! allocations, the first of which is the address of its `Tup` port. Each
+//! allocation but the last holds the target of an aux port, followed by a
+//! *link*: a [`Red`] port whose label is the number of ports of the combinator
+//! starting at this allocation, and whose address is the next allocation. The
+//! last allocation holds the targets of the last two aux ports, just like a
+//! binary combinator.
+
+use super::*;
+
+/// The largest number of ports of an n-ary combinator, which must fit in the
+/// label of a link.
+pub const MAX_TUP_ARITY: usize = Lab::MAX as usize;
+
+/// See [`Port::traverse_tup`].
+pub struct TraverseTup {
+  pub lab: Lab,
+  pub ports: Vec<Wire>,
+}
+
+impl Port {
+  /// The number of aux ports of the n-ary combinator this [`Tup`] port is the
+  /// principal port of.
+  #[inline(always)]
+  pub fn tup_arity(&self) -> usize {
+    Port(self.addr().other_half().val().load(Relaxed)).lab() as usize
+  }
+
+  /// Returns the wire leaving the first aux port of this [`Tup`] port's
+  /// combinator, and the principal port of the combinator holding the rest of
+  /// its aux ports.
+  #[inline(always)]
+  fn tup_rest(&self) -> (Wire, Port) {
+    let link = Port(self.addr().other_half().val().load(Relaxed));
+    let tag = if link.lab() > 3 { Tup } else { Ctr };
+    (Wire::new(self.addr()), Port::new(tag, self.lab(), link.addr()))
+  }
+
+  /// Unfolds the n-ary combinator of this [`Tup`] port in place, returning the
+  /// [`Ctr`] port of a binary combinator whose second aux port holds the rest.
+  #[inline(always)]
+  pub fn unfold_tup(self) -> Port {
+    let (p1, rest) = self.tup_rest();
+    Wire::new(p1.addr().other_half()).set_target(rest);
+    Port::new(Ctr, self.lab(), self.addr())
+  }
+
+  /// Returns the wires leaving each of the aux ports of this [`Tup`] port's
+  /// combinator, in order.
+  pub fn traverse_tup(self) -> TraverseTup {
+    let lab = self.lab();
+    let mut ports = vec![];
+    let mut tup = self;
+    while tup.is(Tup) {
+      let (p1, rest) = tup.tup_rest();
+      ports.push(p1);
+      tup = rest;
+    }
+    let node = tup.traverse_node();
+    ports.extend([node.p1, node.p2]);
+    TraverseTup { lab, ports }
+  }
+}
+
+impl<'a> Net<'a> {
+  /// `trg ~ {#lab x1 .. xn}`, where `arity` is `n`, which must be at least 3
+  /// and at most [`MAX_TUP_ARITY`]; `port` is called with the target of each
+  /// of the aux ports, in order.
+  #[inline(always)]
+  pub fn do_tup(&mut self, lab: Lab, trg: Trg, arity: usize, mut port: impl FnMut(Trg)) {
+    debug_assert!((3 ..= MAX_TUP_ARITY).contains(&arity));
+    let target = trg.target();
+    if target.tag() == Tup && target.lab() == lab && target.tup_arity() == arity {
+      trace!(self.tracer, "fast");
+      self.free_trg(trg);
+      self.rwts.anni += 1;
+      let mut tup = target;
+      while tup.is(Tup) {
+        let (p1, rest) = self.split_tup(tup);
+        port(Trg::wire(p1));
+        tup = rest;
+      }
+      let node = tup.consume_node();
+      port(Trg::wire(node.p1));
+      port(Trg::wire(node.p2));
+    } else {
+      let first = self.alloc();
+      let mut addr = first;
+      for arity in (3 ..= arity).rev() {
+        port(Trg::port(Port::new_var(addr)));
+        let next = self.alloc();
+        Wire::new(addr.other_half()).set_target(Port::new(Red, arity as Lab, next));
+        addr = next;
+      }
+      port(Trg::port(Port::new_var(addr)));
+      port(Trg::port(Port::new_var(addr.other_half())));
+      self.link_trg_port(trg, Port::new(Tup, lab, first));
+    }
+  }
+
+  /// Like [`Port::traverse_tup`], but only splits off the first aux port, and
+  /// frees the link to the rest.
+  #[inline(always)]
+  fn split_tup(&mut self, tup: Port) -> (Wire, Port) {
+    let (p1, rest) = tup.tup_rest();
+    self.half_free(p1.addr().other_half());
+    (p1, rest)
+  }
+
+  /// Annihilates two combinators with the same label, at least one of which
+  /// is n-ary.
+  ///
+  /// Their aux ports are linked in order, until one of them runs out; its last
+  /// aux port is then linked to the rest of the other.
+  #[inline(never)]
+  pub fn anni_tup(&mut self, a: Port, b: Port) {
+    trace!(self.tracer, a, b);
+    self.rwts.anni += 1;
+    let (mut a, mut b) = (a, b);
+    while a.is(Tup) && b.is(Tup) {
+      let (a1, a_rest) = self.split_tup(a);
+      let (b1, b_rest) = self.split_tup(b);
+      self.link_wire_wire(a1, b1);
+      (a, b) = (a_rest, b_rest);
+    }
+    let a = if a.is(Tup) { a.unfold_tup() } else { a }.consume_node();
+    let b = if b.is(Tup) { b.unfold_tup() } else { b }.consume_node();
+    self.link_wire_wire(a.p1, b.p1);
+    self.link_wire_wire(a.p2, b.p2);
+  }
+
+  /// Commutes a nilary agent and an n-ary combinator, linking each of its aux
+  /// ports to a copy of the nilary agent; see [`Net::comm02`].
+  #[inline(never)]
+  pub fn comm0_tup(&mut self, a: Port, b: Port) {
+    trace!(self.tracer, a, b);
+    self.rwts.comm += 1;
+    let mut b = b;
+    while b.is(Tup) {
+      let (p1, rest) = self.split_tup(b);
+      self.link_wire_port(p1, a.clone());
+      b = rest;
+    }
+    let b = b.consume_node();
+    self.link_wire_port(b.p1, a.clone());
+    self.link_wire_port(b.p2, a);
+  }
+}
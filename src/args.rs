@@ -1,4 +1,5 @@
 use clap::Args;
+use hvm64_ast::Encoding;
 use hvm64_transform::TransformPasses;
 use std::path::PathBuf;
 
@@ -15,6 +16,13 @@ pub struct RunArgs {
   /// "#1" "#2" "#3", then the expression that will get reduced is
   /// `r & @main ~ (#1 (#2 (#3 r)))`.
   pub args: Vec<String>,
+
+  /// Run the program as an IO program, performing the actions it reduces to.
+  ///
+  /// This makes the `@IO/*` defs available to the program, which can be used
+  /// to read from stdin, write to stdout, and read and write files.
+  #[arg(long)]
+  pub io: bool,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -27,6 +35,42 @@ pub struct RuntimeOpts {
   #[arg(short = '1', long = "single")]
   pub single_core: bool,
 
+  /// Report parts of the result that cannot be read back.
+  ///
+  /// After reducing, walks the heap to find disconnected subnets, vicious
+  /// circles, and unbound variables, and prints them to stderr.
+  #[arg(long = "check-readback", conflicts_with = "max_output")]
+  pub check_readback: bool,
+
+  /// Maximum number of nodes of the result to print.
+  ///
+  /// Subtrees beyond this limit are elided, and printed as refs such as `@…12`,
+  /// with the number of nodes in the subtree; the total is printed to stderr.
+  /// Also applies to the net that is decoded with '--readback lambda'. Supports
+  /// abbreviations such as '4K' or '400M'.
+  #[arg(long = "max-output", value_parser = hvm64_util::parse_abbrev_number::<usize>)]
+  pub max_output: Option<usize>,
+
+  /// How to print the result.
+  ///
+  /// With 'lambda' (or its alias 'data'), the result is decoded as a λ-term,
+  /// such as `λa λb (a b)`, in which Church numerals, Scott lists, and `true`
+  /// are printed as numbers, lists, and `true`; as `λa λb b` is zero, `false`,
+  /// and the empty list, it is printed as `0`. Results which aren't λ-terms are
+  /// printed as nets, after a note on stderr.
+  #[arg(long, default_value = "net")]
+  pub readback: ReadbackMode,
+
+  /// Print lists in the result that use this encoding as literals.
+  ///
+  /// Applies to results printed as nets, including those that are limited by
+  /// '--max-output' or checked by '--check-readback'.
+  ///
+  /// For example, the Scott-encoded list of the characters of "hi" is printed
+  /// as `"hi"`, rather than as the tree that encodes it.
+  #[arg(long = "readback-literals")]
+  pub readback_literals: Option<LiteralEncoding>,
+
   /// How much memory to allocate on startup.
   ///
   /// Supports abbreviations such as '4G' or '400M'.
@@ -76,6 +120,41 @@ pub struct TransformArgs {
 
   #[command(flatten)]
   pub transform_opts: TransformOpts,
+
+  /// How list and string literals, such as `[1 2]` and `"hi"`, are encoded.
+  #[arg(long, default_value = "scott")]
+  pub literals: LiteralEncoding,
+
+  /// Read the input files as untyped λ-calculus programs, rather than as
+  /// hvm-64 files, and compile them to interaction nets.
+  ///
+  /// A program is a list of named terms, such as `main = let id = λx x (id 1)`.
+  /// Applications are written as `(f a b)`, and native operations as
+  /// `(+ a b)`.
+  #[arg(long)]
+  pub lambda: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadbackMode {
+  Net,
+  #[value(alias = "data")]
+  Lambda,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum LiteralEncoding {
+  Scott,
+  Church,
+}
+
+impl From<LiteralEncoding> for Encoding {
+  fn from(encoding: LiteralEncoding) -> Self {
+    match encoding {
+      LiteralEncoding::Scott => Encoding::Scott,
+      LiteralEncoding::Church => Encoding::Church,
+    }
+  }
 }
 
 macro_rules! transform_passes {
@@ -140,6 +140,14 @@ fn compile_struct(code: &mut String, host: &Host, rust_name: &str, def: &Def<Int
       Instruction::Ctr { lab, trg, p1, p2 } => {
         writeln!(code, "let ({p1}, {p2}) = net.do_ctr({lab}, {trg});")
       }
+      Instruction::Tup { lab, trg, ports } => {
+        let n = ports.len();
+        let ports = ports.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+        writeln!(
+          code,
+          "let [{ports}] = {{ let mut tup = alloc::vec::Vec::with_capacity({n}); net.do_tup({lab}, {trg}, {n}, |trg| tup.push(trg)); <[Trg; {n}]>::try_from(tup).ok().unwrap() }};"
+        )
+      }
       Instruction::Op { op, trg, rhs, out } => {
         writeln!(code, "let ({rhs}, {out}) = net.do_op({op:?}, {trg});")
       }
@@ -195,16 +203,17 @@ fn compile_lab_set(labs: &LabSet) -> Result<String, fmt::Error> {
   Ok(str)
 }
 
-/// Adapts `name` to be a valid suffix for a rust identifier, if necessary.
+/// Adapts `name` to be a valid suffix for a rust identifier, if necessary, by
+/// replacing any `.`, `$`, and `/` in it.
 fn sanitize_name(name: &str) -> String {
-  if !name.contains('.') && !name.contains('$') {
+  if !name.contains(['.', '$', '/']) {
     name.to_owned()
   } else {
     // Append a hash to the name to avoid clashes between `foo.bar` and `foo_bar`.
     let mut hasher = DefaultHasher::new();
     hasher.write(name.as_bytes());
     let hash = hasher.finish();
-    let mut sanitized = name.replace(|c| c == '.' || c == '$', "_");
+    let mut sanitized = name.replace(['.', '$', '/'], "_");
     sanitized.push_str("__");
     write!(sanitized, "__{:016x}", hash).unwrap();
     sanitized
@@ -96,11 +96,14 @@ hvm64-num = { path = "../num" }
       instruction
       interact
       linker
+      native
       net
       node
+      optimize
       parallel
       port
       trace
+      tup
       wire
     }
   }
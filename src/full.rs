@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
-use crate::{args::TransformArgs, RunArgs, RuntimeOpts};
+use crate::{
+  args::{LiteralEncoding, TransformArgs},
+  RunArgs, RuntimeOpts,
+};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -15,7 +18,11 @@ A massively parallel Interaction Combinator evaluator
 Examples: 
 $ hvm64 run examples/church_encoding/church.hvm
 $ hvm64 run examples/addition.hvm "#16" "#3"
+$ hvm64 run --lambda examples/church_encoding/church.lam
 $ hvm64 compile examples/addition.hvm
+$ hvm64 disasm examples/addition.hvm @main
+$ hvm64 pack examples/addition.hvm -O all
+$ hvm64 fmt examples/addition.hvm --check
 $ hvm64 reduce examples/addition.hvm -- "a & @mul ~ (#3 (#4 a))"
 $ hvm64 reduce -- "a & #3 ~ <* #4 a>""##
 )]
@@ -73,6 +80,8 @@ pub enum CliMode {
     transform_args: TransformArgs,
   },
   /// Transform a hvm-64 program using one of the optimization passes.
+  ///
+  /// The resulting nets are printed in canonical form.
   Transform {
     /// Files to load before reducing the expressions.
     ///
@@ -82,4 +91,65 @@ pub enum CliMode {
     #[command(flatten)]
     transform_args: TransformArgs,
   },
+  /// Pack a hvm-64 program into the binary `.hvmb` format, which loads faster.
+  ///
+  /// A `.hvmb` file can be used anywhere a `.hvm` file can.
+  Pack {
+    /// Files to pack.
+    ///
+    /// Multiple files will act as if they're concatenated together.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Output path; defaults to the first file with its extension replaced by
+    /// `.hvmb`.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Only store the program, rather than also storing its definitions in the
+    /// form the runtime uses.
+    ///
+    /// This is required if the program refers to definitions that are only
+    /// available at runtime, such as those of `--io`.
+    #[arg(long)]
+    book_only: bool,
+    #[command(flatten)]
+    transform_args: TransformArgs,
+  },
+  /// Show the instructions that the definitions of a hvm-64 program are
+  /// encoded to.
+  Disasm {
+    /// Name of the file to load.
+    file: PathBuf,
+    /// Name of the definition to show, such as `@main`; if omitted, all
+    /// definitions are shown.
+    name: Option<String>,
+    #[command(flatten)]
+    transform_args: TransformArgs,
+  },
+  /// Format hvm-64 files in place.
+  Fmt {
+    /// Files to format.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Don't write the files; instead, list the files that aren't formatted,
+    /// and fail if there are any.
+    #[arg(long)]
+    check: bool,
+    /// The width lines should fit in.
+    #[arg(long, default_value = "100")]
+    width: usize,
+    /// The number of spaces to indent by.
+    #[arg(long, default_value = "2")]
+    indent: usize,
+    /// Align the `~`s of the redexes of each net.
+    #[arg(long)]
+    align_redexes: bool,
+    /// How list and string literals, such as `[1 2]` and `"hi"`, are encoded.
+    ///
+    /// Trees that encode lists in this encoding are printed as literals.
+    #[arg(long, default_value = "scott")]
+    literals: LiteralEncoding,
+    /// Expand list and string literals into the trees that encode them.
+    #[arg(long)]
+    expand_literals: bool,
+  },
 }
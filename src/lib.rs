@@ -0,0 +1,16 @@
+//! hvm64 as a library.
+//!
+//! [`Runtime`] ties together parsing, transforming, and running programs, for
+//! hosts embedding hvm64; the underlying crates are re-exported for finer
+//! control.
+
+pub use hvm64_ast as ast;
+pub use hvm64_host as host;
+pub use hvm64_num as num;
+pub use hvm64_runtime as run;
+pub use hvm64_transform as transform;
+
+mod runtime;
+
+#[cfg(feature = "std")]
+pub use runtime::{Config, Error, Runtime};
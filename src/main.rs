@@ -11,22 +11,25 @@ use std::{
     self,
     consts::{DLL_PREFIX, DLL_SUFFIX},
   },
+  error::Error as StdError,
   ffi::OsStr,
-  fs, io,
-  path::PathBuf,
-  process::{self, Stdio},
+  fs,
+  io::{self, Write},
+  path::{Path, PathBuf},
+  process::{self, ExitStatus, Stdio},
   time::Instant,
 };
 
 use self::full::{CliMode, FullCli};
 
-use args::{RunArgs, RuntimeOpts, TransformArgs, TransformPass};
+use args::{ReadbackMode, RunArgs, RuntimeOpts, TransformArgs, TransformPass};
 use clap::Parser;
 
-use hvm64_ast::{Book, Net, Tree};
-use hvm64_host::Host;
-use hvm64_runtime::{trace, DynDef, Heap, Port, Trg};
-use hvm64_transform::Transform;
+use hvm64::{Config, Error, Runtime};
+use hvm64_ast::{Book, Net, ParseOpts, PrettyOpts, Program, Tree};
+use hvm64_host::{book_from_binary, book_to_binary, decode_data, decode_lambda, Host};
+use hvm64_runtime::{self as run, trace, DynDef, Rewrites};
+use hvm64_transform::{Transform, TransformOpts};
 use hvm64_util::pretty_num;
 
 fn main() {
@@ -34,102 +37,238 @@ fn main() {
     trace::set_hook();
   }
 
-  let cli = FullCli::parse();
+  match run_cli(FullCli::parse()) {
+    Ok(()) => {}
+    // the unformatted files have already been listed
+    Err(CliError::Unformatted) => process::exit(1),
+    Err(e) => {
+      eprintln!("{e}");
+      process::exit(1);
+    }
+  }
+
+  if cfg!(feature = "trace") {
+    trace::_read_traces(usize::MAX);
+  }
+}
 
+fn run_cli(cli: FullCli) -> Result<(), CliError> {
   match cli.mode {
     CliMode::Compile { file, transform_args, output } => {
-      let output = if let Some(output) = output {
-        output
-      } else if let Some("hvm") = file.extension().and_then(OsStr::to_str) {
-        file.with_extension("")
-      } else {
-        eprintln!("file missing `.hvm` extension; explicitly specify an output path with `--output`.");
-
-        process::exit(1);
+      let output = match output {
+        Some(output) => output,
+        None if file.extension().and_then(OsStr::to_str) == Some("hvm") => file.with_extension(""),
+        None => Err("file missing `.hvm` extension; explicitly specify an output path with `--output`.")?,
       };
 
-      let host = Host::new(&load_book(&[file], transform_args));
-      compile::create_temp_hvm(&host).unwrap();
+      let host = Host::new(&transform_book(load_book(&[file], &transform_args)?, transform_args)?);
+      compile::create_temp_hvm(&host)?;
 
-      compile_temp_hvm().unwrap();
+      compile_temp_hvm()?;
 
-      fs::copy(format!(".hvm/target/release/{DLL_PREFIX}hvm64_gen{DLL_SUFFIX}"), output).unwrap();
+      fs::copy(format!(".hvm/target/release/{DLL_PREFIX}hvm64_gen{DLL_SUFFIX}"), output)?;
     }
     CliMode::Run { run_opts, mut transform_args, file, args } => {
       // Don't pre-reduce or prune the entry point
       transform_args.transform_opts.pre_reduce_skip.push(args.entry_point.clone());
       transform_args.transform_opts.prune_entrypoints.push(args.entry_point.clone());
 
-      let host = load_host(&[file], transform_args, &run_opts.include);
-      run(&host, run_opts, args);
+      let parse_opts = ParseOpts { literals: transform_args.literals.into() };
+      let mut net = Net { root: Tree::Ref(args.entry_point), redexes: vec![] };
+      for arg in &args.args {
+        let arg = Net::parse_with(arg, &parse_opts)?;
+        net.redexes.extend(arg.redexes);
+        net.apply_tree(arg.root);
+      }
+
+      let mut runtime = load_runtime(&[file], transform_args, &run_opts, args.io)?;
+      if args.io {
+        let result = runtime.run_io(&net, &mut io::stdin().lock(), &mut io::stdout()).map_err(|e| match e {
+          Error::Io(e) => CliError::from(format!("IO error: {e}")),
+          e => CliError::from(e),
+        })?;
+        println!("{}", result);
+      } else {
+        reduce_exprs(&mut runtime, &[net], &run_opts)?;
+      }
     }
     CliMode::Reduce { run_opts, transform_args, files, exprs } => {
-      let host = load_host(&files, transform_args, &run_opts.include);
-      let exprs: Vec<_> = exprs.iter().map(|x| x.parse().unwrap()).collect();
-      reduce_exprs(&host, &exprs, &run_opts);
+      let parse_opts = ParseOpts { literals: transform_args.literals.into() };
+      let exprs = exprs.iter().map(|x| Net::parse_with(x, &parse_opts)).collect::<Result<Vec<_>, _>>()?;
+      let mut runtime = load_runtime(&files, transform_args, &run_opts, false)?;
+      reduce_exprs(&mut runtime, &exprs, &run_opts)?;
     }
     CliMode::Transform { transform_args, files } => {
-      let book = load_book(&files, transform_args);
+      let mut book = transform_book(load_book(&files, &transform_args)?, transform_args)?;
+      for net in book.values_mut() {
+        net.canonicalize();
+      }
       println!("{}", book);
     }
+    CliMode::Pack { files, output, book_only, transform_args } => {
+      let output = output.unwrap_or_else(|| files[0].with_extension("hvmb"));
+      let book = transform_book(load_book(&files, &transform_args)?, transform_args)?;
+      let bytes = if book_only {
+        book_to_binary(&book)
+      } else {
+        let mut host = Host::default();
+        host.try_insert_book(&book).map_err(Error::from)?;
+        host.to_binary(&book)
+      };
+      fs::write(output, bytes)?;
+    }
+    CliMode::Disasm { file, name, transform_args } => {
+      let book = transform_book(load_book(&[file], &transform_args)?, transform_args)?;
+      let mut host = Host::default();
+      host.try_insert_book(&book).map_err(Error::from)?;
+      let names = match name {
+        Some(name) => {
+          let name = name.strip_prefix('@').unwrap_or(&name).to_owned();
+          if !book.contains_key(&name) {
+            Err(format!("definition @{name} not found"))?;
+          }
+          vec![name]
+        }
+        None => book.keys().cloned().collect(),
+      };
+      for (i, name) in names.iter().enumerate() {
+        if i != 0 {
+          println!();
+        }
+        println!("@{name}:");
+        let disasm =
+          host.disasm(name).ok_or_else(|| format!("definition @{name} is native, so it can't be disassembled"))?;
+        for line in disasm.to_string().lines() {
+          println!("  {line}");
+        }
+      }
+    }
+    CliMode::Fmt { files, check, width, indent, align_redexes, literals, expand_literals } => {
+      let parse_opts = ParseOpts { literals: literals.into() };
+      let literals = (!expand_literals).then(|| literals.into());
+      let opts = PrettyOpts { width, indent, align_redexes, literals };
+      let mut unformatted = false;
+      for file in files {
+        let contents =
+          String::from_utf8(read_file(&file)?).map_err(|_| format!("Input file {:?} is not valid UTF-8", file))?;
+        let (book, stray_comments) =
+          Book::parse_with_stray_comments(&contents, &parse_opts).map_err(|e| format!("Parsing error {e}"))?;
+        // only the comments before each def are kept, so formatting would
+        // remove any others
+        if let Some(line) = stray_comments.first() {
+          Err(format!(
+            "Input file {:?} has a comment on line {line} that isn't before a definition or import, which can't be formatted",
+            file
+          ))?;
+        }
+        let formatted = book.pretty(&opts) + "\n";
+        if formatted == contents {
+          continue;
+        }
+        if check {
+          println!("{}", file.display());
+          unformatted = true;
+        } else {
+          fs::write(&file, formatted)?;
+        }
+      }
+      if unformatted {
+        Err(CliError::Unformatted)?;
+      }
+    }
   };
 
-  if cfg!(feature = "trace") {
-    trace::_read_traces(usize::MAX);
+  Ok(())
+}
+
+/// Loads `files` into a [`Runtime`], along with the dylibs in `opts.include`,
+/// and the IO defs if `io` is set.
+fn load_runtime(
+  files: &[PathBuf],
+  transform_args: TransformArgs,
+  opts: &RuntimeOpts,
+  io: bool,
+) -> Result<Runtime, CliError> {
+  let mut host = Host::default();
+  load_dylibs(&mut host, &opts.include)?;
+  if io {
+    host.insert_io();
   }
+  let book = match files {
+    [file] if is_binary(file) => None,
+    _ => Some(load_book(files, &transform_args)?),
+  };
+  let config = Config {
+    single_core: opts.single_core,
+    memory: opts.memory,
+    max_rewrites: None,
+    max_output: opts.max_output,
+    transform_passes: TransformPass::to_passes(&transform_args.transform_passes),
+    transform_opts: transform_opts(transform_args.transform_opts),
+  };
+  Ok(match book {
+    Some(book) => Runtime::with_host(host, book, config)?,
+    None => Runtime::from_binary(host, &read_file(&files[0])?, config)?,
+  })
 }
 
-fn run(host: &Host, opts: RuntimeOpts, args: RunArgs) {
-  let mut net = Net { root: Tree::Ref(args.entry_point), redexes: vec![] };
-  for arg in args.args {
-    let arg: Net = arg.parse().unwrap();
-    net.redexes.extend(arg.redexes);
-    net.apply_tree(arg.root);
+/// Reads `files`, along with the files they import, into one book.
+fn load_book(files: &[PathBuf], transform_args: &TransformArgs) -> Result<Book, String> {
+  let opts = ParseOpts { literals: transform_args.literals.into() };
+  let lambda = transform_args.lambda;
+  let mut read = |name: &Path| if lambda { parse_lambda_file(name) } else { parse_file(name, &opts) };
+  let mut book = Book::default();
+  for name in files {
+    let mut file = read(name)?;
+    file.resolve_imports(name, &mut read).map_err(|e| e.to_string())?;
+    book.merge(file).map_err(|e| e.to_string())?;
   }
+  Ok(book)
+}
 
-  reduce_exprs(host, &[net], &opts);
+fn transform_book(mut book: Book, transform_args: TransformArgs) -> Result<Book, Error> {
+  let transform_passes = TransformPass::to_passes(&transform_args.transform_passes);
+  book.transform(transform_passes, &transform_opts(transform_args.transform_opts))?;
+  Ok(book)
 }
 
-fn load_host(files: &[PathBuf], transform_args: TransformArgs, include: &[PathBuf]) -> Host {
-  let mut host: Host = Default::default();
-  load_dylibs(&mut host, include);
-  host.insert_book(&load_book(files, transform_args));
-  host
+fn transform_opts(opts: args::TransformOpts) -> TransformOpts {
+  TransformOpts {
+    pre_reduce_skip: opts.pre_reduce_skip,
+    pre_reduce_memory: opts.pre_reduce_memory,
+    pre_reduce_rewrites: opts.pre_reduce_rewrites,
+    prune_entrypoints: opts.prune_entrypoints,
+  }
 }
 
-fn load_book(files: &[PathBuf], transform_args: TransformArgs) -> Book {
-  let mut book = files
-    .iter()
-    .map(|name| {
-      let contents = fs::read_to_string(name).unwrap_or_else(|_| {
-        eprintln!("Input file {:?} not found", name);
-        process::exit(1);
-      });
-      contents.parse().unwrap_or_else(|e| {
-        eprintln!("Parsing error {e}");
-        process::exit(1);
-      })
-    })
-    .fold(Book::default(), |mut acc, i: Book| {
-      acc.nets.extend(i.nets);
-      acc
-    });
-
-  let transform_passes = TransformPass::to_passes(&transform_args.transform_passes[..]);
-  book
-    .transform(transform_passes, &hvm64_transform::TransformOpts {
-      pre_reduce_skip: transform_args.transform_opts.pre_reduce_skip,
-      pre_reduce_memory: transform_args.transform_opts.pre_reduce_memory,
-      pre_reduce_rewrites: transform_args.transform_opts.pre_reduce_rewrites,
-      prune_entrypoints: transform_args.transform_opts.prune_entrypoints,
-    })
-    .unwrap();
-
-  book
-}
-
-fn load_dylibs(host: &mut Host, include: &[PathBuf]) {
-  let current_dir = env::current_dir().unwrap();
+/// Reads the book in a `.hvm` or `.hvmb` file, without resolving its imports.
+fn parse_file(name: &Path, opts: &ParseOpts) -> Result<Book, String> {
+  let contents = read_file(name)?;
+  if is_binary(name) {
+    return book_from_binary(&contents).map_err(|e| format!("Input file {:?}: {e}", name));
+  }
+  let contents = String::from_utf8(contents).map_err(|_| format!("Input file {:?} is not valid UTF-8", name))?;
+  Book::parse_with(&contents, opts).map_err(|e| format!("Parsing error in {:?}: {e}", name))
+}
+
+/// Reads the λ-calculus program in a file, and compiles it to a book.
+fn parse_lambda_file(name: &Path) -> Result<Book, String> {
+  let contents = fs::read_to_string(name).map_err(|_| format!("Input file {:?} not found", name))?;
+  let program: Program = contents.parse().map_err(|e| format!("Parsing error in {:?}: {e}", name))?;
+  program.to_book().map_err(|e| format!("Compilation error in {:?}: {e}", name))
+}
+
+fn read_file(name: &Path) -> Result<Vec<u8>, String> {
+  fs::read(name).map_err(|_| format!("Input file {:?} not found", name))
+}
+
+/// Whether `name` is a packed `.hvmb` file, rather than a `.hvm` file.
+fn is_binary(name: &Path) -> bool {
+  name.extension().and_then(OsStr::to_str) == Some("hvmb")
+}
+
+fn load_dylibs(host: &mut Host, include: &[PathBuf]) -> Result<(), CliError> {
+  let current_dir = env::current_dir()?;
 
   for file in include {
     unsafe {
@@ -138,10 +277,11 @@ fn load_dylibs(host: &mut Host, include: &[PathBuf]) {
       } else {
         libloading::Library::new(current_dir.join(file))
       }
-      .expect("failed to load dylib");
+      .map_err(|e| CliError::Dylib(file.clone(), e))?;
 
-      let rust_version =
-        lib.get::<fn() -> &'static str>(b"hvm64_dylib_v0__rust_version").expect("failed to load rust version");
+      let rust_version = lib
+        .get::<fn() -> &'static str>(b"hvm64_dylib_v0__rust_version")
+        .map_err(|e| CliError::Dylib(file.clone(), e))?;
       let rust_version = rust_version();
       if rust_version != env!("RUSTC_VERSION") {
         eprintln!(
@@ -150,8 +290,9 @@ fn load_dylibs(host: &mut Host, include: &[PathBuf]) {
         );
       }
 
-      let hvm64_version =
-        lib.get::<fn() -> &'static str>(b"hvm64_dylib_v0__hvm64_version").expect("failed to load hvm64 version");
+      let hvm64_version = lib
+        .get::<fn() -> &'static str>(b"hvm64_dylib_v0__hvm64_version")
+        .map_err(|e| CliError::Dylib(file.clone(), e))?;
       let hvm64_version = hvm64_version();
       if hvm64_version != env!("CARGO_PKG_VERSION") {
         eprintln!(
@@ -162,7 +303,7 @@ fn load_dylibs(host: &mut Host, include: &[PathBuf]) {
 
       let insert_into = lib
         .get::<fn(&mut dyn FnMut(&str, Box<DynDef>))>(b"hvm64_dylib_v0__insert_into")
-        .expect("failed to load insert_into");
+        .map_err(|e| CliError::Dylib(file.clone(), e))?;
       insert_into(&mut |name, def| {
         host.insert_def(name, def);
       });
@@ -171,40 +312,83 @@ fn load_dylibs(host: &mut Host, include: &[PathBuf]) {
       mem::forget(lib);
     }
   }
+  Ok(())
 }
 
-fn reduce_exprs(host: &Host, exprs: &[Net], opts: &RuntimeOpts) {
-  let heap = Heap::new(opts.memory).expect("memory allocation failed");
+fn reduce_exprs(runtime: &mut Runtime, exprs: &[Net], opts: &RuntimeOpts) -> Result<(), CliError> {
   for expr in exprs {
-    let net = &mut hvm64_runtime::Net::new(&heap);
-    host.encode_net(net, Trg::port(Port::new_var(net.root.addr())), expr);
     let start_time = Instant::now();
-    if opts.single_core {
-      net.normal();
-    } else {
-      net.parallel_normal();
+    let (rwts, elapsed, written) = runtime.reduce_with(expr, |host, net| {
+      let elapsed = start_time.elapsed();
+      (net.rwts, elapsed, print_result(host, net, opts))
+    })?;
+    match written {
+      Ok(()) => {}
+      // the reader went away, as in `hvm64 run big.hvm | head`
+      Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+      Err(e) => Err(CliError::Write(e))?,
     }
-    let elapsed = start_time.elapsed();
-    println!("{}", host.readback(net));
     if opts.show_stats {
-      print_stats(net, elapsed);
+      print_stats(&rwts, elapsed);
+    }
+  }
+  Ok(())
+}
+
+/// Reads back the normal form `net`, and prints it as `opts` specify. Any
+/// diagnostics about the readback are printed to stderr.
+fn print_result(host: &Host, net: &run::Net, opts: &RuntimeOpts) -> io::Result<()> {
+  let (out, report) = if opts.check_readback {
+    let (out, diagnostics) = host.readback_checked(net);
+    (out, diagnostics.to_string())
+  } else if let Some(max_nodes) = opts.max_output {
+    let (out, elided) = host.readback_limited(net, max_nodes, usize::MAX);
+    (out, elided.to_string())
+  } else if opts.readback == ReadbackMode::Net && opts.readback_literals.is_none() {
+    // the result is printed as-is, so it can be streamed to stdout
+    let mut stdout = io::BufWriter::new(io::stdout().lock());
+    host.readback_to(net, &mut stdout)?;
+    writeln!(stdout)?;
+    return stdout.flush();
+  } else {
+    (host.readback(net), String::new())
+  };
+  let term = match opts.readback {
+    ReadbackMode::Net => None,
+    ReadbackMode::Lambda => match decode_lambda(&out) {
+      Ok(term) => Some(decode_data(term)),
+      Err(e) => {
+        eprintln!("the result isn't a λ-term, so it's printed as a net: {e}");
+        None
+      }
+    },
+  };
+  let mut stdout = io::stdout().lock();
+  match (term, opts.readback_literals) {
+    (Some(term), _) => writeln!(stdout, "{term}")?,
+    (None, Some(literals)) => {
+      let opts = PrettyOpts { width: usize::MAX, literals: Some(literals.into()), ..Default::default() };
+      writeln!(stdout, "{}", out.pretty(&opts))?;
     }
+    (None, None) => writeln!(stdout, "{out}")?,
   }
+  eprint!("{report}");
+  Ok(())
 }
 
-fn print_stats(net: &hvm64_runtime::Net, elapsed: Duration) {
-  eprintln!("RWTS   : {:>15}", pretty_num(net.rwts.total()));
-  eprintln!("- ANNI : {:>15}", pretty_num(net.rwts.anni));
-  eprintln!("- COMM : {:>15}", pretty_num(net.rwts.comm));
-  eprintln!("- ERAS : {:>15}", pretty_num(net.rwts.eras));
-  eprintln!("- DREF : {:>15}", pretty_num(net.rwts.dref));
-  eprintln!("- OPER : {:>15}", pretty_num(net.rwts.oper));
+fn print_stats(rwts: &Rewrites, elapsed: Duration) {
+  eprintln!("RWTS   : {:>15}", pretty_num(rwts.total()));
+  eprintln!("- ANNI : {:>15}", pretty_num(rwts.anni));
+  eprintln!("- COMM : {:>15}", pretty_num(rwts.comm));
+  eprintln!("- ERAS : {:>15}", pretty_num(rwts.eras));
+  eprintln!("- DREF : {:>15}", pretty_num(rwts.dref));
+  eprintln!("- OPER : {:>15}", pretty_num(rwts.oper));
   eprintln!("TIME   : {:.3?}", elapsed);
-  eprintln!("RPS    : {:.3} M", (net.rwts.total() as f64) / (elapsed.as_millis() as f64) / 1000.0);
+  eprintln!("RPS    : {:.3} M", (rwts.total() as f64) / (elapsed.as_millis() as f64) / 1000.0);
 }
 
 /// Compiles the `.hvm` directory, appending the provided `args` to `cargo`.
-fn compile_temp_hvm() -> Result<(), io::Error> {
+fn compile_temp_hvm() -> Result<(), CliError> {
   let output = process::Command::new("cargo")
     .current_dir(".hvm/gen/")
     .arg("build")
@@ -213,8 +397,41 @@ fn compile_temp_hvm() -> Result<(), io::Error> {
     .output()?;
 
   if !output.status.success() {
-    process::exit(1);
+    Err(CliError::Compile(output.status))?;
   }
 
   Ok(())
 }
+
+/// An error that stops the CLI, making it exit with a failure code.
+#[derive(Debug)]
+enum CliError {
+  /// `hvm64 fmt --check` found files that aren't formatted, and listed them on
+  /// stdout.
+  Unformatted,
+  /// Building the generated crate failed; cargo has reported why.
+  Compile(ExitStatus),
+  /// A dylib passed with `--include` couldn't be loaded.
+  Dylib(PathBuf, libloading::Error),
+  /// The result couldn't be written to stdout.
+  Write(io::Error),
+  Other(Box<dyn StdError>),
+}
+
+impl fmt::Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CliError::Unformatted => write!(f, "some files aren't formatted"),
+      CliError::Compile(status) => write!(f, "failed to build the generated crate ({status})"),
+      CliError::Dylib(file, e) => write!(f, "failed to load dylib {file:?}: {e}"),
+      CliError::Write(e) => write!(f, "failed to write the result: {e}"),
+      CliError::Other(e) => write!(f, "{e}"),
+    }
+  }
+}
+
+impl<E: Into<Box<dyn StdError>>> From<E> for CliError {
+  fn from(e: E) -> Self {
+    CliError::Other(e.into())
+  }
+}
@@ -0,0 +1,355 @@
+#![cfg(feature = "std")]
+
+use hvm64_util::prelude::*;
+
+use std::{
+  error::Error as StdError,
+  fs,
+  io::{self, BufRead, Write},
+  path::Path,
+};
+
+use hvm64_ast::{Book, ImportError, Net, Tree};
+use hvm64_host::{book_from_binary, BinaryError, ElidedSubtrees, Host, MissingRefs};
+use hvm64_runtime::{self as run, Heap, Port, Rewrites, Trg};
+use hvm64_transform::{Transform, TransformError, TransformOpts, TransformPasses};
+
+/// The configuration of a [`Runtime`].
+#[derive(Clone, Debug)]
+pub struct Config {
+  /// Reduce on a single thread, rather than on all available cores.
+  pub single_core: bool,
+  /// How much memory to allocate for the heap, in bytes; if `None`, as much as
+  /// possible is allocated.
+  pub memory: Option<usize>,
+  /// The maximum number of rewrites a single reduction may perform. As the
+  /// limit is checked between batches of rewrites, reductions with a limit are
+  /// always done on a single thread.
+  pub max_rewrites: Option<u64>,
+  /// The maximum number of nodes to read back from a result. Elided subtrees
+  /// are replaced by refs such as `@…12`, and listed by
+  /// [`Runtime::elided`]; see [`Host::readback_limited`].
+  pub max_output: Option<usize>,
+  /// The transformation passes run on the book when it is loaded.
+  pub transform_passes: TransformPasses,
+  pub transform_opts: TransformOpts,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Config {
+      single_core: false,
+      memory: None,
+      max_rewrites: None,
+      max_output: None,
+      transform_passes: TransformPasses::NONE,
+      transform_opts: TransformOpts {
+        pre_reduce_skip: vec![],
+        pre_reduce_memory: None,
+        pre_reduce_rewrites: 100_000_000,
+        prune_entrypoints: vec!["main".to_owned()],
+      },
+    }
+  }
+}
+
+/// An error loading or running a program in a [`Runtime`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+  /// A file could not be read.
+  Io(io::Error),
+  /// A book or net could not be parsed.
+  Parse(String),
+  /// A `.hvmb` file could not be loaded.
+  Binary(BinaryError),
+  /// The imports of a book could not be resolved.
+  Import(ImportError),
+  /// A transformation pass failed.
+  Transform(TransformError),
+  /// A book refers to defs that do not exist.
+  MissingRefs(MissingRefs),
+  /// A net refers to a def that does not exist.
+  MissingDef(String),
+  /// The heap could not be allocated.
+  OutOfMemory,
+  /// A reduction reached [`Config::max_rewrites`].
+  RewriteLimit(u64),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Error::Io(e) => write!(f, "{e}"),
+      Error::Parse(e) => write!(f, "parse error: {e}"),
+      Error::Binary(e) => write!(f, "{e}"),
+      Error::Import(e) => write!(f, "{e}"),
+      Error::Transform(e) => write!(f, "{e}"),
+      Error::MissingRefs(e) => write!(f, "{e}"),
+      Error::MissingDef(name) => write!(f, "reference to undefined def `@{name}`"),
+      Error::OutOfMemory => write!(f, "memory allocation failed"),
+      Error::RewriteLimit(limit) => write!(f, "reduction exceeded the limit of {limit} rewrites"),
+    }
+  }
+}
+
+impl StdError for Error {}
+
+impl From<io::Error> for Error {
+  fn from(e: io::Error) -> Self {
+    Error::Io(e)
+  }
+}
+
+impl From<ImportError> for Error {
+  fn from(e: ImportError) -> Self {
+    Error::Import(e)
+  }
+}
+
+impl From<BinaryError> for Error {
+  fn from(e: BinaryError) -> Self {
+    Error::Binary(e)
+  }
+}
+
+impl From<MissingRefs> for Error {
+  fn from(e: MissingRefs) -> Self {
+    Error::MissingRefs(e)
+  }
+}
+
+impl From<TransformError> for Error {
+  fn from(e: TransformError) -> Self {
+    Error::Transform(e)
+  }
+}
+
+/// Loads and runs hvm64 programs, owning everything needed to do so.
+///
+/// ```rust,ignore
+/// let mut runtime = Runtime::from_file("examples/arithmetic.hvm", Config::default())?;
+/// println!("{}", runtime.apply("main", &["1", "2"])?);
+/// ```
+pub struct Runtime {
+  config: Config,
+  book: Book,
+  host: Host,
+  heap: Box<Heap>,
+  rwts: Rewrites,
+  elided: ElidedSubtrees,
+}
+
+impl Runtime {
+  /// Transforms `book` according to `config`, and loads it.
+  pub fn new(book: Book, config: Config) -> Result<Self, Error> {
+    Self::with_host(Host::default(), book, config)
+  }
+
+  /// Like [`Runtime::new`], but loads the book into `host`, which may already
+  /// contain defs the book refers to, such as native ones.
+  pub fn with_host(mut host: Host, mut book: Book, config: Config) -> Result<Self, Error> {
+    book.transform(config.transform_passes, &config.transform_opts)?;
+    host.try_insert_book(&book)?;
+    Self::from_parts(host, book, config)
+  }
+
+  /// Loads the book packed in a `.hvmb` file into `host`.
+  ///
+  /// Packed defs are inserted directly, unless the book needs to be
+  /// transformed.
+  pub fn from_binary(mut host: Host, bytes: &[u8], config: Config) -> Result<Self, Error> {
+    if config.transform_passes != TransformPasses::NONE {
+      return Self::with_host(host, book_from_binary(bytes)?, config);
+    }
+    let book = host.load_binary(bytes)?;
+    Self::from_parts(host, book, config)
+  }
+
+  fn from_parts(host: Host, book: Book, config: Config) -> Result<Self, Error> {
+    let heap = Heap::new(config.memory).ok_or(Error::OutOfMemory)?;
+    Ok(Runtime { config, book, host, heap, rwts: Rewrites::default(), elided: ElidedSubtrees::default() })
+  }
+
+  /// Parses a book from `code`, and loads it.
+  pub fn from_str(code: &str, config: Config) -> Result<Self, Error> {
+    Self::new(code.parse().map_err(Error::Parse)?, config)
+  }
+
+  /// Parses a book from the file at `path`, along with the files it imports,
+  /// and loads it.
+  pub fn from_file(path: impl AsRef<Path>, config: Config) -> Result<Self, Error> {
+    let path = path.as_ref();
+    let mut book: Book = fs::read_to_string(path)?.parse().map_err(Error::Parse)?;
+    book.resolve_imports(path, &mut |path| {
+      let code = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+      code.parse().map_err(|e| format!("parse error in {}: {e}", path.display()))
+    })?;
+    Self::new(book, config)
+  }
+
+  /// The book loaded into the host, after it was transformed.
+  pub fn book(&self) -> &Book {
+    &self.book
+  }
+
+  pub fn host(&self) -> &Host {
+    &self.host
+  }
+
+  pub fn config(&self) -> &Config {
+    &self.config
+  }
+
+  /// The total rewrites done by all reductions so far.
+  pub fn rewrites(&self) -> &Rewrites {
+    &self.rwts
+  }
+
+  /// The subtrees elided from the result of the last reduction, as it had more
+  /// than [`Config::max_output`] nodes. Empty if the result was read back in
+  /// its entirety.
+  pub fn elided(&self) -> &ElidedSubtrees {
+    &self.elided
+  }
+
+  /// Parses a net, such as `a & @main ~ (1 a)`, and reduces it.
+  pub fn eval(&mut self, net: &str) -> Result<Net, Error> {
+    let net = net.parse().map_err(Error::Parse)?;
+    self.reduce(&net)
+  }
+
+  /// Applies the def `name` to each of `args` in turn, as with `hvm64 run`,
+  /// and reduces the result.
+  pub fn apply(&mut self, name: &str, args: &[&str]) -> Result<Net, Error> {
+    let mut net = Net { root: Tree::Ref(name.to_owned()), redexes: vec![] };
+    for arg in args {
+      let arg: Net = arg.parse().map_err(Error::Parse)?;
+      net.redexes.extend(arg.redexes);
+      net.apply_tree(arg.root);
+    }
+    self.reduce(&net)
+  }
+
+  /// Reduces `net`, and reads back its normal form.
+  pub fn reduce(&mut self, net: &Net) -> Result<Net, Error> {
+    let max_output = self.config.max_output;
+    let (net, elided) = self.reduce_with(net, |host, rnet| readback(host, rnet, max_output))?;
+    self.elided = elided;
+    Ok(net)
+  }
+
+  /// Reduces `net`, and passes its normal form to `readback`, for callers that
+  /// need more than [`Runtime::reduce`] offers, such as the rewrites of this
+  /// reduction alone, or a streaming or checked readback.
+  pub fn reduce_with<T>(&mut self, net: &Net, readback: impl FnOnce(&Host, &run::Net) -> T) -> Result<T, Error> {
+    check_refs(&self.host, net)?;
+    self.reduce_in(
+      |host, rnet| {
+        let root = Trg::port(Port::new_var(rnet.root.addr()));
+        host.encode_net(rnet, root, net);
+      },
+      readback,
+    )
+  }
+
+  /// Expands the def `name` at the root, and reduces it, without the rewrite
+  /// [`Runtime::apply`] spends on the ref to `name`.
+  pub fn boot(&mut self, name: &str) -> Result<Net, Error> {
+    let max_output = self.config.max_output;
+    let (net, elided) = self.boot_with(name, |host, rnet| readback(host, rnet, max_output))?;
+    self.elided = elided;
+    Ok(net)
+  }
+
+  /// Like [`Runtime::boot`], but passes the normal form to `readback`, as
+  /// [`Runtime::reduce_with`] does.
+  pub fn boot_with<T>(&mut self, name: &str, readback: impl FnOnce(&Host, &run::Net) -> T) -> Result<T, Error> {
+    if !self.host.defs.contains_key(name) {
+      return Err(Error::MissingDef(name.to_owned()));
+    }
+    self.reduce_in(|host, rnet| rnet.boot(&host.defs[name]), readback)
+  }
+
+  fn reduce_in<T>(
+    &mut self,
+    init: impl FnOnce(&Host, &mut run::Net),
+    readback: impl FnOnce(&Host, &run::Net) -> T,
+  ) -> Result<T, Error> {
+    let mut rnet = run::Net::new(&self.heap);
+    init(&self.host, &mut rnet);
+    let result = normal(&self.config, &mut rnet);
+    add_rewrites(&mut self.rwts, &rnet.rwts);
+    result?;
+    Ok(readback(&self.host, &rnet))
+  }
+
+  /// Runs `net` as an IO program, as with `hvm64 run --io`, and returns its
+  /// result; see [`Host::run_io`]. The host must contain the IO defs.
+  pub fn run_io(&mut self, net: &Net, stdin: &mut impl BufRead, stdout: &mut impl Write) -> Result<Net, Error> {
+    check_refs(&self.host, net)?;
+    let (config, rwts) = (&self.config, &mut self.rwts);
+    let mut error = None;
+    let result = self.host.run_io(&self.heap, net.clone(), stdin, stdout, |rnet| {
+      // once a reduction fails, the rest are skipped
+      if error.is_none() {
+        error = normal(config, rnet).err();
+        // the same net is reduced after each action, so its rewrites are
+        // counted from zero each time
+        add_rewrites(rwts, &mem::take(&mut rnet.rwts));
+      }
+    });
+    match error {
+      Some(error) => Err(error),
+      None => Ok(result?),
+    }
+  }
+}
+
+/// Reads back `rnet`, eliding subtrees past `max_output` nodes.
+fn readback(host: &Host, rnet: &run::Net, max_output: Option<usize>) -> (Net, ElidedSubtrees) {
+  match max_output {
+    Some(max_nodes) => host.readback_limited(rnet, max_nodes, usize::MAX),
+    None => (host.readback(rnet), ElidedSubtrees::default()),
+  }
+}
+
+/// Reduces `rnet` to normal form, within the limits of `config`.
+fn normal(config: &Config, rnet: &mut run::Net) -> Result<(), Error> {
+  if let Some(limit) = config.max_rewrites {
+    rnet.expand();
+    while !rnet.redexes.is_empty() {
+      if rnet.rwts.total() >= limit {
+        return Err(Error::RewriteLimit(limit));
+      }
+      rnet.reduce(usize::try_from(limit - rnet.rwts.total()).unwrap_or(usize::MAX));
+    }
+  } else if config.single_core {
+    rnet.normal();
+  } else {
+    rnet.parallel_normal();
+  }
+  Ok(())
+}
+
+fn add_rewrites(total: &mut Rewrites, rwts: &Rewrites) {
+  total.anni += rwts.anni;
+  total.comm += rwts.comm;
+  total.eras += rwts.eras;
+  total.dref += rwts.dref;
+  total.oper += rwts.oper;
+}
+
+/// Checks that every ref in `net` is a def in `host`.
+fn check_refs(host: &Host, net: &Net) -> Result<(), Error> {
+  let mut trees = net.trees().collect::<Vec<_>>();
+  while let Some(tree) = trees.pop() {
+    if let Tree::Ref(name) = tree {
+      if !host.defs.contains_key(name) {
+        return Err(Error::MissingDef(name.clone()));
+      }
+    }
+    trees.extend(tree.children());
+  }
+  Ok(())
+}
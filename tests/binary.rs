@@ -0,0 +1,65 @@
+//! Tests for the `.hvmb` binary format.
+
+use hvm64_ast::Book;
+use hvm64_host::{book_from_binary, book_to_binary, BinaryError, Host};
+use hvm64_runtime as run;
+use insta::assert_snapshot;
+
+const BOOK: &str = "
+  @add = (a (b c)) & a ~ $([+] $(b c))
+  @main = ((a a) (@pair b)) & @add ~ (1.5 (-2 b))
+  @pair = (x #3{?((1 @add) x) *})
+";
+
+fn reduce(host: &Host, net: &str) -> String {
+  let heap = run::Heap::new(Some(1 << 16)).unwrap();
+  let mut rnet = run::Net::new(&heap);
+  let root = run::Trg::port(run::Port::new_var(rnet.root.addr()));
+  host.encode_net(&mut rnet, root, &net.parse().unwrap());
+  rnet.normal();
+  host.readback(&rnet).to_string()
+}
+
+#[test]
+fn test_binary_roundtrip() {
+  let book: Book = BOOK.parse().unwrap();
+  assert_eq!(book_from_binary(&book_to_binary(&book)).unwrap().to_string(), book.to_string());
+
+  let host = Host::new(&book);
+  let bytes = host.to_binary(&book);
+  assert_eq!(book_from_binary(&bytes).unwrap().to_string(), book.to_string());
+
+  let mut loaded = Host::default();
+  assert_eq!(loaded.load_binary(&bytes).unwrap().to_string(), book.to_string());
+  for name in book.keys() {
+    assert_eq!(loaded.disasm(name).unwrap().to_string(), host.disasm(name).unwrap().to_string());
+    assert_eq!(loaded.defs[name].labs, host.defs[name].labs);
+  }
+  assert_eq!(reduce(&loaded, "@main"), reduce(&host, "@main"));
+
+  let mut loaded = Host::default();
+  loaded.load_binary(&book_to_binary(&book)).unwrap();
+  assert_eq!(reduce(&loaded, "@main"), reduce(&host, "@main"));
+}
+
+#[test]
+fn test_binary_errors() {
+  let book: Book = BOOK.parse().unwrap();
+  let bytes = Host::new(&book).to_binary(&book);
+  for len in 0 .. bytes.len() {
+    assert!(Host::default().load_binary(&bytes[.. len]).is_err());
+  }
+  let mut bytes = bytes;
+  bytes.push(0);
+  assert_snapshot!(Host::default().load_binary(&bytes).unwrap_err(), @"invalid .hvmb data: trailing data");
+  bytes[4] = 0;
+  assert_snapshot!(Host::default().load_binary(&bytes).unwrap_err(), @"unsupported .hvmb version 0 (expected 1)");
+  assert_snapshot!(book_from_binary(BOOK.as_bytes()).unwrap_err(), @"not a .hvmb file");
+  assert!(matches!(book_from_binary(b"HVMB\x01\x00\x01\x00\x01\x00\x00\x07"), Err(BinaryError::Invalid(_))));
+
+  let book: Book = "@main = (@missing @other)\n@other = @missing".parse().unwrap();
+  assert_snapshot!(Host::default().load_binary(&book_to_binary(&book)).unwrap_err(), @r###"
+  missing definitions:
+    @missing, referenced by @main, @other
+  "###);
+}
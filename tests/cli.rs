@@ -1,10 +1,11 @@
 //! Test the `hvm64` binary, including its CLI interface.
 
 use std::{
+  env,
   error::Error,
   fs,
-  io::Read,
-  process::{Command, ExitStatus, Stdio},
+  io::{Read, Write},
+  process::{self, Command, ExitStatus, Stdio},
 };
 
 use hvm64_ast::{Net, Tree};
@@ -89,6 +90,85 @@ fn test_cli_reduce() {
   )
 }
 
+#[test]
+fn test_cli_max_output() {
+  assert_snapshot!(
+    execute_hvm64(&["reduce", "-m", "100M", "--max-output", "2", "--", "((a a) ((b b) (c c)))"]).unwrap().1,
+    @r###"
+  ((a a) @…3)
+  3 node(s) elided in 1 subtree(s)
+  "###
+  );
+  assert_snapshot!(
+    execute_hvm64(&["reduce", "--max-output", "2", "--check-readback", "--", "*"]).unwrap().1,
+    @r###"
+  error: the argument '--max-output <MAX_OUTPUT>' cannot be used with '--check-readback'
+
+  Usage: hvm64 reduce --max-output <MAX_OUTPUT> [FILES]... -- <EXPRS>...
+
+  For more information, try '--help'.
+  "###
+  );
+}
+
+#[test]
+fn test_cli_literals() {
+  assert_snapshot!(
+    execute_hvm64(&["reduce", "-m", "100M", "--readback-literals", "scott", "--", r#"["hi" 1 []]"#]).unwrap().1,
+    @r###"
+  ["hi" 1 []]
+  "###
+  );
+  assert_snapshot!(
+    execute_hvm64(&[
+      "reduce", "-m", "100M",
+      "--literals", "church", "--readback-literals", "church",
+      "--", "[1 2 3]"
+    ]).unwrap().1,
+    @r###"
+  [1 2 3]
+  "###
+  );
+}
+
+#[test]
+fn test_cli_run_io() {
+  let path = env::temp_dir().join(format!("hvm64-test-cli-io-{}.hvm", process::id()));
+  fs::write(
+    &path,
+    "@main = a & @IO/read_line ~ (* (@echo a))\n@echo = (o a) & o ~ ((x x) (* l)) & @IO/print ~ (l ((* b) a)) & @IO/done ~ (1 b)",
+  )
+  .unwrap();
+  let mut child = Command::new(env!("CARGO_BIN_EXE_hvm64"))
+    .args(["run", "-m", "100M", "-1", "--io", path.to_str().unwrap()])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child.stdin.take().unwrap().write_all(b"hello\n").unwrap();
+  let output = child.wait_with_output().unwrap();
+  fs::remove_file(&path).unwrap();
+  assert!(output.status.success());
+  assert_snapshot!(String::from_utf8(output.stdout).unwrap(), @"hello1");
+}
+
+#[test]
+fn test_cli_broken_pipe() {
+  let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/tests/programs/alloc_big_tree.hvm";
+  let mut child = Command::new(env!("CARGO_BIN_EXE_hvm64"))
+    .args(["run", "-m", "100M", "-1", &path])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  // the output is larger than the pipe buffer, so writing it fails once the
+  // read end is closed
+  drop(child.stdout.take());
+  let output = child.wait_with_output().unwrap();
+  assert!(output.status.success());
+  assert_snapshot!(String::from_utf8(output.stderr).unwrap(), @"");
+}
+
 #[test]
 fn test_cli_run_with_args() {
   let arithmetic_program = get_arithmetic_program_path();
@@ -169,8 +249,8 @@ fn test_cli_transform() {
   @div = ($([/] $(a b)) (a b))
 
   @main = (#1{a b} (#2{c d} {e f}))
-    & @mod ~ (b (d f))
     & @div ~ (a (c e))
+    & @mod ~ (b (d f))
 
   @mod = ($([%] $(a b)) (a b))
 
@@ -202,6 +282,169 @@ fn test_cli_errors() {
   );
 }
 
+#[test]
+fn test_cli_disasm() {
+  let arithmetic_program = get_arithmetic_program_path();
+  assert_snapshot!(execute_hvm64(&["disasm", &arithmetic_program, "@add"]).unwrap().1, @r###"
+  @add:
+    tup        t0 0 -> t0 t1 t2
+    op         t0 [+] -> t0 t3
+    link       t0 t1
+    link       t3 t2
+  "###);
+  let (status, output) = execute_hvm64(&["disasm", &arithmetic_program, "@nope"]).unwrap();
+  assert!(!status.success());
+  assert_snapshot!(output, @"definition @nope not found");
+}
+
+#[test]
+fn test_cli_pack() {
+  let arithmetic_program = get_arithmetic_program_path();
+  let path = env::temp_dir().join(format!("hvm64-test-cli-pack-{}.hvmb", process::id()));
+  let path = path.to_str().unwrap();
+  for extra_args in [&["-O", "all"][..], &["--book-only"], &[]] {
+    let mut args = vec!["pack", &arithmetic_program, "-o", path];
+    args.extend(extra_args);
+    assert!(execute_hvm64(&args).unwrap().0.success());
+    assert_eq!(execute_hvm64(&["run", path, "64", "3"]).unwrap().1, "{21 1}\n");
+  }
+  assert_snapshot!(execute_hvm64(&["reduce", path, "--", "a & @mul ~ (3 (4 a))"]).unwrap().1, @"12");
+  fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_cli_fmt() {
+  let path = env::temp_dir().join(format!("hvm64-test-cli-fmt-{}.hvm", process::id()));
+  let path = path.to_str().unwrap();
+  fs::write(
+    path,
+    "@main = r & @foo ~ (1 (2 r))\n// Divides.\n@foo = (#1{$([/] $(a b)) $([%] $(c d))} (#2{a c} {b d}))",
+  )
+  .unwrap();
+  let (status, output) = execute_hvm64(&["fmt", "--check", path]).unwrap();
+  assert!(!status.success());
+  assert_eq!(output, format!("{path}\n"));
+  assert!(execute_hvm64(&["fmt", "--width", "30", path]).unwrap().0.success());
+  assert_snapshot!(fs::read_to_string(path).unwrap(), @r###"
+  // Divides.
+  @foo = (
+    #1{
+      $([/] $(a b))
+      $([%] $(c d))
+    }
+    (#2{a c} {b d})
+  )
+
+  @main = r
+    & @foo ~ (1 (2 r))
+  "###);
+  assert!(execute_hvm64(&["fmt", "--check", "--width", "30", path]).unwrap().0.success());
+  fs::write(path, "@main = (a\n  // the argument\n  a)").unwrap();
+  let (status, output) = execute_hvm64(&["fmt", path]).unwrap();
+  assert!(!status.success());
+  assert_eq!(
+    output,
+    format!(
+      "Input file {path:?} has a comment on line 2 that isn't before a definition or import, which can't be formatted\n"
+    )
+  );
+  // literals are kept unless expanded, and `//` in a string isn't a comment
+  fs::write(path, "@main = [\"a // b\" [1 2]]").unwrap();
+  assert!(execute_hvm64(&["fmt", path]).unwrap().0.success());
+  assert_eq!(fs::read_to_string(path).unwrap(), "@main = [\"a // b\" [1 2]]\n");
+  assert!(execute_hvm64(&["fmt", "--expand-literals", path]).unwrap().0.success());
+  assert!(!fs::read_to_string(path).unwrap().contains(['"', '[']));
+  fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_cli_imports() {
+  let dir = env::temp_dir().join(format!("hvm64-test-cli-imports-{}", process::id()));
+  fs::create_dir_all(dir.join("lib")).unwrap();
+  fs::write(dir.join("main.hvm"), "@import \"lib/lib.hvm\" as lib\n@main = a & @lib/double ~ (21 a)").unwrap();
+  fs::write(dir.join("lib/lib.hvm"), "@import \"util.hvm\" as util\n@double = ($([*] $(2 a)) a)\n@id = @util/id")
+    .unwrap();
+  fs::write(dir.join("lib/util.hvm"), "@id = (a a)").unwrap();
+  let main = dir.join("main.hvm");
+  let main = main.to_str().unwrap();
+
+  assert_snapshot!(execute_hvm64(&["run", main]).unwrap().1, @"42");
+  assert_snapshot!(execute_hvm64(&["transform", main]).unwrap().1, @r###"
+  @lib/double = ($([*] $(2 a)) a)
+
+  @lib/id = @lib/util/id
+
+  @lib/util/id = (a a)
+
+  @main = a
+    & @lib/double ~ (21 a)
+  "###);
+
+  let (status, output) = execute_hvm64(&["transform", main, main]).unwrap();
+  assert!(!status.success());
+  assert_snapshot!(output, @"duplicate definition of `@lib/double`");
+
+  fs::write(dir.join("lib/util.hvm"), "@import \"../main.hvm\" as main\n@id = (a a)").unwrap();
+  let (status, output) = execute_hvm64(&["run", main]).unwrap();
+  assert!(!status.success());
+  assert!(output.starts_with("import cycle: "), "{output}");
+  let cycle_end = format!("util.hvm -> {}", fs::canonicalize(main).unwrap().display());
+  assert!(output.trim_end().ends_with(&cycle_end), "{output}");
+
+  fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_lambda() {
+  let path = env::temp_dir().join(format!("hvm64-test-cli-lambda-{}.lam", process::id()));
+  let path = path.to_str().unwrap();
+  fs::write(path, "double = λx (* x 2)\nmain = let f = λn (+ (double n) 1) (f (f 3))").unwrap();
+  assert_snapshot!(execute_hvm64(&["transform", "--lambda", path]).unwrap().1, @r###"
+  @double = ($([*] $(2 a)) a)
+
+  @main = a
+    & @double ~ (b $([+] $(1 c)))
+    & #main.f{(d a) (3 d)} ~ (b c)
+  "###);
+  assert_snapshot!(execute_hvm64(&["run", "--lambda", path]).unwrap().1, @"15");
+
+  fs::write(path, "main = [(+ 1 2) λx λy x λx λy (x y)]").unwrap();
+  assert_snapshot!(execute_hvm64(&["run", "--lambda", "--readback", "data", path]).unwrap().1, @"[3 true 1]");
+  assert_snapshot!(execute_hvm64(&["run", "--lambda", "--readback", "lambda", path]).unwrap().1, @"[3 true 1]");
+  assert_snapshot!(execute_hvm64(&["run", "--lambda", "--readback", "lambda", "--max-output", "4", path]).unwrap().1, @r###"
+  ((3 ((@…13 @…1) a)) @…1)
+  the result isn't a λ-term, so it's printed as a net: expected a λ-term consumer, found @…13
+  15 node(s) elided in 3 subtree(s)
+  "###);
+  assert_snapshot!(execute_hvm64(&["reduce", "--readback", "lambda", "--", "(1 2)"]).unwrap().1, @r###"
+  (1 2)
+  the result isn't a λ-term, so it's printed as a net: expected a λ-term consumer, found 1
+  "###);
+
+  fs::write(path, "main = λx (x y)").unwrap();
+  let (status, output) = execute_hvm64(&["run", "--lambda", path]).unwrap();
+  assert!(!status.success());
+  assert_eq!(output, format!("Compilation error in {path:?}: in `main`: unbound variable `y`\n"));
+  fs::remove_file(path).unwrap();
+
+  let church = env!("CARGO_MANIFEST_DIR").to_owned() + "/examples/church_encoding/church.lam";
+  assert_snapshot!(execute_hvm64(&["run", "--lambda", &church]).unwrap().1, @"10");
+}
+
+#[test]
+fn test_cli_missing_refs() {
+  let path = env::temp_dir().join(format!("hvm64-test-cli-missing-{}.hvm", process::id()));
+  fs::write(&path, "@main = (@foo (@bar @baz))\n@baz = @foo").unwrap();
+  let (status, output) = execute_hvm64(&["run", path.to_str().unwrap()]).unwrap();
+  fs::remove_file(&path).unwrap();
+  assert!(!status.success());
+  assert_snapshot!(output, @r###"
+  missing definitions:
+    @bar, referenced by @main
+    @foo, referenced by @baz, @main
+  "###);
+}
+
 #[test]
 fn test_apply_tree() {
   fn eval_with_args(fun: &str, args: &[&str]) -> Net {
@@ -0,0 +1,100 @@
+//! Tests for replacing and removing the defs of a host.
+
+use hvm64_ast::{Book, Net};
+use hvm64_host::Host;
+use hvm64_runtime::{self as run, Def, InterpretedDef, LabSet};
+
+fn reduce(host: &Host, net: &str) -> String {
+  let heap = run::Heap::new(Some(1 << 16)).unwrap();
+  let mut rnet = run::Net::new(&heap);
+  let root = run::Trg::port(run::Port::new_var(rnet.root.addr()));
+  host.encode_net(&mut rnet, root, &net.parse::<Net>().unwrap());
+  rnet.normal();
+  host.readback(&rnet).to_string()
+}
+
+fn interpreted(host: &Host, net: &str) -> Box<Def<InterpretedDef>> {
+  Box::new(Def::new(LabSet::NONE, host.encode_def(&net.parse().unwrap())))
+}
+
+fn labs(host: &Host, name: &str) -> Vec<u16> {
+  (0 .. 8).filter(|&lab| host.defs[name].labs.has(lab)).collect()
+}
+
+#[test]
+fn test_replace_def() {
+  let book: Book = "
+    @one = 1
+    @pair = (@one @two)
+    @two = #1(x x)
+  "
+  .parse()
+  .unwrap();
+  let mut host = Host::new(&book);
+  assert_eq!(reduce(&host, "@pair"), "(1 #1(a a))");
+  assert_eq!(labs(&host, "pair"), [0, 2]);
+
+  let def = interpreted(&host, "#2{@two 3}");
+  let old = host.replace_def("one", def);
+  assert!(old.is_some());
+  assert_eq!(reduce(&host, "@pair"), "(#2{#1(a a) 3} #1(b b))");
+  assert_eq!(reduce(&host, "@one"), "#2{#1(a a) 3}");
+  assert_eq!(labs(&host, "pair"), [0, 2, 5]);
+  assert_eq!(labs(&host, "one"), [2, 5]);
+  assert_eq!(host.back.len(), host.defs.len());
+
+  // replacing a def that others refer to recursively
+  let def = interpreted(&host, "(* @one)");
+  host.replace_def("one", def);
+  assert_eq!(labs(&host, "one"), [0]);
+  assert_eq!(labs(&host, "pair"), [0, 2]);
+
+  assert!(host.replace_def("three", interpreted(&host, "3")).is_none());
+  assert_eq!(reduce(&host, "@three"), "3");
+}
+
+#[test]
+fn test_remove_def() {
+  let book: Book = "
+    @a = (@b @c)
+    @b = (@b @c)
+    @c = 1
+  "
+  .parse()
+  .unwrap();
+  let mut host = Host::new(&book);
+  assert_eq!(host.remove_def("c").err().unwrap(), ["a", "b"]);
+  assert_eq!(host.remove_def("b").err().unwrap(), ["a"]);
+  assert!(matches!(host.remove_def("a"), Ok(Some(_))));
+  assert!(matches!(host.remove_def("b"), Ok(Some(_))));
+  assert!(matches!(host.remove_def("c"), Ok(Some(_))));
+  assert!(matches!(host.remove_def("c"), Ok(None)));
+  assert!(matches!(host.remove_def("nope"), Ok(None)));
+  assert!(host.defs.is_empty());
+  assert!(host.back.is_empty());
+}
+
+#[test]
+fn test_insert_book_in_pieces() {
+  let book: Book = "
+    @a = (@b @c)
+    @c = #1(x x)
+  "
+  .parse()
+  .unwrap();
+  let mut host = Host::default();
+  host.insert_book_with_default(&book, &mut |_| Box::new(Def::new(LabSet::NONE, InterpretedDef::default())));
+  assert_eq!(labs(&host, "a"), [0, 2]);
+
+  let book: Book = "
+    @b = #2{@d 1}
+    @d = (@a @c)
+  "
+  .parse()
+  .unwrap();
+  host.insert_book(&book);
+  assert_eq!(labs(&host, "a"), [0, 2, 5]);
+  assert_eq!(labs(&host, "b"), [0, 2, 5]);
+  assert_eq!(reduce(&host, "a & @b ~ #2{(* a) *}"), "#1(a a)");
+  assert_eq!(host.back.len(), host.defs.len());
+}
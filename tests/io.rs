@@ -0,0 +1,80 @@
+//! Tests for running programs with the IO defs.
+
+use std::{env, fs, io::Cursor, process};
+
+use hvm64_ast::{Book, Net, Tree};
+use hvm64_host::Host;
+use hvm64_runtime as run;
+use insta::assert_snapshot;
+
+fn run_io(book: &str, stdin: &str) -> (String, String) {
+  let mut host = Host::default();
+  host.insert_io();
+  host.insert_book(&book.parse::<Book>().unwrap());
+  let heap = run::Heap::new(Some(1 << 20)).unwrap();
+  let mut stdout = Vec::new();
+  let net = Net { root: Tree::Ref("main".to_owned()), redexes: vec![] };
+  let result = host
+    .run_io(&heap, net, &mut Cursor::new(stdin), &mut stdout, |net| net.normal())
+    .map_or_else(|e| e.to_string(), |r| r.to_string());
+  (result, String::from_utf8(stdout).unwrap())
+}
+
+#[test]
+fn test_io_done() {
+  assert_snapshot!(run_io("@main = a & @IO/done ~ (42 a)", "").0, @"42");
+  assert_snapshot!(run_io("@main = (a a)", "").0, @"expected an IO action, found (a a)");
+}
+
+#[test]
+fn test_io_echo() {
+  let book = "
+    @main = a & @IO/read_line ~ (* (@echo a))
+    @echo = (o a) & @unwrap ~ (o line) & @IO/print ~ (line ((* b) a)) & @IO/read_line ~ (* (@echo2 b))
+    @echo2 = (o a) & @unwrap ~ (o line) & @IO/print ~ (line ((* b) a)) & @IO/done ~ (0 b)
+    @unwrap = (o a) & o ~ ((l l) (* a))
+  ";
+  let (result, stdout) = run_io(book, "hello, λ\r\nworld\nignored\n");
+  assert_eq!(result, "0");
+  assert_eq!(stdout, "hello, λworld");
+}
+
+#[test]
+fn test_io_eof() {
+  // finishes with `1` if there is a line, and `0` at the end of the input
+  let book = "
+    @main = a & @IO/read_line ~ (* (@check a))
+    @check = (o a) & o ~ ((* b) (c a)) & @IO/done ~ (1 b) & @IO/done ~ (0 c)
+  ";
+  assert_eq!(run_io(book, "").0, "0");
+  assert_eq!(run_io(book, "\n").0, "1");
+  assert_eq!(run_io(book, "no newline").0, "1");
+}
+
+#[test]
+fn test_io_files() {
+  // reads a path and contents from stdin, writes the contents to the path, and
+  // then reads the path again and prints its contents
+  let book = "
+    @main = a & @IO/read_line ~ (* (@write a))
+    @write = (p a)
+      & @unwrap ~ (p path)
+      & @IO/read_line ~ (* ((c b) a))
+      & @unwrap ~ (c contents)
+      & @IO/write_file ~ ((path contents) ((* d) b))
+      & @IO/read_line ~ (* (@read d))
+    @read = (p a) & @unwrap ~ (p path) & @IO/read_file ~ (path (@print a))
+    @print = (contents a) & @IO/print ~ (contents ((* b) a)) & @IO/done ~ (0 b)
+    @unwrap = (line a) & line ~ ((l l) (* a))
+  ";
+  let path = env::temp_dir().join(format!("hvm64-test-io-{}", process::id()));
+  let path = path.to_str().unwrap();
+  let (result, stdout) = run_io(book, &format!("{path}\nfile contents\n{path}\n"));
+  assert_eq!(fs::read_to_string(path).unwrap(), "file contents");
+  fs::remove_file(path).unwrap();
+  assert_eq!(result, "0");
+  assert_eq!(stdout, "file contents");
+
+  let (result, _) = run_io(book, &format!("{path}/nonexistent/file\n\n"));
+  assert!(result.contains("No such file or directory"), "{result}");
+}
@@ -36,12 +36,12 @@ fn test_list_got() {
     rwts_list.push(rwts.total())
   }
 
-  assert_debug_snapshot!(rwts_list[0], @"594");
-  assert_debug_snapshot!(rwts_list[1], @"623");
-  assert_debug_snapshot!(rwts_list[2], @"681");
-  assert_debug_snapshot!(rwts_list[3], @"797");
-  assert_debug_snapshot!(rwts_list[4], @"1029");
-  assert_debug_snapshot!(rwts_list[5], @"1493");
+  assert_debug_snapshot!(rwts_list[0], @"462");
+  assert_debug_snapshot!(rwts_list[1], @"486");
+  assert_debug_snapshot!(rwts_list[2], @"534");
+  assert_debug_snapshot!(rwts_list[3], @"630");
+  assert_debug_snapshot!(rwts_list[4], @"822");
+  assert_debug_snapshot!(rwts_list[5], @"1206");
 
   // Tests the linearity of the function
   let delta = rwts_list[1] - rwts_list[0];
@@ -61,12 +61,12 @@ fn test_list_put() {
     rwts_list.push(rwts.total())
   }
 
-  assert_debug_snapshot!(rwts_list[0], @"585");
-  assert_debug_snapshot!(rwts_list[1], @"615");
-  assert_debug_snapshot!(rwts_list[2], @"675");
-  assert_debug_snapshot!(rwts_list[3], @"795");
-  assert_debug_snapshot!(rwts_list[4], @"1035");
-  assert_debug_snapshot!(rwts_list[5], @"1515");
+  assert_debug_snapshot!(rwts_list[0], @"453");
+  assert_debug_snapshot!(rwts_list[1], @"478");
+  assert_debug_snapshot!(rwts_list[2], @"528");
+  assert_debug_snapshot!(rwts_list[3], @"628");
+  assert_debug_snapshot!(rwts_list[4], @"828");
+  assert_debug_snapshot!(rwts_list[5], @"1228");
 
   //Tests the linearity of the function
   let delta = rwts_list[1] - rwts_list[0];
@@ -1,8 +1,7 @@
 #![allow(dead_code)]
 
-// use hvm64::{ast::*, run, stdlib::create_host};
+use hvm64::{Config, Runtime};
 use hvm64_ast::{Book, Net};
-use hvm64_host::Host;
 use hvm64_runtime as run;
 use std::fs;
 
@@ -26,15 +25,10 @@ pub fn replace_template(mut code: String, map: &[(&str, &str)]) -> String {
 }
 
 pub fn normal_with(book: Book, mem: Option<usize>, entry_point: &str) -> (run::Rewrites, Net) {
-  let area = run::Heap::new(mem).unwrap();
-  let host = Host::new(&book);
-
-  let mut rnet = run::Net::new(&area);
-  rnet.boot(&host.defs[entry_point]);
-  rnet.normal();
-
-  let net = host.readback(&rnet);
-  (rnet.rwts, net)
+  let config = Config { single_core: true, memory: mem, ..Config::default() };
+  let mut runtime = Runtime::new(book, config).unwrap();
+  let net = runtime.boot(entry_point).unwrap();
+  (*runtime.rewrites(), net)
 }
 
 pub fn normal(book: Book, mem: Option<usize>) -> (run::Rewrites, Net) {
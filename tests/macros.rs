@@ -0,0 +1,61 @@
+//! Tests for the `net!` and `book!` macros.
+
+use hvm64_ast::{book, net, Book, Net, Tree};
+use hvm64_num::Num;
+
+/// Asserts that `net!` reads each net, given as tokens, the same way as the
+/// parser reads it, given as a string; the two must only differ in whitespace.
+macro_rules! assert_parsers_agree {
+  ($($code:literal => ($($net:tt)*),)*) => {$({
+    let strip = |code: &str| code.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    assert_eq!(strip(stringify!($($net)*)), strip($code), "the inputs differ");
+    assert_eq!(net!($($net)*), $code.parse::<Net>().unwrap(), "{}", $code);
+  })*};
+}
+
+#[test]
+fn test_net_macro() {
+  assert_parsers_agree! {
+    "(a a)" => ((a a)),
+    "*" => (*),
+    "@foo/bar.baz$qux_1" => (@foo/bar.baz$qux_1),
+    "(a b) & (b c) ~ (c a)" => ((a b) & (b c) ~ (c a)),
+    "(a b c d) & c ~ (b #2{a d})" => ((a b c d) & c ~ (b #2{a d})),
+    "{a b c} & #3(a b c) ~ #3{* * *}" => ({a b c} & #3(a b c) ~ #3{* * *}),
+    "?((a a) $([+] $(1 b))) & b ~ @foo/bar.baz" => (?((a a) $([+] $(1 b))) & b ~ @foo/bar.baz),
+    "#x{a #1(a b)} & #y(b c) ~ #x{c *}" => (#x{a #1(a b)} & #y(b c) ~ #x{c *}),
+    "(1 +2 -3 1.5 -1.5 +inf -inf +NaN 0x10 0b101 -0x10)" => ((1 +2 -3 1.5 -1.5 +inf -inf +NaN 0x10 0b101 -0x10)),
+    "([+] [* 2] [:<< 1] [- -1] [/ 0x2] [u24] [i24] [f24] [+1] [-1] [= 1.5])" =>
+      (([+] [* 2] [:<< 1] [- -1] [/ 0x2] [u24] [i24] [f24] [+1] [-1] [= 1.5])),
+    "[1 \"hi\" [] [(a a) b] b [* *] [-1 2] [* 2 3] [[]]]" => ([1 "hi" [] [(a a) b] b [* *] [-1 2] [* 2 3] [[]]]),
+    "(\"a\\\"b\" \"λ\")" => (("a\"b" "λ")),
+  }
+}
+
+#[test]
+fn test_net_macro_interpolation() {
+  let index = 3;
+  let arg = Tree::Ref(format!("S{index}"));
+  assert_eq!(net!(a & @GenGotIndex ~ (#{arg} a)), "a & @GenGotIndex ~ (@S3 a)".parse().unwrap());
+  assert_eq!(net!((#{Num::new_u24(index)} *)), "(3 *)".parse().unwrap());
+}
+
+#[test]
+fn test_book_macro() {
+  let book = book! {
+    /// Doubles a number.
+    @double = ($([*] $(2 a)) a)
+
+    @main = a & @double ~ (#x{1 2} a)
+  };
+  let parsed: Book = "
+    /// Doubles a number.
+    @double = ($([*] $(2 a)) a)
+
+    @main = a & @double ~ (#x{1 2} a)
+  "
+  .parse()
+  .unwrap();
+  assert_eq!(book, parsed);
+  assert_eq!(book.to_string(), parsed.to_string());
+}
@@ -0,0 +1,101 @@
+//! Tests for defs implemented through the safe native def interface.
+
+use std::sync::Mutex;
+
+use hvm64_ast::Net;
+use hvm64_host::Host;
+use hvm64_num::{Num, NumTag};
+use hvm64_runtime::{self as run, Arg, Def, LabSet, Native, NativeDef, NativeNet};
+use insta::assert_snapshot;
+
+fn reduce(host: &Host, net: &str) -> String {
+  let heap = run::Heap::new(Some(1 << 16)).unwrap();
+  let mut rnet = run::Net::new(&heap);
+  let root = run::Trg::port(run::Port::new_var(rnet.root.addr()));
+  host.encode_net(&mut rnet, root, &net.parse::<Net>().unwrap());
+  rnet.normal();
+  host.readback(&rnet).to_string()
+}
+
+fn swap<'n>(net: &mut NativeNet<'n, '_>, arg: Arg<'n>) {
+  let Arg::Ctr { lab: 0, p1: pair, p2: out } = arg else { panic!("expected an application") };
+  let (a, b) = net.ctr(0, pair);
+  let (x, y) = net.ctr(0, out);
+  net.link(x, b);
+  net.link(y, a);
+}
+
+fn inc<'n>(net: &mut NativeNet<'n, '_>, arg: Arg<'n>) {
+  let Arg::Ctr { lab: 0, p1: x, p2: out } = arg else { panic!("expected an application") };
+  let x = net.op_num(NumTag::Add, x, Num::new_u24(1));
+  net.link(x, out);
+}
+
+#[derive(Default)]
+struct Record(Mutex<Vec<String>>);
+
+impl NativeDef for Record {
+  fn call<'n>(&self, net: &mut NativeNet<'n, '_>, arg: Arg<'n>) {
+    let kind = match arg {
+      Arg::Num(num) => format!("num {num}"),
+      Arg::Ctr { lab, p1, p2 } => {
+        net.link_era(p1);
+        net.link_era(p2);
+        format!("ctr {lab}")
+      }
+      Arg::Other(other) => {
+        net.link_era(other);
+        "other".to_owned()
+      }
+    };
+    self.0.lock().unwrap().push(kind);
+  }
+}
+
+/// Returns the def it holds, ignoring its argument.
+struct Get(&'static Def);
+
+impl NativeDef for Get {
+  fn call<'n>(&self, net: &mut NativeNet<'n, '_>, arg: Arg<'n>) {
+    let Arg::Ctr { lab: 0, p1: _, p2: out } = arg else { panic!("expected an application") };
+    net.link_ref(out, self.0);
+  }
+}
+
+#[test]
+fn test_native_defs() {
+  let mut host = Host::default();
+  host.insert_def("swap", Box::new(Def::new(LabSet::ALL, Native(swap))));
+  host.insert_native("inc", inc);
+
+  assert_snapshot!(reduce(&host, "a & @swap ~ ((1 2) a)"), @"(2 1)");
+  assert_snapshot!(reduce(&host, "a & @inc ~ (41 a)"), @"42");
+  assert_snapshot!(reduce(&host, "a & @swap ~ ((1 b) a) & @inc ~ (2 b)"), @"(3 1)");
+}
+
+#[test]
+fn test_native_args() {
+  let mut host = Host::default();
+  host.insert_native("record", Record::default());
+
+  assert_snapshot!(reduce(&host, "* & @record ~ 5"), @"*");
+  assert_snapshot!(reduce(&host, "(a a) & @record ~ {1 2}"), @"(a a)");
+  assert_snapshot!(reduce(&host, "* & @record ~ $(1 2)"), @"*");
+
+  let record = host.get_mut::<Native<Record>>("record");
+  assert_eq!(*record.data.0.0.lock().unwrap(), ["num 5", "ctr 1", "other"]);
+}
+
+#[test]
+fn test_native_unused_handles() {
+  let mut host = Host::default();
+  host.insert_native("drop", |_: &mut NativeNet, _: Arg| {});
+  assert_snapshot!(reduce(&host, "a & @drop ~ (1 a)"), @"*");
+}
+
+#[test]
+fn test_native_ref() {
+  let mut host = Host::default();
+  host.insert_native("get_inc", Get(Box::leak(Box::new(Def::new(LabSet::ALL, Native(inc))))));
+  assert_snapshot!(reduce(&host, "a & @get_inc ~ (* b) & b ~ (41 a)"), @"42");
+}
@@ -0,0 +1,108 @@
+//! Tests for the peephole optimizer of interpreted defs.
+
+use hvm64_ast::{Book, Net};
+use hvm64_host::Host;
+use hvm64_runtime::{self as run, Def, InterpretedDef, Port, Trg};
+use insta::assert_snapshot;
+
+/// The instructions of `@f`, unoptimized and optimized.
+fn disasm(code: &str) -> String {
+  let book: Book = code.parse().unwrap();
+  let mut host = Host::new(&book);
+  let optimized = host.disasm("f").unwrap().to_string();
+  let trgs = Def::downcast_ref::<InterpretedDef>(&host.defs["f"]).unwrap().data.trgs;
+  let data = host.encode_def(&book["f"]);
+  let unoptimized_trgs = data.trgs;
+  host.get_mut::<InterpretedDef>("f").data = data;
+  let unoptimized = host.disasm("f").unwrap().to_string();
+  format!("{unoptimized_trgs} trgs:\n{unoptimized}\n{trgs} trgs:\n{optimized}")
+}
+
+/// The normal form of `net` in `book`, with `@f` optimized and unoptimized.
+fn reduce(code: &str, net: &str) -> (String, String) {
+  let book: Book = code.parse().unwrap();
+  let net: Net = net.parse().unwrap();
+  let mut host = Host::new(&book);
+  let heap = run::Heap::new(None).unwrap();
+  let run = |host: &Host| {
+    let mut rnet = run::Net::new(&heap);
+    let root = Trg::port(Port::new_var(rnet.root.addr()));
+    host.encode_net(&mut rnet, root, &net);
+    rnet.normal();
+    host.readback(&rnet).to_string()
+  };
+  let optimized = run(&host);
+  host.get_mut::<InterpretedDef>("f").data = host.encode_def(&book["f"]);
+  (optimized, run(&host))
+}
+
+#[test]
+fn test_optimize() {
+  // an op whose rhs is linked to a number
+  assert_snapshot!(disasm("@f = (a b) & a ~ $([+] $(c b)) & c ~ 1"), @r###"
+  10 trgs:
+  ctr        t0 0 -> t1 t2
+  wires      -> t3 t4 t5 t6
+  link       t1 t3
+  op         t5 [+] -> t7 t8
+  link       t2 t8
+  link       t4 t6
+  const      1 -> t9
+  link       t7 t9
+
+  2 trgs:
+  ctr        t0 0 -> t0 t1
+  op_num     t0 [+] 1 -> t0
+  link       t1 t0
+  "###);
+  // a redex between a var and a tree
+  assert_snapshot!(disasm("@f = (a (b c)) & (b c) ~ a"), @r###"
+  10 trgs:
+  tup        t0 0 -> t1 t2 t3
+  wires      -> t4 t5 t6 t7
+  ctr        t4 0 -> t8 t9
+  link       t2 t8
+  link       t3 t9
+  link       t1 t6
+  link       t5 t7
+
+  4 trgs:
+  tup        t0 0 -> t0 t1 t2
+  ctr        t0 0 -> t0 t3
+  link       t1 t0
+  link       t2 t3
+  "###);
+  // a redex between a var and a ref
+  assert_snapshot!(disasm("@f = (a b) & @g ~ c & c ~ (a b)\n@g = *"), @r###"
+  10 trgs:
+  ctr        t0 0 -> t1 t2
+  const      @g -> t3
+  wires      -> t4 t5 t6 t7
+  link       t3 t4
+  ctr        t6 0 -> t8 t9
+  link       t1 t8
+  link       t2 t9
+  link       t5 t7
+
+  4 trgs:
+  ctr        t0 0 -> t0 t1
+  const      @g -> t2
+  ctr        t2 0 -> t2 t3
+  link       t0 t2
+  link       t1 t3
+  "###);
+}
+
+#[test]
+fn test_optimize_wires() {
+  // the wires of `c ~ (a b)` are linked to a constant through `c`
+  let code = "@f = (a b) & @g ~ c & c ~ (a b)\n@g = (x (y (x y)))";
+  let (optimized, unoptimized) = reduce(code, "a & @f ~ (1 (2 a))");
+  assert_eq!(optimized, unoptimized);
+  assert_snapshot!(optimized, @"(1 2)");
+  // constants linked within both sides of a redex between trees
+  let code = "@f = (a b) & (1 a) ~ (c d) & (c (2 d)) ~ (* b)";
+  let (optimized, unoptimized) = reduce(code, "a & @f ~ (* a)");
+  assert_eq!(optimized, unoptimized);
+  assert_snapshot!(optimized, @"(2 *)");
+}
@@ -0,0 +1,146 @@
+//! Tests for reading back runtime nets into ast nets.
+
+use hvm64_ast::{Net, Program};
+use hvm64_host::{decode_data, decode_lambda, Host};
+use hvm64_runtime::{self as run, Port, Tag};
+use insta::assert_snapshot;
+
+#[test]
+fn test_readback_redirects() {
+  let heap = run::Heap::new_exact(16).unwrap();
+  let host = Host::default();
+  let mut net = run::Net::new(&heap);
+
+  // `(a a)`, where the wire from the first aux port reaches the second through
+  // a redirected wire, as can be left behind by a parallel link race.
+  let node = net.create_node(Tag::Ctr, 0);
+  let (mid, _) = net.create_wire();
+  net.root.set_target(node.p0);
+  node.p1.wire().set_target(Port::new_var(mid.addr()));
+  mid.set_target(Port::new(Tag::Red, 0, node.p2.addr()));
+  node.p2.wire().set_target(node.p1.clone());
+
+  assert_snapshot!(host.readback(&net), @"(a a)");
+}
+
+#[test]
+fn test_readback_placeholder() {
+  let heap = run::Heap::new_exact(16).unwrap();
+  let host = Host::default();
+  let net = run::Net::new(&heap);
+
+  // a vicious circle can leave the root locked
+  net.root.set_target(Port::LOCK);
+
+  assert_snapshot!(host.readback(&net), @"a");
+}
+
+#[test]
+fn test_readback_checked_vicious_circle() {
+  let heap = run::Heap::new_exact(16).unwrap();
+  let host = Host::default();
+  let mut net = run::Net::new(&heap);
+
+  // `(a *)`, where `a` is connected to the aux port of a node whose principal
+  // port is connected to its own other aux port.
+  let root = net.create_node(Tag::Ctr, 0);
+  let circle = net.create_node(Tag::Ctr, 0);
+  net.root.set_target(root.p0);
+  root.p1.wire().set_target(circle.p2.clone());
+  root.p2.wire().set_target(Port::ERA);
+  circle.p1.wire().set_target(circle.p0);
+  circle.p2.wire().set_target(root.p1);
+
+  assert_snapshot!(host.readback(&net), @"(a *)");
+  let (out, diagnostics) = host.readback_checked(&net);
+  assert_snapshot!(out, @"(a *)");
+  assert!(!diagnostics.is_clean());
+  assert_snapshot!(diagnostics, @r###"
+  1 unreachable node(s) in 1 disconnected component(s):
+    b ~ (b a)
+  "###);
+}
+
+#[test]
+fn test_readback_checked_unbound() {
+  let heap = run::Heap::new_exact(16).unwrap();
+  let host = Host::default();
+  let mut net = run::Net::new(&heap);
+
+  let node = net.create_node(Tag::Ctr, 0);
+  let (dangling, _) = net.create_wire();
+  net.root.set_target(node.p0);
+  node.p1.wire().set_target(Port::new_var(dangling.addr()));
+  node.p2.wire().set_target(Port::ERA);
+  dangling.set_target(Port::FREE);
+
+  let (out, diagnostics) = host.readback_checked(&net);
+  assert_snapshot!(out, @"(a *)");
+  assert_snapshot!(diagnostics, @"unbound variable(s): a");
+
+  let node = net.create_node(Tag::Ctr, 0);
+  node.p1.wire().set_target(Port::ERA);
+  node.p2.wire().set_target(Port::ERA);
+  net.root.set_target(node.p0);
+  let (_, diagnostics) = host.readback_checked(&net);
+  assert!(diagnostics.is_clean());
+}
+
+#[test]
+fn test_readback_unknown_ref() {
+  let heap = run::Heap::new_exact(16).unwrap();
+  let other = Host::new(&"@foo = *".parse().unwrap());
+  let mut net = run::Net::new(&heap);
+  let root = run::Trg::port(Port::new_var(net.root.addr()));
+  other.encode_tree(&mut net, root, &"(@foo *)".parse().unwrap());
+
+  let out = Host::default().readback(&net);
+  assert_snapshot!(out, @"(@$unknown *)");
+  assert_eq!(out.to_string().parse::<Net>().unwrap(), out);
+}
+
+#[test]
+fn test_readback_limited() {
+  let heap = run::Heap::new_exact(64).unwrap();
+  let host = Host::default();
+  let mut net = run::Net::new(&heap);
+  let tree = "((a a) ((b b) (c c)))".parse().unwrap();
+  let root = run::Trg::port(Port::new_var(net.root.addr()));
+  host.encode_tree(&mut net, root, &tree);
+
+  let limited = |max_nodes, max_depth| {
+    let (out, elided) = host.readback_limited(&net, max_nodes, max_depth);
+    format!("{out}\n{elided}")
+  };
+  assert_snapshot!(limited(usize::MAX, usize::MAX), @"((a a) ((b b) (c c)))");
+  assert_snapshot!(limited(2, usize::MAX), @r###"
+  (@…1 (@…1 (a a)))
+  2 node(s) elided in 2 subtree(s)
+  "###);
+  assert_snapshot!(limited(usize::MAX, 2), @"((a a) ((b b) (c c)))");
+  assert_snapshot!(limited(0, usize::MAX), @r###"
+  @…5
+  5 node(s) elided in 1 subtree(s)
+  "###);
+}
+
+#[test]
+fn test_decode_lambda() {
+  let decode = |net: &Net| decode_lambda(net).unwrap().to_string();
+  let decode_data = |net: &Net| decode_data(decode_lambda(net).unwrap()).to_string();
+
+  let two = "(#2{(a b) (c a)} (c b))".parse().unwrap();
+  assert_snapshot!(decode(&two), @"λa λb (a (a b))");
+  assert_snapshot!(decode_data(&two), @"2");
+
+  let redex = "a & (b b) ~ (1 a)".parse().unwrap();
+  assert_snapshot!(decode(&redex), @"(λa a 1)");
+
+  let book = "main = [1 λx λy x λx x]".parse::<Program>().unwrap().to_book().unwrap();
+  assert_snapshot!(decode_data(&book.nets["main"]), @"[1 true λf f]");
+
+  let op = "($([+] $(1 a)) a)".parse().unwrap();
+  assert_snapshot!(decode(&op), @"λa (+ a 1)");
+
+  assert!(decode_lambda(&"?((a b) c)".parse().unwrap()).is_err());
+}
@@ -0,0 +1,52 @@
+//! Tests for the `hvm64::Runtime` embedding facade.
+#![cfg(feature = "std")]
+
+use hvm64::{Config, Error, Runtime};
+use insta::assert_snapshot;
+
+const BOOK: &str = "
+  @main = (a (b c)) & a ~ $([*] $(b c))
+  @loop = a & @loop ~ (* a)
+";
+
+fn config() -> Config {
+  Config { single_core: true, memory: Some(1 << 20), ..Config::default() }
+}
+
+#[test]
+fn test_runtime_eval() {
+  let mut runtime = Runtime::from_str(BOOK, config()).unwrap();
+  assert_snapshot!(runtime.apply("main", &["3", "4"]).unwrap(), @"12");
+  assert_snapshot!(runtime.eval("a & @main ~ (5 (6 a))").unwrap(), @"30");
+  assert_snapshot!(runtime.eval("(a a)").unwrap(), @"(a a)");
+  assert!(runtime.rewrites().total() > 0);
+}
+
+#[test]
+fn test_runtime_errors() {
+  let mut runtime = Runtime::from_str(BOOK, config()).unwrap();
+  assert_snapshot!(runtime.eval("a & @missing ~ a").unwrap_err(), @"reference to undefined def `@missing`");
+  assert!(matches!(runtime.eval("(a"), Err(Error::Parse(_))));
+
+  let mut runtime = Runtime::from_str(BOOK, Config { max_rewrites: Some(1000), ..config() }).unwrap();
+  assert_snapshot!(runtime.apply("loop", &[]).unwrap_err(), @"reduction exceeded the limit of 1000 rewrites");
+  assert_snapshot!(runtime.apply("main", &["3", "4"]).unwrap(), @"12");
+
+  assert_snapshot!(
+    Runtime::from_str("@main = (@missing @other)\n@other = @missing", config()).err().unwrap(),
+    @r###"
+  missing definitions:
+    @missing, referenced by @main, @other
+  "###
+  );
+  assert!(matches!(Runtime::from_file("nonexistent.hvm", config()), Err(Error::Io(_))));
+}
+
+#[test]
+fn test_runtime_max_output() {
+  let mut runtime = Runtime::from_str(BOOK, Config { max_output: Some(2), ..config() }).unwrap();
+  assert_snapshot!(runtime.eval("((a a) ((b b) (c c)))").unwrap(), @"((a a) @…3)");
+  assert_eq!(runtime.elided().subtrees, [3]);
+  assert_snapshot!(runtime.eval("(a a)").unwrap(), @"(a a)");
+  assert!(runtime.elided().is_empty());
+}
@@ -0,0 +1,82 @@
+//! Tests for the serde support of the AST.
+
+use hvm64_ast::{Book, Net, Tree};
+use hvm64_num::{Num, NumTag};
+use hvm64_runtime::Rewrites;
+use insta::assert_snapshot;
+use serde::Deserialize;
+
+fn roundtrip_num(num: Num) -> String {
+  let json = serde_json::to_string(&num).unwrap();
+  let back: Num = serde_json::from_str(&json).unwrap();
+  assert_eq!(back.to_string(), num.to_string());
+  json
+}
+
+#[test]
+fn test_serde_book() {
+  let book: Book = "
+    @main = (a a) & @foo ~ $([+] 1)
+    @foo = #2{?((1 *) x) x}
+  "
+  .parse()
+  .unwrap();
+  let json = serde_json::to_string(&book).unwrap();
+  assert_snapshot!(json, @r###"{"nets":{"foo":{"root":{"ctr":{"lab":5,"p1":{"switch":{"arms":{"ctr":{"lab":0,"p1":{"num":{"u24":1}},"p2":"era"}},"out":{"var":"x"}}},"p2":{"var":"x"}}},"redexes":[]},"main":{"root":{"ctr":{"lab":0,"p1":{"var":"a"},"p2":{"var":"a"}}},"redexes":[[{"ref":"foo"},{"op":{"rhs":{"num":{"sym":"+"}},"out":{"num":{"u24":1}}}}]]}},"docs":{},"imports":[],"labels":{}}"###);
+  assert_eq!(serde_json::from_str::<Book>(&json).unwrap(), book);
+}
+
+#[test]
+fn test_serde_book_metadata() {
+  let book: Book = "
+    @import \"lib.hvm\" as lib
+    /// Duplicates a number.
+    @main = a & @lib/num ~ #dup_x{a *}
+  "
+  .parse()
+  .unwrap();
+  let json = serde_json::to_string(&book).unwrap();
+  assert_snapshot!(json, @r###"{"nets":{"main":{"root":{"var":"a"},"redexes":[[{"ref":"lib/num"},{"ctr":{"lab":3,"p1":{"var":"a"},"p2":"era"}}]]}},"docs":{"main":"/// Duplicates a number."},"imports":[{"path":"lib.hvm","ns":"lib","doc":""}],"labels":{"dup_x":1}}"###);
+  let back = serde_json::from_str::<Book>(&json).unwrap();
+  assert_eq!(back, book);
+  assert_eq!(back.to_string(), book.to_string());
+
+  let book = serde_json::from_str::<Book>(r#"{"nets": {"main": {"root": "era", "redexes": []}}}"#).unwrap();
+  assert_snapshot!(book, @"@main = *");
+}
+
+#[test]
+fn test_serde_num() {
+  assert_snapshot!(roundtrip_num(Num::new_u24(16777215)), @r###"{"u24":16777215}"###);
+  assert_snapshot!(roundtrip_num(Num::new_i24(-5)), @r###"{"i24":-5}"###);
+  assert_snapshot!(roundtrip_num(Num::new_f24(1.5)), @r###"{"f24":1.5}"###);
+  assert_snapshot!(roundtrip_num(Num::new_f24(f32::NEG_INFINITY)), @r###"{"f24":"-inf"}"###);
+  assert_snapshot!(roundtrip_num(Num::new_f24(f32::NAN)), @r###"{"f24":"+NaN"}"###);
+  assert_snapshot!(roundtrip_num(Num::new_sym(NumTag::ShlS)), @r###"{"sym":":<<"}"###);
+  assert_snapshot!(roundtrip_num(Num::new_sym(NumTag::F24)), @r###"{"sym":"f24"}"###);
+  assert_snapshot!(roundtrip_num(Num::operate_sym(Num::new_sym(NumTag::Sub), Num::new_u24(3))), @r###"{"-":3}"###);
+
+  assert_eq!(serde_json::from_str::<Num>(r#"{"f24": 2}"#).unwrap().to_string(), "2.0");
+  assert_snapshot!(serde_json::from_str::<Num>(r#"{"u24": 16777216}"#).unwrap_err(), @"invalid value: integer `16777216`, expected a 24-bit unsigned integer");
+  assert_snapshot!(serde_json::from_str::<Num>(r#"{"i24": 8388608}"#).unwrap_err(), @"invalid value: integer `8388608`, expected a 24-bit signed integer");
+  assert_snapshot!(serde_json::from_str::<Num>(r#"{"u25": 1}"#).unwrap_err(), @r###"unknown variant `u25`, expected one of `u24`, `i24`, `f24`, `sym`, `+`, `-`, `:-`, `*`, `/`, `:/`, `%`, `:%`, `&`, `|`, `^`, `<<`, `:<<`, `>>`, `:>>`, `=`, `!`, `<`, `>` at line 1 column 6"###);
+}
+
+#[test]
+fn test_serde_deep_tree() {
+  let mut tree = Tree::Era;
+  for _ in 0 .. 100_000 {
+    tree = Tree::Ctr { lab: 0, p1: Box::new(tree), p2: Box::new(Tree::Era) };
+  }
+  let net = Net { root: tree, redexes: vec![] };
+  let json = serde_json::to_string(&net).unwrap();
+  let mut de = serde_json::Deserializer::from_str(&json);
+  de.disable_recursion_limit();
+  assert_eq!(Net::deserialize(&mut de).unwrap().to_string(), net.to_string());
+}
+
+#[test]
+fn test_serde_rewrites() {
+  let rwts = Rewrites { anni: 1, comm: 2, eras: 3, dref: 4, oper: 5 };
+  assert_snapshot!(serde_json::to_string(&rwts).unwrap(), @r###"{"anni":1,"comm":2,"eras":3,"dref":4,"oper":5}"###);
+}
@@ -5,7 +5,7 @@ use std::{fs, path::Path};
 
 use hvm64_ast::{self as ast, Book, Net};
 use hvm64_host::Host;
-use hvm64_runtime as run;
+use hvm64_runtime::{self as run, InterpretedDef};
 
 use insta::assert_snapshot;
 
@@ -31,7 +31,14 @@ fn execute_host(host: &Host) -> Option<(run::Rewrites, Net)> {
   let entrypoint = host.defs.get("main").unwrap();
   net.boot(entrypoint);
   net.parallel_normal();
-  Some((net.rwts, host.readback(&net)))
+  let readback = host.readback(&net);
+  let mut streamed = Vec::new();
+  host.readback_to(&net, &mut streamed).unwrap();
+  assert_eq!(String::from_utf8(streamed).unwrap(), readback.to_string());
+  let mut canonical = readback.clone();
+  canonical.canonicalize();
+  assert_eq!(canonical, readback);
+  Some((net.rwts, readback))
 }
 
 fn test_run(host: &Host) {
@@ -61,4 +68,18 @@ fn test_path(path: &Path) {
 
   test_pre_reduce_run(book.clone());
   test_run(&host);
+  test_unoptimized_run(&book, &host);
+}
+
+/// Checks that running the program without optimizing its defs gives the same
+/// result.
+fn test_unoptimized_run(book: &Book, host: &Host) {
+  let mut unoptimized = Host::new(book);
+  for (name, net) in book.iter() {
+    let data = unoptimized.encode_def(net);
+    unoptimized.get_mut::<InterpretedDef>(name).data = data;
+  }
+  let Some((_, expected)) = execute_host(host) else { return };
+  let Some((_, net)) = execute_host(&unoptimized) else { return };
+  assert!(net.alpha_eq(&expected), "{net}\n{expected}");
 }
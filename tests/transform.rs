@@ -30,16 +30,16 @@ pub fn test_fast_pre_reduce() {
 
   assert_snapshot!(format!("Fast:\n{rwts_1}Slow:\n{rwts_2}"), @r###"
   Fast:
-  RWTS   :          33_236
-  - ANNI :           4_385
-  - COMM :          11_725
+  RWTS   :          33_229
+  - ANNI :           4_380
+  - COMM :          11_723
   - ERAS :           1_598
   - DREF :          15_528
   - OPER :               0
   Slow:
-  RWTS   :          50_951
-  - ANNI :           8_763
-  - COMM :          23_450
+  RWTS   :          50_940
+  - ANNI :           8_756
+  - COMM :          23_446
   - ERAS :           3_196
   - DREF :          15_542
   - OPER :               0
@@ -138,3 +138,27 @@ pub fn test_prune() {
   @main = (@main (@a @b))
   "###);
 }
+
+#[test]
+pub fn test_transform_keeps_docs() {
+  let mut book = Book::from_str(
+    "
+    // The entry point.
+    @main = a & @double ~ (21 a)
+
+    /// Doubles a number.
+    /// (It's unused after pre-reducing.)
+    @double = ($([*] $(2 a)) a)
+
+    // Never used.
+    @unused = *
+    ",
+  )
+  .unwrap();
+  book.pre_reduce(&|_| false, None, u64::MAX);
+  book.prune(&["main".to_owned()]);
+  assert_snapshot!(book, @r###"
+  // The entry point.
+  @main = 42
+  "###);
+}
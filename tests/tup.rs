@@ -0,0 +1,69 @@
+//! Tests for native n-ary combinators, which nested constructors with the same
+//! label are encoded as.
+
+use hvm64_host::Host;
+use hvm64_runtime as run;
+use insta::assert_snapshot;
+
+/// Reduces `net`, returning the rewrites it took and its readback.
+fn reduce(net: &str) -> (run::Rewrites, String) {
+  let host = Host::default();
+  let heap = run::Heap::new(Some(1 << 16)).unwrap();
+  let mut rnet = run::Net::new(&heap);
+  let root = run::Trg::port(run::Port::new_var(rnet.root.addr()));
+  host.encode_net(&mut rnet, root, &net.parse().unwrap());
+  rnet.normal();
+  let out = host.readback(&rnet).to_string();
+  let mut streamed = vec![];
+  host.readback_to(&rnet, &mut streamed).unwrap();
+  assert_eq!(String::from_utf8(streamed).unwrap(), out);
+  (rnet.rwts, out)
+}
+
+#[test]
+fn test_tup_anni() {
+  let (rwts, out) = reduce("(a b c d e) & (1 2 3 4 5) ~ (a b c d e)");
+  assert_snapshot!(out, @"(1 (2 (3 (4 5))))");
+  assert_eq!(rwts.anni, 1);
+
+  // tuples of different arities, and a tuple and a binary constructor, still
+  // annihilate in one interaction
+  let (rwts, out) = reduce("(a b c) & (1 2 3 4 5) ~ (a b c)");
+  assert_snapshot!(out, @"(1 (2 (3 (4 5))))");
+  assert_eq!(rwts.anni, 1);
+  let (rwts, out) = reduce("(a b) & (a b) ~ (1 2 3 4)");
+  assert_snapshot!(out, @"(1 (2 (3 4)))");
+  assert_eq!(rwts.anni, 1);
+}
+
+#[test]
+fn test_tup_comm() {
+  let (rwts, out) = reduce("(a b c d) & * ~ (a b c d)");
+  assert_snapshot!(out, @"(* (* (* *)))");
+  assert_eq!((rwts.comm, rwts.eras), (1, 0));
+
+  // other interactions see the binary constructors a tuple is equivalent to
+  let (rwts, out) = reduce("(a b) & #2{a b} ~ (1 2 3)");
+  assert_snapshot!(out, @"((1 (2 3)) (1 (2 3)))");
+  assert_eq!((rwts.anni, rwts.comm), (0, 5));
+  let (_, out) = reduce("a & #2{1 2 3} ~ (* a)");
+  assert_snapshot!(out, @"#2{1 #2{2 3}}");
+}
+
+#[test]
+fn test_tup_readback() {
+  let (_, out) = reduce("(#2{b c d} {e f} (d c b) e f)");
+  assert_snapshot!(out, @"(#2{a #2{b c}} ({d e} ((c (b a)) (d e))))");
+
+  let host = Host::default();
+  let heap = run::Heap::new(Some(1 << 16)).unwrap();
+  let mut rnet = run::Net::new(&heap);
+  let root = run::Trg::port(run::Port::new_var(rnet.root.addr()));
+  host.encode_tree(&mut rnet, root, &"((a a) b c (d d) c b)".parse().unwrap());
+  let (out, elided) = host.readback_limited(&rnet, usize::MAX, 1);
+  assert_snapshot!(out, @"(@…1 (a (b (@…1 (b a)))))");
+  assert_eq!(elided.subtrees, [1, 1]);
+  let (out, elided) = host.readback_limited(&rnet, 0, usize::MAX);
+  assert_snapshot!(out, @"@…7");
+  assert_eq!(elided.subtrees, [7]);
+}
@@ -0,0 +1,92 @@
+//! Tests for converting Rust values to and from nets.
+
+use core::fmt::Debug;
+
+use hvm64_ast::Book;
+use hvm64_host::{Encoding, FromNet, Host, IntoNet, ValueEncoder};
+use hvm64_runtime as run;
+use insta::assert_snapshot;
+
+#[derive(Debug, Clone, PartialEq, IntoNet, FromNet)]
+struct Point {
+  x: i32,
+  y: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, IntoNet, FromNet)]
+enum Shape<T> {
+  Empty,
+  Circle(Point, T),
+  Polygon { points: Vec<Point> },
+}
+
+fn host() -> Host {
+  let book: Book = "
+    @id = (x x)
+    @add = (a (b c)) & a ~ $([+] $(b c))
+    @sum = (list out) & list ~ (@add (0 out))
+  "
+  .parse()
+  .unwrap();
+  Host::new(&book)
+}
+
+fn roundtrip<T: IntoNet + FromNet + Clone + PartialEq + Debug>(host: &Host, heap: &run::Heap, value: T) {
+  for encoding in [Encoding::Scott, Encoding::Church] {
+    assert_eq!(host.call_in::<T>(heap, encoding, "id", (value.clone(),)).unwrap(), value);
+  }
+}
+
+fn encode(encoding: Encoding, value: impl IntoNet) -> String {
+  match ValueEncoder::new(encoding).encode(value) {
+    Ok(tree) => tree.to_string(),
+    Err(err) => err.to_string(),
+  }
+}
+
+#[test]
+fn test_value_encoding() {
+  assert_snapshot!(encode(Encoding::Scott, Some(1u32)), @"((1 v1) (* v1))");
+  assert_snapshot!(encode(Encoding::Scott, (1u32, -2i32)), @"((1 (-2 v1)) v1)");
+  assert_snapshot!(encode(Encoding::Scott, true), @"(v1 (* v1))");
+  assert_snapshot!(encode(Encoding::Scott, "hi"), @"((104 (((105 ((* (v1 v1)) v2)) (* v2)) v3)) (* v3))");
+  assert_snapshot!(encode(Encoding::Church, vec![1u32, 2, 3]), @"({(1 (v2 v1)) {(2 (v3 v2)) (3 (v4 v3))}} (v4 v1))");
+  assert_snapshot!(encode(Encoding::Church, Vec::<u32>::new()), @"(* (v1 v1))");
+  assert_snapshot!(encode(Encoding::Scott, 0xFFFFFFu64), @"16777215");
+  assert_snapshot!(encode(Encoding::Scott, 0x1000000u64), @"expected a 24-bit u64, found 16777216");
+  assert_snapshot!(encode(Encoding::Scott, -0x800000i64), @"-8388608");
+  assert_snapshot!(encode(Encoding::Scott, Some(-0x800001i64)), @"expected a 24-bit i64, found -8388609");
+  assert_snapshot!(encode(Encoding::Scott, 0.25f64), @"0.25");
+}
+
+#[test]
+fn test_value_roundtrip() {
+  let host = host();
+  let heap = run::Heap::new(Some(1 << 20)).unwrap();
+  roundtrip(&host, &heap, 42u32);
+  roundtrip(&host, &heap, -7i16);
+  roundtrip(&host, &heap, 1.5f32);
+  roundtrip(&host, &heap, -0.75f64);
+  roundtrip(&host, &heap, (true, false, ()));
+  roundtrip(&host, &heap, Some(Some(3u8)));
+  roundtrip(&host, &heap, None::<u8>);
+  roundtrip(&host, &heap, vec![vec![1u32], vec![], vec![2, 3]]);
+  roundtrip(&host, &heap, "hello, λ".to_owned());
+  roundtrip(&host, &heap, Shape::<u32>::Empty);
+  roundtrip(&host, &heap, Shape::Circle(Point { x: 1, y: -1 }, "r".to_owned()));
+  roundtrip(&host, &heap, Shape::<()>::Polygon { points: vec![Point { x: 0, y: 0 }, Point { x: 3, y: 4 }] });
+}
+
+#[test]
+fn test_value_call() {
+  let host = host();
+  assert_eq!(host.call::<u32>("add", (1u32, 2u32)).unwrap(), 3);
+  assert_eq!(host.call::<u64>("add", (1u64 << 23, 5u64)).unwrap(), (1 << 23) + 5);
+  let heap = run::Heap::new(Some(1 << 20)).unwrap();
+  assert_eq!(host.call_in::<u32>(&heap, Encoding::Church, "sum", (vec![1u32, 2, 3, 4],)).unwrap(), 10);
+  assert_snapshot!(host.call::<bool>("id", (5u32,)).unwrap_err(), @"expected bool, found 5");
+  assert_snapshot!(host.call::<u8>("id", (300u32,)).unwrap_err(), @"expected u8, found 300");
+  assert_snapshot!(host.call::<Point>("id", (Some(1u32),)).unwrap_err(), @"expected Point, found ((1 a) (* a))");
+  assert_snapshot!(host.call::<u32>("id", (u32::MAX,)).unwrap_err(), @"expected a 24-bit u32, found 4294967295");
+  assert_snapshot!(host.call::<u32>("missing", ()).unwrap_err(), @"there is no def named `@missing`");
+}
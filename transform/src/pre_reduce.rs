@@ -156,7 +156,8 @@ impl<'a> State<'a> {
     let net = self.host.readback(&rt);
 
     // Mutate the host in-place with the pre-reduced net.
-    let instr = self.host.encode_def(&net);
+    let mut instr = self.host.encode_def(&net);
+    instr.optimize();
     self.host.get_mut::<InterpretedDef>(name).data = instr;
 
     // Replace the "Cycled" state with the "Reduced" state
@@ -16,6 +16,7 @@ impl Prune for Book {
     let unvisited = state.unvisited;
     for name in unvisited {
       self.remove(&name);
+      self.docs.remove(&name);
     }
   }
 }
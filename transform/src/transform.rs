@@ -93,7 +93,7 @@ impl TransformOpts {
 
 macro_rules! transform_passes {
   ($($pass:ident),* $(,)?) => {
-    #[derive(Debug, Default, Clone, Copy)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
     #[non_exhaustive]
     pub struct TransformPasses {
       $(pub $pass: bool),*